@@ -14,6 +14,9 @@ pub struct Args {
     pub generate_default_settings: bool,
     #[arg(short, long)]
     pub ignore_user_settings: bool,
+    /// Checks the configuration files, listing every problem found in them
+    #[arg(long)]
+    pub check_config: bool,
 }
 
 
@@ -30,6 +33,16 @@ impl Args {
             crate::models::settings::colors::EditorColors::create_default_config_file().expect("Could not create default config file");
             performed_command = true;
         }
+        if self.check_config {
+            let problems = crate::models::settings::check_config();
+            for problem in &problems {
+                println!("{}", problem);
+            }
+            if problems.is_empty() {
+                println!("No problems found");
+            }
+            std::process::exit(if problems.is_empty() { 0 } else { 1 });
+        }
         if performed_command {
             std::process::exit(0);
         }
@@ -7,8 +7,10 @@ use tuirealm::tui::layout::Rect;
 use tuirealm::tui::prelude::Text;
 use tuirealm::tui::widgets::Paragraph;
 use crate::models::{AppEvent, Message};
+use crate::models::file::File;
 use crate::models::pane::{Pane, TextPane};
 use crate::models::pane::text::TextBuffer;
+use crate::models::style::StyledLine;
 use crate::models::text_buffer::BufferText;
 
 pub struct Buffer {
@@ -48,6 +50,14 @@ impl Buffer{
     }
 }
 
+/// Returns the row of a byte and the column that it is displayed at, where tabs are `tab_size` columns wide.
+fn display_position(file: &File, byte: usize, tab_size: usize) -> Option<(usize, usize)> {
+    let (col, row) = file.get_cursor(byte)?;
+    let line = file.get_line(row)?;
+    let col = line.get(..col)?.chars().map(|c| if c == '\t' { tab_size } else { 1 }).sum();
+    Some((row, col))
+}
+
 impl MockComponent for Buffer {
     fn view(&mut self, frame: &mut Frame, area: Rect) {
         if self.props.get_or(Attribute::Display, AttrValue::Flag(true)) == AttrValue::Flag(true) {
@@ -75,13 +85,47 @@ impl MockComponent for Buffer {
                 let (_, offset) = pane.get_scroll_amount().unwrap_or((0, 0));
                 let start = offset;
                 let end = offset + area.height as usize;
-                let text = pane.draw_section(start, end);
+                let mut text = pane.draw_section(start, end);
                 //let text = pane.draw();
+                let mut rows = pane.borrow_current_file().get_visible_rows(start, end - start + 1);
 
                 let (_, row) = pane.get_cursor();
 
                 let settings = pane.get_settings();
 
+                // Highlight the pair at the cursor and its match
+                let file = pane.borrow_current_file();
+                let tab_size = file.get_tab_size() as usize;
+                let (col, _) = pane.get_cursor();
+                if let Some(pairs) = file.get_byte_offset(row, col).and_then(|byte| file.get_matching_pair(byte)) {
+                    for range in [pairs.0, pairs.1] {
+                        let Some((start_row, start_col)) = display_position(file, range.start, tab_size) else {
+                            continue;
+                        };
+                        let end_col = display_position(file, range.end, tab_size).map_or(start_col + 1, |(_, col)| col);
+                        if let Some(index) = rows.iter().position(|r| *r == start_row) {
+                            if let Some(line) = text.lines.get_mut(index) {
+                                line.patch_style_range(start_col, end_col.max(start_col + 1), settings.borrow().colors.matching_pair);
+                            }
+                        }
+                    }
+                }
+
+                // Pin the headers of the enclosing scopes over the top rows
+                let sticky_scroll = settings.borrow().editor_settings.sticky_scroll.min(area.height as usize / 2);
+                let sticky_rows = pane.borrow_current_file().get_sticky_rows(start, sticky_scroll);
+                for (index, sticky_row) in sticky_rows.into_iter().enumerate() {
+                    if index >= text.lines.len() || index >= rows.len() {
+                        break;
+                    }
+                    let mut line = pane.draw_section(sticky_row, sticky_row).lines.drain(..).next().unwrap_or_else(StyledLine::new);
+                    for span in line.spans.iter_mut() {
+                        span.patch_style(settings.borrow().colors.sticky_scroll);
+                    }
+                    text.lines[index] = line;
+                    rows[index] = sticky_row;
+                }
+
                 let number_line_type = {
                     let settings = settings.borrow();
                     settings.editor_settings.number_line
@@ -91,6 +135,7 @@ impl MockComponent for Buffer {
                     .add_number_line_style(number_line_type)
                     .add_current_row(row)
                     .set_start_row(start)
+                    .set_rows(rows)
                     .set_scroll_cols(self.scroll.1 as usize);
 
                 let text = text_buffer.draw(text);
@@ -0,0 +1,88 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use tuirealm::{Attribute, AttrValue, Component, Event, Frame, MockComponent, State};
+use tuirealm::command::{Cmd, CmdResult};
+use tuirealm::tui::layout::Rect;
+use tuirealm::tui::text::Text;
+use tuirealm::tui::widgets::Paragraph;
+use crate::models::{AppEvent, Message};
+use crate::models::pane::{Pane, TextPane};
+use crate::models::pane::text::TextBuffer;
+use crate::models::style::{StyledLine, StyledSpan, StyledText};
+
+/// The column to the left of the buffer that marks the rows with syntax errors.
+pub struct Gutter {
+    pane: Rc<RefCell<TextBuffer>>,
+}
+
+impl Gutter {
+    pub fn new(pane: Rc<RefCell<TextBuffer>>) -> Self {
+        Self {
+            pane,
+        }
+    }
+}
+
+impl MockComponent for Gutter {
+    fn view(&mut self, frame: &mut Frame, area: Rect) {
+        if area.width == 0 {
+            return;
+        }
+
+        let pane = self.pane.clone();
+        let pane = pane.borrow();
+        let (_, offset) = pane.get_scroll_amount().unwrap_or((0, 0));
+
+        let file = pane.borrow_current_file();
+        let error_rows = file.get_syntax_error_rows();
+        let mut rows = file.get_visible_rows(offset, area.height as usize);
+
+        let settings = pane.get_settings();
+        let settings = settings.borrow();
+
+        let sticky_scroll = settings.editor_settings.sticky_scroll.min(area.height as usize / 2);
+        for (index, sticky_row) in file.get_sticky_rows(offset, sticky_scroll).into_iter().enumerate() {
+            if let Some(row) = rows.get_mut(index) {
+                *row = sticky_row;
+            }
+        }
+
+        let mut text = StyledText::new();
+        for row in rows {
+            let mark = if error_rows.contains(&row) {
+                StyledSpan::styled("E", settings.colors.syntax_error_mark)
+            } else {
+                StyledSpan::from(" ")
+            };
+            text.lines.push(StyledLine::from(vec![mark]));
+        }
+
+        let text: Text = text.into();
+        frame.render_widget(
+            Paragraph::new(text),
+            area,
+        );
+    }
+
+    fn query(&self, _attr: Attribute) -> Option<AttrValue> {
+        None
+    }
+
+    fn attr(&mut self, _attr: Attribute, _value: AttrValue) {
+
+    }
+
+    fn state(&self) -> State {
+        State::None
+    }
+
+    fn perform(&mut self, _cmd: Cmd) -> CmdResult {
+        CmdResult::None
+    }
+}
+
+impl Component<Message, AppEvent> for Gutter {
+    fn on(&mut self, _ev: Event<AppEvent>) -> Option<Message> {
+        None
+    }
+}
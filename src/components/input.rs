@@ -65,6 +65,9 @@ impl Component<Message, AppEvent> for InputLayer {
             Event::User(AppEvent::CreateHelpFile) => {
                 Some(Message::OpenHelpFile)
             }
+            Event::User(AppEvent::OpenBindings(mode)) => {
+                Some(Message::OpenBindings(mode))
+            }
             _ => None,
         }
     }
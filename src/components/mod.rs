@@ -2,4 +2,6 @@ pub mod buffer;
 pub mod gutter;
 pub mod number_line;
 pub mod status_bar;
-pub mod input;
\ No newline at end of file
+pub mod input;
+pub mod outline;
+pub mod picker;
@@ -0,0 +1,92 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use tuirealm::{Attribute, AttrValue, Component, Event, Frame, MockComponent, State};
+use tuirealm::command::{Cmd, CmdResult};
+use tuirealm::tui::layout::Rect;
+use tuirealm::tui::text::Text;
+use tuirealm::tui::widgets::{Block, Borders, Paragraph};
+use crate::models::{AppEvent, Message};
+use crate::models::pane::{Pane, TextPane};
+use crate::models::pane::text::TextBuffer;
+use crate::models::style::{StyledLine, StyledSpan, StyledText};
+
+/// A side pane that lists the symbols of the current file and highlights the one under the cursor.
+pub struct Outline {
+    pane: Rc<RefCell<TextBuffer>>,
+}
+
+impl Outline {
+    pub fn new(pane: Rc<RefCell<TextBuffer>>) -> Self {
+        Self {
+            pane,
+        }
+    }
+}
+
+impl MockComponent for Outline {
+    fn view(&mut self, frame: &mut Frame, area: Rect) {
+        if area.width == 0 {
+            return;
+        }
+
+        let pane = self.pane.clone();
+        let pane = pane.borrow();
+        let file = pane.borrow_current_file();
+
+        let symbols = file.get_symbols();
+        let current = file.get_symbol_at(pane.get_current_byte_position());
+
+        let settings = pane.get_settings();
+        let settings = settings.borrow();
+
+        // Keep the current symbol in the middle of the pane
+        let height = area.height as usize;
+        let start = match current {
+            Some(current) => current.saturating_sub(height / 2).min(symbols.len().saturating_sub(height)),
+            None => 0,
+        };
+
+        let mut text = StyledText::new();
+        for (index, symbol) in symbols.iter().enumerate().skip(start).take(height) {
+            let entry = format!("{}{} {}", "  ".repeat(symbol.depth), symbol.kind, symbol.name);
+            let span = if Some(index) == current {
+                StyledSpan::styled(entry, settings.colors.selected)
+            } else {
+                StyledSpan::from(entry)
+            };
+            text.lines.push(StyledLine::from(vec![span]));
+        }
+        if symbols.is_empty() {
+            text.lines.push(StyledLine::from(vec![StyledSpan::styled("No symbols", settings.colors.folded)]));
+        }
+
+        let text: Text = text.into();
+        frame.render_widget(
+            Paragraph::new(text)
+                .block(Block::default().borders(Borders::LEFT)),
+            area,
+        );
+    }
+
+    fn query(&self, _attr: Attribute) -> Option<AttrValue> {
+        None
+    }
+
+    fn attr(&mut self, _attr: Attribute, _value: AttrValue) {
+
+    }
+
+    fn state(&self) -> State {
+        State::None
+    }
+
+    fn perform(&mut self, _cmd: Cmd) -> CmdResult {
+        CmdResult::None
+    }
+}
+
+impl Component<Message, AppEvent> for Outline {
+    fn on(&mut self, _ev: Event<AppEvent>) -> Option<Message> {
+        None
+    }
+}
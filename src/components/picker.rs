@@ -0,0 +1,86 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use tuirealm::{Attribute, AttrValue, Component, Event, Frame, MockComponent, State};
+use tuirealm::command::{Cmd, CmdResult};
+use tuirealm::tui::layout::Rect;
+use tuirealm::tui::text::Text;
+use tuirealm::tui::widgets::{Block, Borders, Clear, Paragraph};
+use crate::models::{AppEvent, Message};
+use crate::models::pane::Pane;
+use crate::models::pane::text::TextBuffer;
+use crate::models::style::{StyledLine, StyledSpan, StyledText};
+
+/// The most entries that a picker shows at once
+const PICKER_HEIGHT: usize = 12;
+
+/// A list drawn over the bottom of the buffer while a picker mode is active.
+pub struct Picker {
+    pane: Rc<RefCell<TextBuffer>>,
+}
+
+impl Picker {
+    pub fn new(pane: Rc<RefCell<TextBuffer>>) -> Self {
+        Self {
+            pane,
+        }
+    }
+}
+
+impl MockComponent for Picker {
+    fn view(&mut self, frame: &mut Frame, area: Rect) {
+        let pane = self.pane.clone();
+        let pane = pane.borrow();
+        let Some((title, items, selected)) = pane.get_picker() else {
+            return;
+        };
+
+        let settings = pane.get_settings();
+        let settings = settings.borrow();
+
+        let rows = items.len().clamp(1, PICKER_HEIGHT).min(area.height.saturating_sub(2) as usize);
+        let start = selected.map_or(0, |selected| (selected + 1).saturating_sub(rows));
+
+        let mut text = StyledText::new();
+        for (index, item) in items.iter().enumerate().skip(start).take(rows) {
+            let span = if Some(index) == selected {
+                StyledSpan::styled(item.clone(), settings.colors.selected)
+            } else {
+                StyledSpan::from(item.clone())
+            };
+            text.lines.push(StyledLine::from(vec![span]));
+        }
+
+        let height = rows as u16 + 2;
+        let area = Rect::new(area.x, area.y + area.height.saturating_sub(height), area.width, height.min(area.height));
+
+        let text: Text = text.into();
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(text)
+                .block(Block::default().borders(Borders::ALL).title(title)),
+            area,
+        );
+    }
+
+    fn query(&self, _attr: Attribute) -> Option<AttrValue> {
+        None
+    }
+
+    fn attr(&mut self, _attr: Attribute, _value: AttrValue) {
+
+    }
+
+    fn state(&self) -> State {
+        State::None
+    }
+
+    fn perform(&mut self, _cmd: Cmd) -> CmdResult {
+        CmdResult::None
+    }
+}
+
+impl Component<Message, AppEvent> for Picker {
+    fn on(&mut self, _ev: Event<AppEvent>) -> Option<Message> {
+        None
+    }
+}
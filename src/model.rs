@@ -12,6 +12,9 @@ use std::sync::mpsc::{Receiver, Sender};
 use tuirealm::listener::{ListenerResult, Poll};
 use crate::components::status_bar::StatusBar;
 use crate::components::buffer::Buffer;
+use crate::components::gutter::Gutter;
+use crate::components::outline::Outline;
+use crate::components::picker::Picker;
 use crate::components::input::InputLayer;
 
 
@@ -22,6 +25,9 @@ use crate::models::pane::{Pane, TextPane};
 use crate::models::pane::text::TextBuffer;
 use crate::threads::registers::RegisterMessage;
 
+/// The widest that the outline pane gets
+const OUTLINE_WIDTH: u16 = 40;
+
 pub struct AppEventPort{
     pub receiver: Receiver<AppEvent>,
 }
@@ -86,10 +92,14 @@ impl Default for Model {
             Err(FileError::RecoverFileFound(file)) => {
                 sender.send(AppEvent::Message("Recover file found. Use `:recover` to recover the file".to_string().into())).unwrap();
                 sender.send(AppEvent::RemoveInfoDisplay).unwrap();
-                file
+                *file
             }
         };
 
+        if let Some(message) = settings.borrow().problems_message() {
+            sender.send(AppEvent::Message(message.into())).unwrap();
+        }
+
         let (reg_sender, reg_receiver) = std::sync::mpsc::channel();
         let reg_receiver = Rc::new(reg_receiver);
 
@@ -123,6 +133,30 @@ impl Default for Model {
             ],
             ).is_ok());
 
+        assert!(app.mount(
+            Id::Gutter,
+            Box::new(
+                Gutter::new(pane.clone())
+            ),
+            Vec::default(),
+        ).is_ok());
+
+        assert!(app.mount(
+            Id::Outline,
+            Box::new(
+                Outline::new(pane.clone())
+            ),
+            Vec::default(),
+        ).is_ok());
+
+        assert!(app.mount(
+            Id::Picker,
+            Box::new(
+                Picker::new(pane.clone())
+            ),
+            Vec::default(),
+        ).is_ok());
+
         //assert!(app.active(&Id::Buffer).is_ok());
 
         let (component_sender, component_receiver) = std::sync::mpsc::channel();
@@ -212,10 +246,14 @@ impl Model {
             Err(FileError::RecoverFileFound(file)) => {
                 sender.send(AppEvent::Message("Recover file found. Use `:recover` to recover the file".to_string().into())).unwrap();
                 sender.send(AppEvent::RemoveInfoDisplay).unwrap();
-                file
+                *file
             }
         };
 
+        if let Some(message) = settings.borrow().problems_message() {
+            sender.send(AppEvent::Message(message.into())).unwrap();
+        }
+
 
         let pane = TextBuffer::new(file, sender.clone(), settings.clone(), register_channels.clone());
         let pane = Rc::new(RefCell::new(pane));
@@ -246,6 +284,30 @@ impl Model {
             ],
         ).is_ok());
 
+        assert!(app.mount(
+            Id::Gutter,
+            Box::new(
+                Gutter::new(pane.clone())
+            ),
+            Vec::default(),
+        ).is_ok());
+
+        assert!(app.mount(
+            Id::Outline,
+            Box::new(
+                Outline::new(pane.clone())
+            ),
+            Vec::default(),
+        ).is_ok());
+
+        assert!(app.mount(
+            Id::Picker,
+            Box::new(
+                Picker::new(pane.clone())
+            ),
+            Vec::default(),
+        ).is_ok());
+
         //assert!(app.active(&Id::Buffer).is_ok());
 
         let (component_sender, component_receiver) = std::sync::mpsc::channel();
@@ -297,7 +359,29 @@ impl Model {
                         )
                         .split(f.size());
 
-                    self.app.view(&Id::Buffer, f, chunks[0]);
+                    let gutter_width = self.pane.borrow().get_gutter_width() as u16;
+                    let outline_width = if self.pane.borrow().is_outline_open() {
+                        (chunks[0].width / 3).min(OUTLINE_WIDTH)
+                    } else {
+                        0
+                    };
+                    let columns = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .margin(0)
+                        .constraints(
+                            [
+                                Constraint::Length(gutter_width),
+                                Constraint::Min(0),
+                                Constraint::Length(outline_width),
+                            ]
+                                .as_ref(),
+                        )
+                        .split(chunks[0]);
+
+                    self.app.view(&Id::Buffer, f, columns[1]);
+                    self.app.view(&Id::Gutter, f, columns[0]);
+                    self.app.view(&Id::Outline, f, columns[2]);
+                    self.app.view(&Id::Picker, f, columns[1]);
                     self.app.view(&Id::Status, f, chunks[1])
                 })
                 .is_ok());
@@ -390,7 +474,7 @@ impl Update<Message> for Model {
                         }
                         Err(FileError::RecoverFileFound(file)) => {
                             self.sender.send(AppEvent::Message("Recover file found. Use `:recover` to recover the file".to_string().into())).unwrap();
-                            file
+                            *file
                         }
                     };
                     let file = self.pane.borrow_mut().change_file(file);
@@ -431,11 +515,24 @@ impl Update<Message> for Model {
                     None
                 }
                 Message::Key(key) => {
-                    self.pane.borrow_mut().process_keypress(key);
+                    let buffer_names = self.files.keys()
+                        .filter(|path| !path.as_os_str().is_empty())
+                        .map(|path| path.to_string_lossy().to_string())
+                        .collect();
+                    let mut pane = self.pane.borrow_mut();
+                    pane.set_buffer_names(buffer_names);
+                    pane.process_keypress(key);
                     None
                 }
                 Message::Tick => {
-                    self.pane.borrow_mut().refresh();
+                    let mut pane = self.pane.borrow_mut();
+                    if pane.check_key_timeout() {
+                        self.redraw = true;
+                    }
+                    if pane.idle() {
+                        self.redraw = true;
+                    }
+                    pane.refresh();
                     None
                 }
                 Message::InfoMessage(msg) => {
@@ -465,6 +562,18 @@ impl Update<Message> for Model {
 
                     None
                 }
+                Message::OpenBindings(mode) => {
+                    // The list is made again each time since the bindings can change while running
+                    let bindings_file = help::create_bindings_file(self.settings.clone(), &mode);
+
+                    let file = self.pane.borrow_mut().change_file(bindings_file);
+
+                    let path = file.get_path().unwrap_or(PathBuf::from(""));
+
+                    self.files.insert(path, file);
+
+                    None
+                }
                 _ => None,
             }
 
@@ -0,0 +1,275 @@
+//! Every command that a binding or the command line can run, along with the arguments that it takes.
+//! Bindings are checked against this when they are loaded, so that a typo is found before the key is pressed.
+
+use crate::models::file::range;
+
+
+/// The shape of one argument of a command.
+#[derive(Debug, Clone, Copy)]
+pub enum Argument {
+    /// One of a fixed set of words
+    OneOf(&'static [&'static str]),
+    /// A whole number, like a count or a line
+    Number,
+    /// A word that isn't checked any further, like a path or a register
+    Word,
+    /// Everything up to the end of the command, like a pattern
+    Text,
+}
+
+/// A command that the pane runs, which is what the command line and most bindings end up in.
+pub struct PaneCommand {
+    pub name: &'static str,
+    /// The arguments in order, where the ones past `required` can be left out
+    pub arguments: &'static [Argument],
+    pub required: usize,
+}
+
+const fn command(name: &'static str, arguments: &'static [Argument], required: usize) -> PaneCommand {
+    PaneCommand {
+        name,
+        arguments,
+        required,
+    }
+}
+
+const DIRECTIONS: &[&str] = &[
+    "up", "down", "left", "right", "page_up", "page_down", "half_page_up", "half_page_down",
+    "start_of_file", "end_of_file", "start_of_line", "end_of_line", "up_line_start", "down_line_start",
+    "next_word_front", "next_word_back", "prev_word_front", "prev_word_back", "to", "to_byte", "to_pair",
+];
+
+const TEXT_OBJECTS: &[&str] = &[
+    "char", "line", "word", "to_next_word", "to_prev_word", "to_end_line", "to_start_line", "selection",
+];
+
+/// The names that `change_mode` takes, which are the modes along with the ways of starting them.
+pub const MODE_CHANGES: &[&str] = &[
+    "Normal", "Insert", "Symbols", "Command", "History", "command_selection", "insert_before",
+    "insert_after", "insert_start_of_line", "insert_end_of_line", "insert_below", "insert_above",
+    "selection_normal", "selection_line", "selection_block", "history_search", "search_down", "search_up",
+    "mirror", "pair", "selection_normal_mirror", "selection_normal_pair", "selection_line_mirror",
+    "selection_line_pair", "selection_block_mirror", "selection_block_pair",
+];
+
+/// The commands that the pane runs, other than the ones that take a line range.
+pub const PANE_COMMANDS: &[PaneCommand] = &[
+    command("q", &[], 0),
+    command("q!", &[], 0),
+    command("qa!", &[], 0),
+    command("e", &[Argument::Word], 1),
+    command("w", &[Argument::Word], 0),
+    command("w!", &[Argument::Word], 0),
+    command("wq", &[Argument::Word], 0),
+    command("wq!", &[Argument::Word], 0),
+    command("w!q", &[Argument::Word], 0),
+    command("w!q!", &[Argument::Word], 0),
+    command("recover", &[], 0),
+    command("help", &[], 0),
+    command("h", &[], 0),
+    command("paste", &[Argument::OneOf(&["before", "after", "selection"]), Argument::Word], 1),
+    command("copy", &[Argument::OneOf(TEXT_OBJECTS), Argument::Word], 1),
+    command("delete", &[Argument::OneOf(TEXT_OBJECTS)], 1),
+    command("undo", &[], 0),
+    command("redo", &[], 0),
+    command("change_mode", &[Argument::OneOf(MODE_CHANGES), Argument::Word], 0),
+    command("fold", &[Argument::OneOf(&["toggle", "open", "close", "open_all", "close_all"])], 1),
+    command("reindent", &[Argument::Word], 0),
+    command("comment", &[Argument::Word], 0),
+    command("outline", &[Argument::OneOf(&["open", "close", "toggle"])], 0),
+    command("symbols", &[], 0),
+    command("history", &[Argument::OneOf(&["command", "search"])], 0),
+    command("syntax_error", &[Argument::OneOf(&["next", "prev"])], 0),
+    command("clear_selection", &[], 0),
+    command("select", &[Argument::Text], 1),
+    command("move", &[Argument::OneOf(DIRECTIONS), Argument::Word], 1),
+    command("search", &[Argument::OneOf(&["next", "previous"]), Argument::Number], 0),
+    command("search_word", &[Argument::OneOf(&["forward", "backward"]), Argument::OneOf(&["partial", "whole"])], 0),
+    command("search_selection", &[Argument::OneOf(&["forward", "backward"])], 0),
+    command("goto_line", &[Argument::Number], 1),
+    command("ssr", &[Argument::Text], 1),
+    command("ssr!", &[Argument::Text], 0),
+    command("set", &[Argument::Text], 0),
+    command("setlocal", &[Argument::Text], 0),
+    command("bind", &[Argument::Text], 1),
+    command("bind!", &[Argument::Text], 1),
+    command("unbind", &[Argument::Text], 1),
+    command("unbind!", &[Argument::Text], 1),
+    command("bindings", &[Argument::Word], 0),
+];
+
+/// The commands that take a line range, like `:10,20d`, which all take free-form arguments.
+pub const RANGE_COMMANDS: &[&str] = &[
+    "s", "d", "y", "m", "t", "co", "sort", "w", "mark", "k", "g", "v", "normal", "norm",
+];
+
+/// The commands that each mode runs itself when they are bound in it.
+pub const MODE_COMMANDS: &[(&str, &[&str])] = &[
+    ("Normal", &[
+        "cancel", "left", "right", "up", "down", "start_of_file", "end_of_file", "page_up", "page_down",
+        "half_page_up", "half_page_down", "start_of_line", "end_of_line", "up_line_start", "down_line_start",
+        "next_word_front", "next_word_back", "previous_word_front", "previous_word_back",
+        "select_next_word_front", "select_next_word_back", "select_previous_word_front",
+        "select_previous_word_back", "search_next",
+        "search_previous", "search_word_forward", "search_word_backward", "search_partial_word_forward",
+        "search_partial_word_backward", "goto_pair", "insert_before", "insert_after", "insert_start_of_line",
+        "insert_end_of_line", "insert_below", "insert_above", "command_mode", "selection_mode",
+        "selection_mode_line", "selection_mode_block", "search_mode_down", "search_mode_up", "replace_mode",
+        "goto_line", "copy_char", "copy_line", "copy_word", "copy_to_next_word", "copy_to_prev_word",
+        "copy_to_end_line", "copy_to_start_line", "delete_char", "delete_line", "delete_word",
+        "delete_to_next_word", "delete_to_prev_word", "delete_to_end_line", "delete_to_start_line",
+        "cut_char", "cut_line", "cut_word", "cut_to_next_word", "cut_to_prev_word", "cut_to_end_line",
+        "cut_to_start_line", "paste_before", "paste_after", "undo", "redo", "reindent_line", "reindent_file",
+        "toggle_comment", "next_syntax_error", "prev_syntax_error", "open_symbols", "toggle_outline",
+        "toggle_fold", "open_fold", "close_fold", "open_all_folds", "close_all_folds",
+    ]),
+    ("Insert", &[
+        "cancel", "tab", "backspace", "delete", "newline", "left", "right", "up", "down", "start_of_file",
+        "end_of_file", "page_up", "page_down", "half_page_up", "half_page_down", "start_of_line",
+        "end_of_line", "up_line_start", "down_line_end", "next_word_front", "next_word_back",
+        "previous_word_front", "previous_word_back", "command_mode", "selection_mode", "selection_mode_line",
+        "selection_mode_block", "search_mode_down", "search_mode_up", "replace_mode", "goto_line",
+        "copy_char", "copy_line", "copy_word", "copy_to_next_word", "copy_to_prev_word", "copy_to_end_line",
+        "copy_to_start_line", "delete_char", "delete_line", "delete_word", "delete_to_next_word",
+        "delete_to_prev_word", "delete_to_end_line", "delete_to_start_line", "cut_char", "cut_line",
+        "cut_word", "cut_to_next_word", "cut_to_prev_word", "cut_to_end_line", "cut_to_start_line",
+        "paste_before", "paste_after", "undo", "redo",
+    ]),
+    ("Selection", &[
+        "cancel", "left", "right", "up", "down", "start_of_file", "end_of_file", "page_up", "page_down",
+        "half_page_down", "start_of_line", "end_of_line", "up_line_start", "down_line_start",
+        "next_word_front", "next_word_back", "previous_word_front", "previous_word_back", "goto_pair", "copy",
+        "delete", "cut", "paste", "reindent", "toggle_comment", "mirror_mode", "pair_mode", "command_mode",
+        "search_selection_forward", "search_selection_backward", "expand_selection", "shrink_selection",
+    ]),
+    ("Command", &[
+        "cancel", "left", "right", "up", "down", "backspace", "delete", "execute", "complete",
+        "complete_previous",
+    ]),
+    ("Search", &[
+        "cancel", "left", "right", "up", "down", "backspace", "delete", "accept", "copy", "delete_search",
+        "cut", "paste", "next_match", "previous_match", "toggle_case", "toggle_whole_word", "mirror_mode",
+        "pair_mode",
+    ]),
+    ("Mirror", &["cancel", "left", "right", "up", "down", "backspace", "delete", "execute"]),
+    ("Pair", &["cancel", "left", "right", "up", "down", "backspace", "delete", "execute"]),
+    ("Symbols", &["cancel", "left", "right", "up", "down", "start", "end", "backspace", "delete", "execute"]),
+    ("History", &["cancel", "left", "right", "up", "down", "start", "end", "backspace", "delete", "execute"]),
+    ("Confirm", &["yes", "no", "all", "quit", "cancel"]),
+];
+
+/// The modes that hand the commands that they don't know to the pane, so ex commands can be bound in them.
+const FORWARDING_MODES: &[&str] = &["Normal", "Insert", "Selection"];
+
+/// Commands for splits, tabs and jumps that can be bound in the Universal table but don't do anything yet.
+pub const RESERVED_COMMANDS: &[&str] = &[
+    "split_horizontal", "split_vertical", "pane_left", "pane_right", "pane_up", "pane_down", "new_tab",
+    "new_tab_current_pane", "tab_left", "tab_right", "jump_forwards", "jump_backwards",
+];
+
+
+/// The commands that a mode runs itself, or `None` for a mode that doesn't exist.
+pub fn mode_commands(mode: &str) -> Option<&'static [&'static str]> {
+    MODE_COMMANDS.iter()
+        .find(|(name, _)| *name == mode)
+        .map(|(_, commands)| *commands)
+}
+
+/// Whether a mode hands the commands that it doesn't know to the pane, which is what runs ex commands and aliases.
+pub fn forwards_commands(mode: &str) -> bool {
+    FORWARDING_MODES.contains(&mode)
+}
+
+/// Every command name that can be used as a key in a table of keybindings.toml.
+pub fn binding_names() -> Vec<&'static str> {
+    let mut names = MODE_COMMANDS.iter()
+        .flat_map(|(_, commands)| commands.iter().copied())
+        .chain(RESERVED_COMMANDS.iter().copied())
+        .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Checks a command that is bound in a mode, where `Universal` is the table that every mode shares.
+/// An empty command is a binding that was removed, so it is always fine.
+pub fn check_binding(mode: &str, command: &str) -> Result<(), String> {
+    let mut words = command.split_whitespace();
+    let Some(name) = words.next() else {
+        return Ok(());
+    };
+
+    if mode == "Universal" {
+        if RESERVED_COMMANDS.contains(&name) || MODE_COMMANDS.iter().any(|(_, commands)| commands.contains(&name)) {
+            return check_no_arguments(name, words.next());
+        }
+        return check_command(command);
+    }
+
+    let commands = mode_commands(mode).ok_or(format!("Unknown mode: {}", mode))?;
+    if commands.contains(&name) {
+        return check_no_arguments(name, words.next());
+    }
+    if forwards_commands(mode) {
+        return check_command(command);
+    }
+    Err(format!("{} is not a command of {} mode", name, mode))
+}
+
+fn check_no_arguments(name: &str, argument: Option<&str>) -> Result<(), String> {
+    match argument {
+        Some(argument) => Err(format!("{} doesn't take an argument, but was given {}", name, argument)),
+        None => Ok(()),
+    }
+}
+
+/// Checks a command that the pane runs, like one typed on the command line.
+pub fn check_command(command: &str) -> Result<(), String> {
+    let command = command.trim();
+    let mut words = command.split_whitespace();
+    let Some(name) = words.next() else {
+        return Err(String::from("The command is empty"));
+    };
+
+    if let Some(spec) = PANE_COMMANDS.iter().find(|spec| spec.name == name) {
+        return check_arguments(spec, words.collect());
+    }
+
+    let (range, rest) = range::parse_range(command)?;
+    let name_end = match rest.chars().next() {
+        Some(shift @ ('>' | '<')) => rest.find(|c: char| c != shift).unwrap_or(rest.len()),
+        _ => rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len()),
+    };
+    let name = &rest[..name_end];
+    match name {
+        "" if range != range::LineRange::Default => Ok(()),
+        name if name.starts_with(['>', '<']) || RANGE_COMMANDS.contains(&name) => Ok(()),
+        "" => Err(format!("Unknown command: {}", command)),
+        _ => Err(format!("Unknown command: {}", rest.split_whitespace().next().unwrap_or(rest))),
+    }
+}
+
+fn check_arguments(spec: &PaneCommand, arguments: Vec<&str>) -> Result<(), String> {
+    if arguments.len() < spec.required {
+        return Err(format!("{} needs {} argument{}", spec.name, spec.required, if spec.required == 1 { "" } else { "s" }));
+    }
+    let takes_text = matches!(spec.arguments.last(), Some(Argument::Text));
+    if arguments.len() > spec.arguments.len() && !takes_text {
+        return Err(format!("{} takes at most {} argument{}", spec.name, spec.arguments.len(), if spec.arguments.len() == 1 { "" } else { "s" }));
+    }
+
+    for (argument, shape) in arguments.iter().zip(spec.arguments) {
+        match shape {
+            Argument::OneOf(words) if !words.contains(argument) => {
+                return Err(format!("{} takes one of {}, not {}", spec.name, words.join(", "), argument));
+            }
+            Argument::Number if argument.parse::<usize>().is_err() => {
+                return Err(format!("{} takes a number, not {}", spec.name, argument));
+            }
+            // The rest of the command is free-form
+            Argument::Text => break,
+            _ => {}
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use crate::models::command_registry::{PANE_COMMANDS, RANGE_COMMANDS};
+use crate::models::settings::editor_settings::{EditorSettings, OPTION_NAMES};
+
+/// The commands that take a path to write to.
+const WRITE_COMMANDS: [&str; 6] = ["w", "w!", "wq", "wq!", "w!q", "w!q!"];
+
+/// What the word before the cursor in the command line could be completed to.
+pub struct Completion {
+    /// The byte where the word that gets replaced starts
+    pub start: usize,
+    /// The words that could replace it, in the order that Tab cycles through them
+    pub candidates: Vec<String>,
+}
+
+/// Completes the last word of `line`, which is the command line up to the cursor.
+/// `buffer_names` are the paths of the other open buffers, which `:e` switches to,
+/// and `aliases` are the names from the `[Aliases]` table, which complete along with the commands.
+pub fn complete(line: &str, buffer_names: &[String], aliases: &[String]) -> Completion {
+    let start = line.char_indices()
+        .rfind(|(_, chr)| chr.is_whitespace())
+        .map_or(0, |(index, chr)| index + chr.len_utf8());
+    let word = &line[start..];
+    let command = line.split_whitespace().next().unwrap_or("");
+
+    let candidates = if start == 0 || line[..start].trim().is_empty() {
+        // The commands come from the registry so that every command the pane runs is completed
+        let mut candidates = PANE_COMMANDS.iter()
+            .map(|command| command.name)
+            .chain(RANGE_COMMANDS.iter().copied())
+            .map(|name| name.to_string())
+            .chain(aliases.iter().cloned())
+            .filter(|name| name.starts_with(word))
+            .collect::<Vec<_>>();
+        candidates.sort();
+        candidates.dedup();
+        candidates
+    } else if command == "e" {
+        let mut candidates = buffer_names.iter()
+            .filter(|name| name.starts_with(word))
+            .cloned()
+            .collect::<Vec<_>>();
+        for path in complete_path(word) {
+            if !candidates.contains(&path) {
+                candidates.push(path);
+            }
+        }
+        candidates
+    } else if WRITE_COMMANDS.contains(&command) {
+        complete_path(word)
+    } else if command == "set" || command == "setlocal" {
+        complete_setting(word)
+    } else {
+        Vec::new()
+    };
+
+    Completion {
+        start,
+        candidates,
+    }
+}
+
+/// Replaces a leading `~` with the home directory.
+pub fn expand_home(path: &str) -> String {
+    if path == "~" || path.starts_with("~/") {
+        if let Ok(home) = env::var("HOME") {
+            return format!("{}{}", home.trim_end_matches('/'), &path[1..]);
+        }
+    }
+    path.to_string()
+}
+
+/// Lists the files and directories that start with the last part of `word`.
+/// Directories end with a `/` so that the next Tab continues into them.
+fn complete_path(word: &str) -> Vec<String> {
+    let word = expand_home(word);
+    let (directory, prefix) = match word.rfind('/') {
+        Some(index) => word.split_at(index + 1),
+        None => ("", word.as_str()),
+    };
+    let read_from = if directory.is_empty() { Path::new(".") } else { Path::new(directory) };
+    let Ok(entries) = fs::read_dir(read_from) else {
+        return Vec::new();
+    };
+
+    let mut candidates = entries.filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            // Hidden files are only offered once a `.` has been typed
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let separator = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{}{}{}", directory, name, separator))
+        })
+        .collect::<Vec<_>>();
+    candidates.sort();
+    candidates
+}
+
+/// Completes a setting name, or the value of a setting once `=` has been typed.
+fn complete_setting(word: &str) -> Vec<String> {
+    match word.split_once('=') {
+        Some((name, value)) => EditorSettings::option_values(name).iter()
+            .filter(|candidate| candidate.starts_with(value))
+            .map(|candidate| format!("{}={}", name, candidate))
+            .collect(),
+        None => OPTION_NAMES.iter().copied()
+            .filter(|setting| setting.starts_with(word))
+            .map(str::to_string)
+            .collect(),
+    }
+}
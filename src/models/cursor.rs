@@ -80,10 +80,8 @@ impl Cursor {
     }
 
     pub fn get_relative_cursor(self, pane: &dyn TextPane) -> (usize, usize) {
-        let settings = pane.get_settings();
-        let settings = settings.borrow();
-        let tab_size = if !settings.editor_settings.use_spaces {
-            let file = pane.borrow_current_file();
+        let file = pane.borrow_current_file();
+        let tab_size = if !file.get_use_spaces() {
             match file.get_line(self.row) {
                 Some(line) => {
                     let mut tab_size = 0;
@@ -92,14 +90,13 @@ impl Cursor {
                             tab_size += 1;
                         }
                     }
-                    tab_size * (settings.editor_settings.tab_size as usize - 1)
+                    tab_size * (file.get_tab_size() as usize - 1)
                 }
                 None => 0,
             }
         } else {
             0
         };
-        let file = pane.borrow_current_file();
         let col = match file.get_line(self.row) {
             None => self.col,
             Some(line) => {
@@ -110,7 +107,9 @@ impl Cursor {
         };
 
 
-        (col.saturating_sub(self.col_offset) + self.number_line_width + self.gutter_width + tab_size, self.row.saturating_sub(self.row_offset))
+        let row = file.count_visible_rows(self.row_offset, self.row);
+
+        (col.saturating_sub(self.col_offset) + self.number_line_width + self.gutter_width + tab_size, row)
     }
 
     pub fn get_scroll_amount(self) -> (usize, usize) {
@@ -132,8 +131,8 @@ impl Cursor {
         };
 
         match self.col_movement {
-            ColMovement::Right if rect.width != 0 && ((col + self.number_line_width - self.gutter_width).saturating_sub(self.col_offset)) >= rect.width => {
-                self.col_offset = (col + self.number_line_width - self.gutter_width).saturating_sub(rect.width) + 1;
+            ColMovement::Right if rect.width != 0 && ((col + self.number_line_width).saturating_sub(self.col_offset)) >= rect.width => {
+                self.col_offset = (col + self.number_line_width).saturating_sub(rect.width) + 1;
             }
             ColMovement::Left if (col.saturating_sub(self.col_offset)) == 0 => {
                 self.col_offset = col;
@@ -141,15 +140,31 @@ impl Cursor {
             _ => {}
         }
 
+        if let Some((start, _)) = file.get_closed_fold(self.row_offset) {
+            self.row_offset = start;
+        }
+
         match self.row_movement {
-            RowMovement::Down if rect.height != 0 && ((self.row) - self.row_offset) >= rect.height => {
-                self.row_offset = (self.row).saturating_sub(rect.height) + 1;
+            RowMovement::Down if rect.height != 0 && file.count_visible_rows(self.row_offset, self.row) >= rect.height => {
+                let mut row_offset = self.row;
+                for _ in 1..rect.height {
+                    row_offset = file.prev_visible_row(row_offset);
+                }
+                self.row_offset = row_offset;
             }
             RowMovement::Up if (self.row.saturating_sub(self.row_offset)) == 0 => {
                 self.row_offset = self.row;
             }
             _ => {}
         }
+
+        // The scope headers pinned to the top of the view hide the rows below them
+        let sticky_scroll = pane.get_settings().borrow().editor_settings.sticky_scroll.min(self.height / 2);
+        if sticky_scroll > 0 && self.row >= self.row_offset {
+            while self.row_offset > 0 && file.count_visible_rows(self.row_offset, self.row) < file.get_sticky_rows(self.row_offset, sticky_scroll).len() {
+                self.row_offset = file.prev_visible_row(self.row_offset);
+            }
+        }
     }
 
     pub fn set_cursor(&mut self, col: usize, row: usize) {
@@ -171,14 +186,17 @@ impl Cursor {
         match direction {
             CursorMovement::Up => {
                 self.row_movement = RowMovement::Up;
-                self.row = self.row.saturating_sub(n);
+                for _ in 0..n {
+                    self.row = file.prev_visible_row(self.row);
+                }
             }
             CursorMovement::Down => {
                 if self.row < number_of_lines {
-                    let new_row =(self.row + n) % number_of_lines;
-                    if new_row < self.row {
-                        self.row = number_of_lines.saturating_sub(1)
-                    } else {
+                    for _ in 0..n {
+                        let new_row = file.next_visible_row(self.row);
+                        if new_row >= number_of_lines {
+                            break;
+                        }
                         self.row = new_row;
                     }
                 }
@@ -309,9 +327,9 @@ impl Cursor {
             }
         }
 
-
-
-
+        if let Some((start, _)) = file.get_closed_fold(self.row) {
+            self.row = start;
+        }
     }
 
 }
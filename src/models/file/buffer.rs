@@ -1,16 +1,77 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
 use std::fmt;
 use std::io::Write;
-use std::ops::{RangeBounds};
+use std::ops::{Range, RangeBounds};
 use std::path::PathBuf;
+use crate::models::file::language;
 use crate::models::settings::Settings;
+use crate::models::settings::editor_settings::LocalSettings;
 
 use tree_sitter;
 use crop::{Rope, RopeSlice};
 
 
 
+/// A range of the buffer that is written in another language along with the syntax tree for that range.
+#[derive(Debug, Clone)]
+pub struct Injection {
+    pub language: &'static str,
+    pub range: Range<usize>,
+    pub tree: tree_sitter::Tree,
+}
+
+/// How an edit moved the rows of a buffer: the `old_rows` rows starting at `row` were replaced by `new_rows` rows,
+/// and every row after them moved by the difference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowEdit {
+    pub row: usize,
+    pub old_rows: usize,
+    pub new_rows: usize,
+}
+
+impl RowEdit {
+    /// Returns where `row` is after the edit, or `None` if the edit replaced its line.
+    pub fn follow_row(&self, row: usize) -> Option<usize> {
+        if row < self.row {
+            Some(row)
+        } else if row >= self.row + self.old_rows {
+            Some(row + self.new_rows - self.old_rows)
+        } else {
+            None
+        }
+    }
+
+    /// Returns where the inclusive rows `start..=end` are after the edit.
+    /// An edit between the first and last row resizes them, and `None` is returned if it replaced either of them.
+    pub fn follow_region(&self, (start, end): (usize, usize)) -> Option<(usize, usize)> {
+        if end < self.row {
+            Some((start, end))
+        } else if start >= self.row + self.old_rows {
+            Some((start + self.new_rows - self.old_rows, end + self.new_rows - self.old_rows))
+        } else if start < self.row && self.row + self.old_rows <= end + 1 {
+            Some((start, end + self.new_rows - self.old_rows))
+        } else {
+            None
+        }
+    }
+}
+
+/// The most row edits that a buffer remembers, after which the older ones are forgotten.
+const MAX_ROW_EDITS: usize = 4096;
+
+/// The injection query of the buffer's language and the injections found for a buffer version.
+/// Each injected language keeps its own parser, and the trees of the injections are edited along with the buffer
+/// so that an injection is parsed incrementally from its old tree when the injections are found again.
+#[derive(Default)]
+struct InjectionLayer {
+    query: Option<tree_sitter::Query>,
+    parsers: HashMap<&'static str, tree_sitter::Parser>,
+    version: Option<usize>,
+    injections: Vec<Injection>,
+}
+
 impl fmt::Debug for Buffer {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Buffer {{ current: {}, history: {:?},  version: {} }}", self.current, self.history,  self.version)
@@ -22,8 +83,15 @@ pub struct Buffer {
     current: usize,
     history: Vec<Rope>,
     tree_sitter_info: Option<(tree_sitter::Parser, Vec<tree_sitter::Tree>)>,
+    injections: RefCell<InjectionLayer>,
     settings: Rc<RefCell<Settings>>,
+    /// The settings that were changed for this buffer alone with `:setlocal`
+    local_settings: LocalSettings,
     version: usize,
+    /// The row edits along with the buffer version they were made in
+    row_edits: Vec<(usize, RowEdit)>,
+    /// The oldest version that every row edit since is remembered for
+    row_edits_start: usize,
 }
 
 
@@ -33,8 +101,12 @@ impl Buffer {
             current: 0,
             history: vec![Rope::new()],
             tree_sitter_info: None,
+            injections: RefCell::new(InjectionLayer::default()),
             settings,
+            local_settings: LocalSettings::default(),
             version: 0,
+            row_edits: Vec::new(),
+            row_edits_start: 0,
         }
     }
 
@@ -42,10 +114,142 @@ impl Buffer {
         self.settings = settings;
     }
 
+    pub fn get_local_settings(&self) -> LocalSettings {
+        self.local_settings
+    }
+
+    pub fn set_local_settings(&mut self, local_settings: LocalSettings) {
+        self.local_settings = local_settings;
+    }
+
+    /// The width of a tab in this buffer.
+    pub fn get_tab_size(&self) -> u8 {
+        self.local_settings.tab_size.unwrap_or_else(|| self.settings.borrow().editor_settings.tab_size)
+    }
+
+    /// Whether indenting this buffer inserts spaces instead of tabs.
+    pub fn get_use_spaces(&self) -> bool {
+        self.local_settings.use_spaces.unwrap_or_else(|| self.settings.borrow().editor_settings.use_spaces)
+    }
+
     pub fn get_version(&self) -> usize {
         self.version
     }
 
+    /// Returns the row edits made since `version` in the order they were made,
+    /// or `None` if the buffer changed in a way that moves rows unpredictably, like an undo.
+    pub fn get_row_edits(&self, version: usize) -> Option<Vec<RowEdit>> {
+        if version < self.row_edits_start {
+            return None;
+        }
+        let start = self.row_edits.partition_point(|(edit_version, _)| *edit_version < version);
+        Some(self.row_edits[start..].iter().map(|(_, edit)| *edit).collect())
+    }
+
+    /// Remembers how replacing `range` with `text` moves the rows and applies the edit to the injected trees.
+    /// This needs to be called before the edit is made since it reads the text being replaced.
+    fn record_edit(&mut self, range: Range<usize>, text: &str) {
+        self.edit_injections(&range, text);
+        self.record_row_edit(range, text);
+    }
+
+    /// Edits the trees of the injections so that they can be reused when the injections are parsed again.
+    fn edit_injections(&self, range: &Range<usize>, text: &str) {
+        let mut layer = self.injections.borrow_mut();
+        if layer.injections.is_empty() {
+            return;
+        }
+
+        let byte_len = self.history[self.current].byte_len();
+        let start = range.start.min(byte_len);
+        let old_end = range.end.min(byte_len).max(start);
+        let new_end = start + text.len();
+        let start_position = self.get_point(start);
+        let new_end_position = match text.rfind('\n') {
+            Some(last_newline) => tree_sitter::Point::new(
+                start_position.row + text.matches('\n').count(),
+                text.len() - last_newline - 1,
+            ),
+            None => tree_sitter::Point::new(start_position.row, start_position.column + text.len()),
+        };
+        let edit = tree_sitter::InputEdit {
+            start_byte: start,
+            old_end_byte: old_end,
+            new_end_byte: new_end,
+            start_position,
+            old_end_position: self.get_point(old_end),
+            new_end_position,
+        };
+
+        let shift = |offset: usize| if offset >= old_end {
+            offset - old_end + new_end
+        } else {
+            offset.min(new_end)
+        };
+        for injection in layer.injections.iter_mut() {
+            injection.tree.edit(&edit);
+            injection.range = shift(injection.range.start)..shift(injection.range.end);
+        }
+    }
+
+    /// Remembers how replacing `range` with `text` moves the rows.
+    fn record_row_edit(&mut self, range: Range<usize>, text: &str) {
+        let rope = &self.history[self.current];
+        let start = range.start.min(rope.byte_len());
+        let end = range.end.min(rope.byte_len()).max(start);
+        let old_text = rope.byte_slice(start..end);
+        let old_rows = old_text.bytes().filter(|byte| *byte == b'\n').count();
+        let new_rows = text.bytes().filter(|byte| *byte == b'\n').count();
+        if old_rows == 0 && new_rows == 0 {
+            return;
+        }
+
+        let row = rope.line_of_byte(start);
+        // Whole lines replaced at the start of a row move that row, otherwise it stays and the rows after it change
+        let old_ends_line = start == end || old_text.bytes().next_back() == Some(b'\n');
+        let new_ends_line = text.is_empty() || text.ends_with('\n');
+        let row = if start == rope.byte_of_line(row) && old_ends_line && new_ends_line {
+            row
+        } else {
+            row + 1
+        };
+
+        if self.row_edits.len() >= MAX_ROW_EDITS {
+            let forgotten = self.row_edits.drain(..MAX_ROW_EDITS / 2).next_back();
+            if let Some((version, _)) = forgotten {
+                self.row_edits_start = version + 1;
+            }
+        }
+        self.row_edits.push((self.version, RowEdit {
+            row,
+            old_rows,
+            new_rows,
+        }));
+    }
+
+    /// Forgets the row edits after a change that doesn't come from them, like an undo.
+    fn forget_row_edits(&mut self) {
+        self.row_edits.clear();
+        self.row_edits_start = self.version;
+        // The injected trees were for the text before the change, so they are parsed from scratch
+        self.injections.borrow_mut().injections.clear();
+    }
+
+    /// Turns the bounds of a range into a byte range of the current text.
+    fn get_byte_range<R>(&self, range: &R) -> Range<usize> where R: RangeBounds<usize> {
+        let start = match range.start_bound() {
+            std::ops::Bound::Included(n) => *n,
+            std::ops::Bound::Excluded(n) => *n + 1,
+            std::ops::Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            std::ops::Bound::Included(n) => *n + 1,
+            std::ops::Bound::Excluded(n) => *n,
+            std::ops::Bound::Unbounded => self.history[self.current].byte_len(),
+        };
+        start..end
+    }
+
     pub fn save(&mut self, file_path: &PathBuf) {
         let file = std::fs::File::create(file_path).unwrap();
         let mut writer = std::io::BufWriter::new(file);
@@ -63,6 +267,142 @@ impl Buffer {
         self.tree_sitter_info = Some((parser, trees));
     }
 
+    pub fn get_tree(&self) -> Option<&tree_sitter::Tree> {
+        match self.tree_sitter_info.as_ref() {
+            None => None,
+            Some((_, trees)) => trees.get(self.current),
+        }
+    }
+
+    /// Sets the query that finds the ranges written in other languages.
+    /// This needs to be called after `set_tree_sitter` since the query is compiled for the buffer's grammar.
+    pub fn set_injection_query(&mut self, query: &str) -> Result<(), String> {
+        let tree = self.get_tree().ok_or(String::from("The buffer has no syntax tree"))?;
+        let query = tree_sitter::Query::new(tree.language(), query).map_err(|error| error.message)?;
+
+        let mut layer = self.injections.borrow_mut();
+        layer.query = Some(query);
+        layer.version = None;
+        Ok(())
+    }
+
+    /// Returns the injected ranges of the buffer sorted by their start.
+    /// The injections are found again whenever the buffer has changed,
+    /// and each one is reparsed from the edited tree of the injection it overlaps.
+    pub fn get_injections(&self) -> Vec<Injection> {
+        let mut layer = self.injections.borrow_mut();
+        if layer.version == Some(self.version) {
+            return layer.injections.clone();
+        }
+
+        let InjectionLayer { query, parsers, injections: old_injections, .. } = &mut *layer;
+        let mut old_injections = std::mem::take(old_injections);
+        let (Some(query), Some(tree)) = (query.as_ref(), self.get_tree()) else {
+            return Vec::new();
+        };
+
+        let text = self.history[self.current].to_string();
+        let content_index = query.capture_index_for_name("injection.content");
+        let language_index = query.capture_index_for_name("injection.language");
+
+        let mut injections = Vec::new();
+        let mut cursor = tree_sitter::QueryCursor::new();
+        for query_match in cursor.matches(query, tree.root_node(), text.as_bytes()) {
+            let Some(content) = query_match.captures.iter().find(|capture| Some(capture.index) == content_index) else {
+                continue;
+            };
+
+            let name = match query_match.captures.iter().find(|capture| Some(capture.index) == language_index) {
+                Some(capture) => capture.node.utf8_text(text.as_bytes()).unwrap_or_default().to_string(),
+                None => query.property_settings(query_match.pattern_index).iter()
+                    .find(|property| &*property.key == "injection.language")
+                    .and_then(|property| property.value.as_ref())
+                    .map(|value| value.to_string())
+                    .unwrap_or_default(),
+            };
+            let Some((name, grammar)) = language::get_language(&name) else {
+                continue;
+            };
+
+            let mut range = content.node.byte_range();
+            for predicate in query.general_predicates(query_match.pattern_index) {
+                if &*predicate.operator != "offset!" {
+                    continue;
+                }
+                let offsets = predicate.args.iter().filter_map(|arg| match arg {
+                    tree_sitter::QueryPredicateArg::String(value) => value.parse::<isize>().ok(),
+                    _ => None,
+                }).collect::<Vec<_>>();
+                if let [_, start, _, end] = offsets[..] {
+                    range = range.start.saturating_add_signed(start)..range.end.saturating_add_signed(end);
+                }
+            }
+            if range.start >= range.end || range.end > text.len() {
+                continue;
+            }
+
+            let parser = parsers.entry(name).or_insert_with(|| {
+                let mut parser = tree_sitter::Parser::new();
+                parser.set_language(grammar).unwrap();
+                parser
+            });
+            let included = tree_sitter::Range {
+                start_byte: range.start,
+                end_byte: range.end,
+                start_point: self.get_point(range.start),
+                end_point: self.get_point(range.end),
+            };
+            if parser.set_included_ranges(&[included]).is_err() {
+                continue;
+            }
+            let old_tree = old_injections.iter()
+                .position(|old| old.language == name && old.range.start < range.end && range.start < old.range.end)
+                .map(|index| old_injections.swap_remove(index).tree);
+            if let Some(tree) = parser.parse(&text, old_tree.as_ref()) {
+                injections.push(Injection {
+                    language: name,
+                    range,
+                    tree,
+                });
+            }
+        }
+        injections.sort_by(|a, b| a.range.start.cmp(&b.range.start).then(b.range.end.cmp(&a.range.end)));
+
+        layer.version = Some(self.version);
+        layer.injections = injections.clone();
+        injections
+    }
+
+    /// Returns the smallest injection that contains the byte range.
+    pub fn get_injection_at(&self, start: usize, end: usize) -> Option<Injection> {
+        self.get_injections().into_iter()
+            .filter(|injection| injection.range.start <= start && end <= injection.range.end)
+            .min_by_key(|injection| injection.range.len())
+    }
+
+    /// Whether the text at `byte_offset` starts with `token`.
+    pub fn has_text_at(&self, byte_offset: usize, token: &str) -> bool {
+        let rope = &self.history[self.current];
+        let end = byte_offset + token.len();
+        end <= rope.byte_len()
+            && rope.is_char_boundary(byte_offset)
+            && rope.is_char_boundary(end)
+            && rope.byte_slice(byte_offset..end) == token
+    }
+
+    /// Returns the text of a byte range as chunks of the rope, so that queries can read it without copying the buffer.
+    pub fn get_chunks(&self, range: Range<usize>) -> impl Iterator<Item = &[u8]> {
+        let rope = &self.history[self.current];
+        let range = range.start.min(rope.byte_len())..range.end.min(rope.byte_len());
+        rope.byte_slice(range).chunks().map(str::as_bytes)
+    }
+
+    fn get_point(&self, byte_offset: usize) -> tree_sitter::Point {
+        let row = self.history[self.current].line_of_byte(byte_offset);
+        let column = byte_offset - self.history[self.current].byte_of_line(row);
+        tree_sitter::Point::new(row, column)
+    }
+
     pub fn get_char_at(&self, mut byte_offset: usize) -> Option<char> {
         let current = &self.history[self.current];
 
@@ -99,6 +439,7 @@ impl Buffer {
         self.current = self.current.saturating_sub(1);
 
         self.version += 1;
+        self.forget_row_edits();
     }
 
     pub fn redo(&mut self) {
@@ -107,11 +448,43 @@ impl Buffer {
         }
 
         self.version += 1;
+        self.forget_row_edits();
+    }
+
+    /// Starts an undo step that the following edits are gathered into, returning where it starts.
+    /// The edits stay separate steps until `end_undo_group` merges them.
+    pub fn start_undo_group(&mut self) -> usize {
+        self.get_new_rope();
+        self.current
+    }
+
+    /// Merges the undo steps made since `start_undo_group` into a single step.
+    pub fn end_undo_group(&mut self, start: usize) {
+        if self.current < start {
+            return;
+        }
+
+        self.history.drain(start..self.current);
+        if let Some((_, trees)) = self.tree_sitter_info.as_mut() {
+            trees.drain(start..self.current);
+        }
+        self.current = start;
+
+        // Leave no empty step behind when nothing was edited
+        if self.current == self.history.len() - 1 && self.history[start] == self.history[start - 1] {
+            self.history.pop();
+            if let Some((_, trees)) = self.tree_sitter_info.as_mut() {
+                trees.pop();
+            }
+            self.current -= 1;
+        }
+        self.version += 1;
     }
 
     pub fn line_len(&self, row: usize) -> Option<usize> {
+        let tab_size = self.get_tab_size() as usize;
         self.history[self.current].lines().nth(row).map(|line| line.chars().map(|c| if c == '\t' {
-            self.settings.borrow().editor_settings.tab_size as usize
+            tab_size
         } else {
             1
         }).sum())
@@ -130,7 +503,7 @@ impl Buffer {
     }
 
     pub fn get_byte_count(&self) -> usize {
-        self.history[self.current].bytes().count()
+        self.history[self.current].byte_len()
     }
 
 
@@ -217,6 +590,7 @@ impl Buffer {
                 let x = byte_offset - self.history[self.current].byte_of_line(y);
                 
 
+                self.record_edit(byte_offset..byte_offset, text.as_ref());
                 self.history[self.current].insert(byte_offset, text.as_ref());
 
                 let end_x = x + text.as_ref().bytes().count();
@@ -237,7 +611,7 @@ impl Buffer {
 
             }
             None => {
-            
+                self.record_edit(byte_offset..byte_offset, text.as_ref());
                 self.history[self.current].insert(byte_offset, text.as_ref());
 
             },
@@ -261,6 +635,8 @@ impl Buffer {
     }
 
     fn delete_internal<R>(&mut self, range: R) where R: std::ops::RangeBounds<usize> {
+        self.record_edit(self.get_byte_range(&range), "");
+
         let mut tree_sitter_info = self.tree_sitter_info.take();
 
         match tree_sitter_info.as_mut() {
@@ -324,6 +700,8 @@ impl Buffer {
     }
 
     fn replace_internal<R, T>(&mut self, range:R, text: T) where R: std::ops::RangeBounds<usize>, T: AsRef<str> {
+        self.record_edit(self.get_byte_range(&range), text.as_ref());
+
         let mut tree_sitter_info = self.tree_sitter_info.take();
 
         match tree_sitter_info.as_mut() {
@@ -508,6 +886,7 @@ impl Buffer {
 
         match tree_sitter_info.as_mut() {
             None => {
+                self.record_edit(byte_offset..byte_offset, text.as_ref());
                 self.history[self.current].insert(byte_offset, text.as_ref());
             },
             Some((parser, trees)) => {
@@ -518,6 +897,7 @@ impl Buffer {
                 let x = byte_offset - self.history[self.current].byte_of_line(y);
                 
 
+                self.record_edit(byte_offset..byte_offset, text.as_ref());
                 self.history[self.current].insert(byte_offset, text.as_ref());
 
                 let end_x = x + text.as_ref().bytes().count();
@@ -582,6 +962,7 @@ impl Buffer {
                     }
                     current = self.get_char_at(end);
                 }
+                self.record_edit(start..end, "");
                 self.history[self.current].delete(start..end);
                 start
             },
@@ -609,6 +990,7 @@ impl Buffer {
                     }
                     current = self.get_char_at(end);
                 }
+                self.record_edit(start..end, "");
                 self.history[self.current].delete(start..end);
 
                 let line_num = self.history[self.current].line_of_byte(byte_offset);
@@ -653,6 +1035,7 @@ impl Buffer {
                 } else {
                     self.history[self.current].byte_len()
                 };
+                self.record_edit(line_byte..next_line_byte, "");
                 self.history[self.current].delete(line_byte..next_line_byte);
             },
             Some((parser, trees)) => {
@@ -664,6 +1047,7 @@ impl Buffer {
                 } else {
                     self.history[self.current].byte_len()
                 };
+                self.record_edit(line_byte..next_line_byte, "");
                 self.history[self.current].delete(line_byte..next_line_byte);
 
                 let y = row;
@@ -739,6 +1123,38 @@ impl Buffer {
         self.version += 1;
     }
 
+    /// Replaces each range with its text as a single undo step.
+    /// The ranges must not overlap.
+    pub fn replace_ranges<T>(&mut self, edits: Vec<(std::ops::Range<usize>, T)>) where T: AsRef<str> {
+        self.get_new_rope();
+
+        self.replace_ranges_internal(edits);
+    }
+
+    /// Replaces each range with its text as part of the current undo step.
+    /// The ranges must not overlap.
+    pub fn replace_ranges_current<T>(&mut self, edits: Vec<(std::ops::Range<usize>, T)>) where T: AsRef<str> {
+        if self.current == 0 {
+            self.get_new_rope();
+        }
+
+        self.replace_ranges_internal(edits);
+    }
+
+    fn replace_ranges_internal<T>(&mut self, mut edits: Vec<(std::ops::Range<usize>, T)>) where T: AsRef<str> {
+        edits.sort_by_key(|(range, _)| range.start);
+        for (range, text) in edits.iter().rev() {
+            self.record_edit(range.clone(), text.as_ref());
+            self.history[self.current].replace(range.clone(), text.as_ref());
+        }
+
+        if let Some((parser, trees)) = self.tree_sitter_info.as_mut() {
+            trees[self.current] = parser.parse(self.history[self.current].to_string(), None).unwrap();
+        }
+
+        self.version += 1;
+    }
+
     pub fn get_nth_byte(&self, n: usize) -> Option<u8> {
         self.history[self.current].bytes().nth(n)
     }
@@ -750,42 +1166,54 @@ impl Buffer {
     pub fn insert_chain<T>(&mut self, values: Vec<(usize, T)>)
         where T: AsRef<str>
     {
-        let buffer = self.get_new_rope();
+        self.get_new_rope();
         for (offset, text) in values.iter().rev() {
-            buffer.insert(*offset, text.as_ref());
+            self.record_edit(*offset..*offset, text.as_ref());
+            self.history[self.current].insert(*offset, text.as_ref());
         }
+        self.version += 1;
     }
 
     pub fn delete_chain<R>(&mut self, values: Box<[R]>)
         where R: std::ops::RangeBounds<usize> + Copy
     {
-        let buffer = self.get_new_rope();
+        self.get_new_rope();
         for range in values.iter().rev() {
-            buffer.delete(*range);
+            self.record_edit(self.get_byte_range(range), "");
+            self.history[self.current].delete(*range);
         }
+        self.version += 1;
     }
 
     pub fn replace_chain<R, T>(&mut self, values: Box<[(R, T)]>)
         where R: std::ops::RangeBounds<usize> + Copy, T: AsRef<str>
     {
-        let buffer = self.get_new_rope();
+        self.get_new_rope();
         for (range, text) in values.iter().rev() {
-            buffer.replace(*range, text.as_ref());
+            self.record_edit(self.get_byte_range(range), text.as_ref());
+            self.history[self.current].replace(*range, text.as_ref());
         }
+        self.version += 1;
     }
 
     pub fn insert_pair<T>(&mut self, start: usize, end: usize, text: (T, T)) where T: AsRef<str> {
         self.get_new_rope();
+        self.record_edit(end + 1..end + 1, text.1.as_ref());
         self.history[self.current].insert(end + 1, text.1);
+        self.record_edit(start..start, text.0.as_ref());
         self.history[self.current].insert(start, text.0);
+        self.version += 1;
     }
 
     pub fn insert_bulk_pair<T>(&mut self, ranges: Vec<(usize, usize)>, texts: Vec<(T, T)>) where T: AsRef<str> {
         self.get_new_rope();
         for (range, text) in ranges.iter().rev().zip(texts.iter().rev()) {
+            self.record_edit(range.1 + 1..range.1 + 1, text.1.as_ref());
             self.history[self.current].insert(range.1 + 1, &text.1);
+            self.record_edit(range.0..range.0, text.0.as_ref());
             self.history[self.current].insert(range.0, &text.0);
         }
+        self.version += 1;
     }
 
     pub fn get_version_count(&self) -> usize {
@@ -799,7 +1227,7 @@ impl Buffer {
         }
 
         let line = self.history[self.current].line_slice(row..row + 1);
-        Some(BufferSlice::new(line, self.settings.clone()))
+        Some(BufferSlice::new(line, self.get_tab_size() as usize))
     }
 
     pub fn get_row_special(&self, row: usize, col_offset: usize, cols: usize) -> Option<BufferSlice> {
@@ -822,7 +1250,7 @@ impl Buffer {
         if col_offset > len {
             return None;
         }
-        Some(BufferSlice::new(line.byte_slice(col_offset..len), self.settings.clone()))
+        Some(BufferSlice::new(line.byte_slice(col_offset..len), self.get_tab_size() as usize))
 
     }
 
@@ -830,54 +1258,45 @@ impl Buffer {
         if start > end {
             return None;
         }
-        if end > self.history[self.current].bytes().count() {
+        if end > self.history[self.current].byte_len() {
             return None;
         }
-        Some(BufferSlice::new(self.history[self.current].byte_slice(start..end), self.settings.clone()))
+        Some(BufferSlice::new(self.history[self.current].byte_slice(start..end), self.get_tab_size() as usize))
     }
 
     pub fn get_word(&self, byte_offset: usize) -> Option<BufferSlice> {
+        let range = self.get_word_range(byte_offset)?;
+        Some(BufferSlice::new(self.history[self.current].byte_slice(range), self.get_tab_size() as usize))
+    }
 
-        if byte_offset >= self.history[self.current].bytes().count() {
+    /// Returns the byte range of the run of letters, digits and underscores at a byte offset.
+    pub fn get_word_range(&self, mut byte_offset: usize) -> Option<std::ops::Range<usize>> {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        if !self.get_char_at(byte_offset).is_some_and(is_word) {
             return None;
         }
-
-        if !self.get_char_at(byte_offset).unwrap().is_alphanumeric() {
-            return None;
+        while !self.history[self.current].is_char_boundary(byte_offset) {
+            byte_offset -= 1;
         }
 
         let mut start = byte_offset;
-        let mut end = byte_offset;
-
-        let mut current = self.get_char_at(byte_offset);
-        while let Some(c) = current {
-            if c.is_alphanumeric() || c == '_' {
-                start = start.saturating_sub(1);
-            } else {
-                break;
-            }
-            current = self.get_char_at(start);
-        }
-
-        current = self.get_char_at(byte_offset);
-        while let Some(c) = current {
-            if c.is_alphanumeric() || c == '_' {
-                end += 1;
-            } else {
-                break;
+        while start > 0 {
+            match self.get_char_at(start - 1) {
+                Some(c) if is_word(c) => start -= c.len_utf8(),
+                _ => break,
             }
-            current = self.get_char_at(end);
         }
 
-        if start == end {
-            return None;
+        let mut end = byte_offset;
+        while let Some(c) = self.get_char_at(end).filter(|c| is_word(*c)) {
+            end += c.len_utf8();
         }
 
-        Some(BufferSlice::new(self.history[self.current].byte_slice(start..end), self.settings.clone()))
+        Some(start..end)
     }
 
     pub fn get_until_next_word(&self, byte_offset: usize) -> Option<BufferSlice> {
-        if byte_offset >= self.history[self.current].bytes().count() {
+        if byte_offset >= self.history[self.current].byte_len() {
             return None;
         }
 
@@ -899,11 +1318,11 @@ impl Buffer {
             }
             current = self.get_char_at(end);
         }
-        Some(BufferSlice::new(self.history[self.current].byte_slice(byte_offset..end), self.settings.clone()))
+        Some(BufferSlice::new(self.history[self.current].byte_slice(byte_offset..end), self.get_tab_size() as usize))
     }
 
     pub fn get_until_prev_word(&self, byte_offset: usize) -> Option<BufferSlice> {
-        if byte_offset >= self.history[self.current].bytes().count() {
+        if byte_offset >= self.history[self.current].byte_len() {
             return None;
         }
 
@@ -926,11 +1345,11 @@ impl Buffer {
             current = self.get_char_at(start);
         }
 
-        Some(BufferSlice::new(self.history[self.current].byte_slice(start..byte_offset), self.settings.clone()))
+        Some(BufferSlice::new(self.history[self.current].byte_slice(start..byte_offset), self.get_tab_size() as usize))
     }
 
     pub fn get_cursor_from_byte_offset(&self, byte_offset: usize) -> Option<(usize, usize)> {
-        if byte_offset >= self.history[self.current].bytes().count() {
+        if byte_offset >= self.history[self.current].byte_len() {
             return None;
         }
 
@@ -1089,8 +1508,11 @@ impl From<&str> for Buffer {
             history: vec![Rope::from(s)],
             settings: Rc::new(RefCell::new(Settings::default())),
             tree_sitter_info: None,
+            injections: RefCell::new(InjectionLayer::default()),
+            local_settings: LocalSettings::default(),
             version: 0,
-
+            row_edits: Vec::new(),
+            row_edits_start: 0,
         }
     }
 }
@@ -1102,7 +1524,11 @@ impl From<String> for Buffer {
             history: vec![Rope::from(s)],
             settings: Rc::new(RefCell::new(Settings::default())),
             tree_sitter_info: None,
+            injections: RefCell::new(InjectionLayer::default()),
+            local_settings: LocalSettings::default(),
             version: 0,
+            row_edits: Vec::new(),
+            row_edits_start: 0,
         }
     }
 }
@@ -1114,7 +1540,11 @@ impl From<&String> for Buffer {
             history: vec![Rope::from(s.as_str())],
             settings: Rc::new(RefCell::new(Settings::default())),
             tree_sitter_info: None,
+            injections: RefCell::new(InjectionLayer::default()),
+            local_settings: LocalSettings::default(),
             version: 0,
+            row_edits: Vec::new(),
+            row_edits_start: 0,
         }
     }
 }
@@ -1135,14 +1565,15 @@ impl fmt::Display for BufferSlice<'_> {
 
 pub struct BufferSlice<'a> {
     pub slice: RopeSlice<'a>,
-    pub settings: Rc<RefCell<Settings>>,
+    /// The width of a tab in the buffer that the slice is from
+    pub tab_size: usize,
 }
 
 impl<'a> BufferSlice<'a> {
-    pub fn new(slice: RopeSlice<'a>, settings: Rc<RefCell<Settings>>) -> Self {
+    pub fn new(slice: RopeSlice<'a>, tab_size: usize) -> Self {
         Self {
             slice,
-            settings,
+            tab_size,
         }
     }
 
@@ -1156,7 +1587,7 @@ impl<'a> BufferSlice<'a> {
 
     pub fn len(&self) -> usize {
         self.slice.chars().map(|c| if c == '\t' {
-            self.settings.borrow().editor_settings.tab_size as usize
+            self.tab_size
         } else {
             1
         }).sum()
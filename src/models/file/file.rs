@@ -1,20 +1,27 @@
 use std::cell::RefCell;
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashMap};
 use std::fmt;
 use std::fmt::Formatter;
 use std::io::{Read, Write};
+use std::ops::Range;
 use std::path::PathBuf;
 use std::rc::Rc;
-use tree_sitter::Parser;
-use crate::models::file::buffer::Buffer;
+use regex::Regex;
+use tree_sitter::{Node, Parser, Point, Query, QueryCursor};
+use crate::models::file::buffer::{Buffer, RowEdit};
+use crate::models::file::language;
+use crate::models::file::search::Substitution;
+use crate::models::file::structural::{self, StructuralMatch, StructuralPattern};
+use crate::models::file::symbol::{Symbol, SymbolKind};
 use crate::models::settings::Settings;
+use crate::models::settings::editor_settings::LocalSettings;
 use crate::models::style::{StyledLine, StyledSpan, StyledText};
 
 #[derive(Debug)]
 pub enum FileError {
     FileDoesNotExist,
     Directory,
-    RecoverFileFound(File),
+    RecoverFileFound(Box<File>),
 }
 pub trait ReplaceSelections<S> {
     fn replace_selections(&mut self, selections: S);
@@ -25,6 +32,38 @@ pub trait InsertPairs<P> {
 }
 
 
+/// The fold regions of a buffer along with the buffer version they were computed for.
+type FoldCache = Option<(usize, Vec<(usize, usize)>)>;
+
+/// The closed folds along with the buffer version their rows are for.
+type ClosedFolds = (usize, BTreeSet<(usize, usize)>);
+
+/// The syntax error ranges of a buffer along with the buffer version they were found in.
+type SyntaxErrorCache = Option<(usize, Vec<Range<usize>>)>;
+
+/// The symbols of a buffer along with the buffer version they were found in.
+type SymbolCache = Option<(usize, Vec<Symbol>)>;
+
+/// The nesting depth of every bracket in a buffer along with the buffer version they were counted in.
+type DelimiterCache = Option<(usize, Rc<HashMap<usize, usize>>)>;
+
+/// The pair found at a byte offset along with the buffer version and the byte offset it was looked up for.
+type PairCache = Option<(usize, usize, Option<(Range<usize>, Range<usize>)>)>;
+
+/// The words of node kinds that open a scope worth pinning for sticky scroll, like `function_item` or `if_statement`.
+const SCOPE_KIND_WORDS: &[&str] = &[
+    "function", "method", "closure", "lambda", "impl", "class", "struct", "enum", "trait", "interface", "mod",
+    "module", "namespace", "if", "else", "for", "while", "loop", "match", "switch", "case", "try",
+];
+
+/// Whether a node kind opens a scope, which is when one of the words of its name is a scope word.
+fn is_scope_kind(kind: &str) -> bool {
+    kind.split('_').any(|word| SCOPE_KIND_WORDS.contains(&word))
+}
+
+/// How many bytes of rows a search for the next match copies at first, doubling with every step that finds nothing.
+const SEARCH_STEP: usize = 1 << 16;
+
 #[derive(Debug)]
 pub struct LSPInfo {
 
@@ -50,8 +89,30 @@ pub struct File {
     lsp_info: Option<LSPInfo>,
     settings: Rc<RefCell<Settings>>,
     highlights: BTreeSet<usize>,
+    /// The last search, whose matches are highlighted in the rows that are drawn
+    search_highlight: Option<Regex>,
+    /// The compiled indent query of the language
+    indent_query: Option<Query>,
+    /// The compiled tags query of the language, which finds the symbols
+    tags_query: Option<Query>,
+    /// The compiled fold query of the language
+    fold_query: Option<Query>,
+    /// The fold regions that are closed, stored as inclusive row ranges
+    closed_folds: RefCell<ClosedFolds>,
+    /// The fold regions for a buffer version
+    fold_cache: RefCell<FoldCache>,
+    /// The syntax errors for a buffer version
+    syntax_error_cache: RefCell<SyntaxErrorCache>,
+    /// The symbols for a buffer version
+    symbol_cache: RefCell<SymbolCache>,
+    /// The bracket depths for a buffer version
+    delimiter_cache: RefCell<DelimiterCache>,
+    /// The matching pair at the last byte offset that was looked up for a buffer version
+    pair_cache: RefCell<PairCache>,
     saved: bool,
     safe_close: bool,
+    /// Whether the file is generated text that can't be written, like the list of key bindings
+    read_only: bool,
 }
 
 impl File {
@@ -250,6 +311,20 @@ impl File {
                     }
                 };
 
+                if let Some(query) = language.as_deref().and_then(language::get_injection_query) {
+                    // The bundled queries are written for these grammars, so an error only leaves out the injections
+                    buffer.set_injection_query(query).ok();
+                }
+
+                // The queries are compiled once here rather than every time the buffer changes
+                let compile_query = |get_query: fn(&str) -> Option<&'static str>| {
+                    let query = language.as_deref().and_then(get_query)?;
+                    Query::new(buffer.get_tree()?.language(), query).ok()
+                };
+                let indent_query = compile_query(language::get_indent_query);
+                let tags_query = compile_query(language::get_tags_query);
+                let fold_query = compile_query(language::get_fold_query);
+
                 buffer.add_new_rope();
                 buffer.add_new_rope();
 
@@ -263,12 +338,23 @@ impl File {
                     language,
                     settings,
                     highlights: BTreeSet::new(),
+                    search_highlight: None,
+                    indent_query,
+                    tags_query,
+                    fold_query,
+                    closed_folds: RefCell::new((0, BTreeSet::new())),
+                    fold_cache: RefCell::new(None),
+                    syntax_error_cache: RefCell::new(None),
+                    symbol_cache: RefCell::new(None),
+                    delimiter_cache: RefCell::new(None),
+                    pair_cache: RefCell::new(None),
                     saved: true,
                     safe_close: false,
+                    read_only: false,
                 };
 
                 if recovered_file {
-                    Err(FileError::RecoverFileFound(file))
+                    Err(FileError::RecoverFileFound(Box::new(file)))
                 } else {
                     return Ok(file);
                 }
@@ -285,8 +371,19 @@ impl File {
                     language: None,
                     settings,
                     highlights: BTreeSet::new(),
+                    search_highlight: None,
+                    indent_query: None,
+                    tags_query: None,
+                    fold_query: None,
+                    closed_folds: RefCell::new((0, BTreeSet::new())),
+                    fold_cache: RefCell::new(None),
+                    syntax_error_cache: RefCell::new(None),
+                    symbol_cache: RefCell::new(None),
+                    delimiter_cache: RefCell::new(None),
+                    pair_cache: RefCell::new(None),
                     saved: true,
                     safe_close: false,
+                    read_only: false,
                 })
             }
         }
@@ -299,7 +396,18 @@ impl File {
         self.path = Some(path);
     }
 
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
     pub fn save(&mut self, file_path: Option<PathBuf>, force: bool) -> Result<(), String> {
+        if self.read_only {
+            return Err("This buffer is read-only".to_string());
+        }
         match file_path {
             Some(path) => {
                 match &mut self.path {
@@ -345,12 +453,29 @@ impl File {
         self.settings.clone()
     }
 
+    pub fn get_local_settings(&self) -> LocalSettings {
+        self.buffer.get_local_settings()
+    }
+
+    pub fn set_local_settings(&mut self, local_settings: LocalSettings) {
+        self.buffer.set_local_settings(local_settings);
+    }
+
+    /// The width of a tab in this file, which `:setlocal` can change from the global setting.
+    pub fn get_tab_size(&self) -> u8 {
+        self.buffer.get_tab_size()
+    }
+
+    pub fn get_use_spaces(&self) -> bool {
+        self.buffer.get_use_spaces()
+    }
+
     pub fn get_path(&self) -> Option<PathBuf> {
         self.path.clone()
     }
 
     pub fn has_saved(&self) -> bool {
-        self.saved
+        self.saved || self.read_only
     }
 
     pub fn get_byte(&self, index: usize) -> u8 {
@@ -367,6 +492,15 @@ impl File {
     pub fn get_word(&self, byte_offset: usize) -> Option<String> {
         self.buffer.get_word(byte_offset).map(|word| word.to_string())
     }
+    /// Returns the text between two byte offsets, or `None` if they don't fall on character boundaries.
+    pub fn get_slice(&self, start: usize, end: usize) -> Option<String> {
+        self.buffer.to_string().get(start..end).map(str::to_string)
+    }
+
+    pub fn get_word_range(&self, byte_offset: usize) -> Option<Range<usize>> {
+        self.buffer.get_word_range(byte_offset)
+    }
+
     pub fn get_until_next_word(&self, byte_offset: usize) -> Option<String> {
         self.buffer.get_until_next_word(byte_offset).map(|word| word.to_string())
     }
@@ -491,6 +625,19 @@ impl File {
         start
     }
 
+    pub fn get_byte_count(&self) -> usize {
+        self.buffer.get_byte_count()
+    }
+
+    pub fn get_version(&self) -> usize {
+        self.buffer.get_version()
+    }
+
+    /// Returns how the rows moved since `version`, or `None` if they moved in a way that can't be followed, like an undo.
+    pub fn get_row_edits(&self, version: usize) -> Option<Vec<RowEdit>> {
+        self.buffer.get_row_edits(version)
+    }
+
     pub fn get_line_count(&self) -> usize {
         self.buffer.get_line_count()
     }
@@ -501,6 +648,12 @@ impl File {
 
     pub fn clear_highlights(&mut self) {
         self.highlights.clear();
+        self.search_highlight = None;
+    }
+
+    /// Highlights the matches of a search wherever the file is drawn, which only looks for them in the visible rows.
+    pub fn set_search_highlight(&mut self, regex: Regex) {
+        self.search_highlight = Some(regex);
     }
 
 
@@ -520,36 +673,926 @@ impl File {
         }
     }
 
-    pub fn find(&mut self, _col: usize, row: usize, string: &str, down: bool) -> BTreeSet<usize> {
-        let range = if down {
-            row..self.buffer.get_line_count()
+    /// Returns the byte ranges of every match of a regular expression in the whole buffer.
+    /// Matches can span lines and empty matches are left out.
+    pub fn find_regex(&self, regex: &Regex) -> Vec<Range<usize>> {
+        self.find_regex_in(regex, 0..self.buffer.get_byte_count())
+    }
+
+    /// Returns the byte ranges of the matches of a regular expression that lie in `range`,
+    /// which should start and end at the start of a row.
+    pub fn find_regex_in(&self, regex: &Regex, range: Range<usize>) -> Vec<Range<usize>> {
+        let Some(text) = self.buffer.get_slice(range.start, range.end) else {
+            return Vec::new();
+        };
+        regex.find_iter(&text.to_string())
+            .map(|found| found.start() + range.start..found.end() + range.start)
+            .filter(|range| !range.is_empty())
+            .collect()
+    }
+
+    /// Returns where the row holding `byte` starts.
+    pub fn get_row_start(&self, byte: usize) -> usize {
+        match self.buffer.get_cursor_from_byte_offset(byte) {
+            Some((_, row)) => self.buffer.get_byte_offset(0, row).unwrap_or(byte),
+            None => byte,
+        }
+    }
+
+    /// Returns where the row after the one holding `byte` starts, or the end of the buffer.
+    pub fn get_next_row_start(&self, byte: usize) -> usize {
+        let len = self.buffer.get_byte_count();
+        match self.buffer.get_cursor_from_byte_offset(byte) {
+            Some((_, row)) => self.buffer.get_byte_offset(0, row + 1).unwrap_or(len),
+            None => len,
+        }
+    }
+
+    /// Finds the first match that starts after `byte`, or the last one that starts before it when going backward.
+    /// The search wraps around the ends of the file; the returned flag tells if it did.
+    pub fn find_next_regex(&self, regex: &Regex, byte: usize, forward: bool) -> Option<(Range<usize>, bool)> {
+        let len = self.buffer.get_byte_count();
+        let row_start = self.get_row_start(byte.min(len));
+        let row_end = self.get_next_row_start(byte);
+
+        if forward {
+            self.find_regex_forward(regex, row_start..len, |found| found.start > byte).map(|found| (found, false))
+                .or_else(|| self.find_regex_forward(regex, 0..row_end, |_| true).map(|found| (found, true)))
         } else {
-            0..row
+            self.find_regex_backward(regex, 0..row_end, |found| found.start < byte).map(|found| (found, false))
+                .or_else(|| self.find_regex_backward(regex, row_start..len, |_| true).map(|found| (found, true)))
+        }
+    }
+
+    /// Finds the first accepted match in the rows of `range`, which are searched in growing steps from its start
+    /// so that a nearby match doesn't copy the whole buffer.
+    fn find_regex_forward(&self, regex: &Regex, range: Range<usize>, accept: impl Fn(&Range<usize>) -> bool) -> Option<Range<usize>> {
+        let mut start = range.start;
+        let mut step = SEARCH_STEP;
+        while start < range.end {
+            let end = self.get_next_row_start(start + step).min(range.end);
+            let found = self.find_regex_in(regex, start..end).into_iter().find(&accept);
+            if found.is_some() {
+                return found;
+            }
+            start = end;
+            step *= 2;
+        }
+        None
+    }
+
+    /// Finds the last accepted match in the rows of `range`, which are searched in growing steps from its end.
+    fn find_regex_backward(&self, regex: &Regex, range: Range<usize>, accept: impl Fn(&Range<usize>) -> bool) -> Option<Range<usize>> {
+        let mut end = range.end;
+        let mut step = SEARCH_STEP;
+        while end > range.start {
+            let start = self.get_row_start(end.saturating_sub(step)).max(range.start);
+            let found = self.find_regex_in(regex, start..end).into_iter().rev().find(&accept);
+            if found.is_some() {
+                return found;
+            }
+            end = start;
+            step *= 2;
+        }
+        None
+    }
+
+    /// Returns the edits that a substitution makes to the lines between `first_row` and `last_row`.
+    pub fn find_substitutions(&self, substitution: &Substitution, first_row: usize, last_row: usize) -> Vec<(Range<usize>, String)> {
+        let start = self.buffer.get_byte_offset(0, first_row).unwrap_or(0);
+        let end = self.buffer.get_byte_offset(0, last_row + 1).unwrap_or(self.buffer.get_byte_count());
+
+        match self.buffer.get_slice(start, end) {
+            Some(text) => substitution.find_edits(&text.to_string(), start),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the last row that can hold text, which leaves out the empty row after a final newline.
+    pub fn get_last_row(&self) -> usize {
+        let count = self.get_line_count();
+        if count > 1 && self.buffer.get_row(count - 1).is_none() {
+            count - 2
+        } else {
+            count.saturating_sub(1)
+        }
+    }
+
+    /// Returns the text of the rows from `first_row` to `last_row` without their line endings.
+    pub fn get_rows(&self, first_row: usize, last_row: usize) -> Vec<String> {
+        (first_row..=last_row)
+            .filter_map(|row| self.get_line(row))
+            .map(|line| line.strip_suffix('\n').unwrap_or(&line).to_string())
+            .collect()
+    }
+
+    /// Replaces the rows from `first_row` to `last_row` with `rows` as a single undo step.
+    /// Replacing them with no rows deletes them.
+    pub fn replace_rows(&mut self, first_row: usize, last_row: usize, rows: Vec<String>) {
+        let mut start = self.buffer.get_byte_offset(0, first_row).unwrap_or(0);
+        let end = self.buffer.get_byte_offset(0, last_row + 1).unwrap_or(start);
+        let ends_with_newline = end > start && self.buffer.get_nth_byte(end - 1) == Some(b'\n');
+
+        let mut text = rows.iter().map(|row| format!("{}\n", row)).collect::<String>();
+        if !ends_with_newline {
+            text.pop();
+            // Deleting the last rows also deletes the newline that ended the row before them
+            if rows.is_empty() && start > 0 {
+                start -= 1;
+            }
+        }
+
+        self.buffer.replace_ranges(vec![(start..end, text)]);
+        self.saved = false;
+    }
+
+    /// Inserts `rows` as lines before `row`, or after the last line when `row` is past it.
+    pub fn insert_rows(&mut self, row: usize, rows: Vec<String>) {
+        let insertion = self.insert_rows_edit(row, &rows);
+        self.buffer.replace_ranges(vec![insertion]);
+        self.saved = false;
+    }
+
+    /// Moves the rows from `first_row` to `last_row` before `row`, counted from before the move, as a single undo step.
+    /// The lines are deleted and inserted again rather than rewritten, so that the lines in between only shift.
+    pub fn move_rows(&mut self, first_row: usize, last_row: usize, row: usize) {
+        if row >= first_row && row <= last_row + 1 {
+            return;
+        }
+        let rows = self.get_rows(first_row, last_row);
+        let insertion = self.insert_rows_edit(row, &rows);
+        let deletion = (self.delete_rows_range(first_row, last_row), String::new());
+        // The insertion comes first so that it is kept when both start at the same byte
+        self.buffer.replace_ranges(vec![insertion, deletion]);
+        self.saved = false;
+    }
+
+    /// Returns the edit that inserts `rows` as lines before `row`, or after the last line when `row` is past it.
+    fn insert_rows_edit(&self, row: usize, rows: &[String]) -> (Range<usize>, String) {
+        let start = self.buffer.get_byte_offset(0, row).unwrap_or(0);
+        let text = rows.iter().map(|row| format!("{}\n", row)).collect::<String>();
+        if start > 0 && !self.buffer.has_text_at(start - 1, "\n") {
+            // The last line has no newline, so the rows get the newline before them instead
+            return (start..start, format!("\n{}", &text[..text.len().saturating_sub(1)]));
+        }
+        (start..start, text)
+    }
+
+    /// Returns the range that deleting the rows from `first_row` to `last_row` removes.
+    fn delete_rows_range(&self, first_row: usize, last_row: usize) -> Range<usize> {
+        let start = self.buffer.get_byte_offset(0, first_row).unwrap_or(0);
+        let end = self.buffer.get_byte_offset(0, last_row + 1).unwrap_or(start);
+        // Deleting the last rows also deletes the newline that ended the row before them
+        if end > start && start > 0 && !self.buffer.has_text_at(end - 1, "\n") {
+            return start - 1..end;
+        }
+        start..end
+    }
+
+    /// Indents the non-blank rows from `first_row` to `last_row` by `levels`, or outdents them when it is negative.
+    pub fn shift_rows(&mut self, first_row: usize, last_row: usize, levels: isize) {
+        let mut edits = Vec::new();
+        for row in first_row..=last_row {
+            if self.get_line(row).unwrap_or_default().trim().is_empty() {
+                continue;
+            }
+            let current = self.get_indentation(row);
+            let indentation = self.shift_indentation(&current, levels);
+            if current != indentation {
+                let start = self.buffer.get_byte_offset(0, row).unwrap_or(0);
+                edits.push((start..start + current.len(), indentation));
+            }
+        }
+
+        if !edits.is_empty() {
+            self.buffer.replace_ranges(edits);
+            self.saved = false;
+        }
+    }
+
+    /// Returns the byte range of the smallest syntax node that strictly contains `start..end`.
+    /// The returned range is exclusive at the end.
+    pub fn get_enclosing_node(&self, start: usize, end: usize) -> Option<(usize, usize)> {
+        // Nodes of an injected language come first, then the string or block that holds them
+        if let Some(injection) = self.buffer.get_injection_at(start, end) {
+            if let Some(range) = Self::enclosing_node_in(&injection.tree, start, end) {
+                return Some(range);
+            }
+        }
+
+        Self::enclosing_node_in(self.buffer.get_tree()?, start, end)
+    }
+
+    fn enclosing_node_in(tree: &tree_sitter::Tree, start: usize, end: usize) -> Option<(usize, usize)> {
+        let mut node = tree.root_node().descendant_for_byte_range(start, end)?;
+        loop {
+            let node_start = node.start_byte();
+            let node_end = node.end_byte();
+            if node_start <= start && node_end >= end && (node_start < start || node_end > end) {
+                return Some((node_start, node_end));
+            }
+            node = node.parent()?;
+        }
+    }
+
+    /// Returns the language of the text at the byte offset,
+    /// which is the injected language when the offset is inside an injection.
+    pub fn get_language_at(&self, byte_offset: usize) -> Option<&str> {
+        match self.buffer.get_injection_at(byte_offset, byte_offset) {
+            Some(injection) => Some(injection.language),
+            None => self.language.as_deref(),
+        }
+    }
+
+    /// Returns the byte ranges of the `ERROR` and `MISSING` nodes in the syntax tree sorted by their start.
+    /// An error range only covers the first row of its node since an `ERROR` node can swallow the rest of the file,
+    /// and a missing node covers the character it should have come before.
+    pub fn get_syntax_errors(&self) -> Vec<Range<usize>> {
+        let version = self.buffer.get_version();
+        if let Some((cached_version, errors)) = self.syntax_error_cache.borrow().as_ref() {
+            if *cached_version == version {
+                return errors.clone();
+            }
+        }
+
+        let mut errors = Vec::new();
+        if let Some(tree) = self.buffer.get_tree() {
+            let mut stack = vec![tree.root_node()];
+            while let Some(node) = stack.pop() {
+                if node.is_missing() {
+                    let start = node.start_byte();
+                    let end = self.buffer.get_char_at(start).map(|c| start + c.len_utf8()).unwrap_or(start);
+                    errors.push(start..end);
+                } else if node.is_error() {
+                    let start = node.start_byte();
+                    let row = node.start_position().row;
+                    let row_end = self.buffer.get_byte_offset(self.get_row_len(row).unwrap_or(0), row).unwrap_or(node.end_byte());
+                    errors.push(start..node.end_byte().min(row_end).max(start + 1));
+                } else if node.has_error() {
+                    let mut cursor = node.walk();
+                    stack.extend(node.children(&mut cursor));
+                }
+            }
+        }
+        errors.sort_by(|a, b| a.start.cmp(&b.start).then(a.end.cmp(&b.end)));
+        errors.dedup();
+
+        *self.syntax_error_cache.borrow_mut() = Some((version, errors.clone()));
+        errors
+    }
+
+    /// Returns the rows that contain the start of a syntax error.
+    pub fn get_syntax_error_rows(&self) -> BTreeSet<usize> {
+        self.get_syntax_errors().iter()
+            .filter_map(|error| self.get_cursor(error.start).map(|(_, row)| row))
+            .collect()
+    }
+
+    pub fn has_syntax_tree(&self) -> bool {
+        self.buffer.get_tree().is_some()
+    }
+
+    /// Returns the start of the first syntax error after the byte offset, wrapping around to the start of the file.
+    pub fn next_syntax_error(&self, byte_offset: usize) -> Option<usize> {
+        let errors = self.get_syntax_errors();
+        errors.iter().map(|error| error.start).find(|start| *start > byte_offset)
+            .or(errors.first().map(|error| error.start))
+    }
+
+    /// Returns the start of the last syntax error before the byte offset, wrapping around to the end of the file.
+    pub fn prev_syntax_error(&self, byte_offset: usize) -> Option<usize> {
+        let errors = self.get_syntax_errors();
+        errors.iter().rev().map(|error| error.start).find(|start| *start < byte_offset)
+            .or(errors.last().map(|error| error.start))
+    }
+
+    /// Returns the definitions in the file in the order they appear, found with the language's tags query.
+    /// Each symbol knows how deeply it is nested in the other symbols, so the list reads as an outline.
+    pub fn get_symbols(&self) -> Vec<Symbol> {
+        let version = self.buffer.get_version();
+        if let Some((cached_version, symbols)) = self.symbol_cache.borrow().as_ref() {
+            if *cached_version == version {
+                return symbols.clone();
+            }
+        }
+
+        let symbols = self.find_symbols().unwrap_or_default();
+
+        *self.symbol_cache.borrow_mut() = Some((version, symbols.clone()));
+        symbols
+    }
+
+    fn find_symbols(&self) -> Option<Vec<Symbol>> {
+        let tree = self.buffer.get_tree()?;
+        let query = self.tags_query.as_ref()?;
+        let name_index = query.capture_index_for_name("name");
+
+        let mut cursor = QueryCursor::new();
+        let text = |range: Range<usize>| self.buffer.get_slice(range.start, range.end).map(|slice| slice.to_string()).unwrap_or_default();
+
+        let mut symbols: Vec<Symbol> = Vec::new();
+        for query_match in cursor.matches(query, tree.root_node(), |node: Node| self.buffer.get_chunks(node.byte_range())) {
+            let Some((definition, kind)) = query_match.captures.iter().find_map(|capture| {
+                SymbolKind::from_capture(&query.capture_names()[capture.index as usize]).map(|kind| (capture.node, kind))
+            }) else {
+                continue;
+            };
+            let name = query_match.captures.iter().find(|capture| Some(capture.index) == name_index).map(|capture| capture.node);
+
+            // Definitions without a name, like impl blocks, are named by their first line
+            let (name, name_start) = match name {
+                Some(node) => (text(node.byte_range()), node.start_byte()),
+                None => {
+                    let start = definition.start_byte();
+                    let header = text(start..self.get_next_row_start(start).min(definition.end_byte()));
+                    let header = header.lines().next().unwrap_or_default();
+                    (header.trim().trim_end_matches(['{', ':']).trim_end().to_string(), start)
+                }
+            };
+
+            let range = definition.byte_range();
+            match symbols.iter_mut().find(|symbol| symbol.range == range) {
+                Some(symbol) => {
+                    if kind.specificity() > symbol.kind.specificity() {
+                        symbol.kind = kind;
+                    }
+                }
+                None => symbols.push(Symbol {
+                    name,
+                    kind,
+                    range,
+                    name_start,
+                    depth: 0,
+                }),
+            }
+        }
+
+        symbols.sort_by(|a, b| a.range.start.cmp(&b.range.start).then(b.range.end.cmp(&a.range.end)));
+
+        let mut parents: Vec<Range<usize>> = Vec::new();
+        for symbol in symbols.iter_mut() {
+            while parents.last().is_some_and(|parent| parent.end < symbol.range.end) {
+                parents.pop();
+            }
+            symbol.depth = parents.len();
+            parents.push(symbol.range.clone());
+        }
+
+        Some(symbols)
+    }
+
+    /// Returns the index of the innermost symbol that contains the byte offset.
+    pub fn get_symbol_at(&self, byte_offset: usize) -> Option<usize> {
+        self.get_symbols().iter().enumerate()
+            .filter(|(_, symbol)| symbol.range.contains(&byte_offset))
+            .max_by_key(|(_, symbol)| symbol.depth)
+            .map(|(index, _)| index)
+    }
+
+    /// Returns every fold region in the file as inclusive row ranges sorted by their start row.
+    /// The regions come from the language's fold query or from indentation for plain text.
+    pub fn get_fold_regions(&self) -> Vec<(usize, usize)> {
+        let version = self.buffer.get_version();
+        if let Some((cached_version, regions)) = self.fold_cache.borrow().as_ref() {
+            if *cached_version == version {
+                return regions.clone();
+            }
+        }
+
+        let mut regions = match self.syntax_fold_regions() {
+            Some(regions) => regions,
+            None => self.indentation_fold_regions(),
         };
-        for y in range {
-            if let Some(line) = self.buffer.get_row(y) {
-                let mut line = line.to_string();
-                let mut split_bits = 0;
-                while let Some(index) = line.find(string) {
-
-                    let index = index + split_bits;
-
-
-                    let range =  self.buffer.get_byte_offset(index, y)
-                        .expect("Invalid byte offset")
-                        ..
-                        self.buffer.get_byte_offset(index, y)
-                            .expect("Invalid byte offset") +
-                            string.len();
-                    for b in range {
-                        self.highlights.insert(b);
+        regions.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+        regions.dedup();
+
+        *self.fold_cache.borrow_mut() = Some((version, regions.clone()));
+        regions
+    }
+
+    fn syntax_fold_regions(&self) -> Option<Vec<(usize, usize)>> {
+        let tree = self.buffer.get_tree()?;
+        let query = self.fold_query.as_ref()?;
+        let mut cursor = QueryCursor::new();
+
+        let mut regions = Vec::new();
+        for query_match in cursor.matches(query, tree.root_node(), |node: Node| self.buffer.get_chunks(node.byte_range())) {
+            for capture in query_match.captures {
+                let start = capture.node.start_position().row;
+                let mut end = capture.node.end_position().row;
+                if capture.node.end_position().column == 0 {
+                    end = end.saturating_sub(1);
+                }
+                if end > start {
+                    regions.push((start, end));
+                }
+            }
+        }
+
+        Some(regions)
+    }
+
+    fn indentation_fold_regions(&self) -> Vec<(usize, usize)> {
+        let tab_size = self.buffer.get_tab_size() as usize;
+
+        let indents = (0..self.get_line_count()).map(|row| {
+            let line = self.buffer.get_row(row)?.to_string();
+            if line.trim().is_empty() {
+                return None;
+            }
+            Some(line.chars().take_while(|c| c.is_whitespace()).map(|c| if c == '\t' {
+                tab_size
+            } else {
+                1
+            }).sum::<usize>())
+        }).collect::<Vec<Option<usize>>>();
+
+        let mut regions = Vec::new();
+        for (row, indent) in indents.iter().enumerate() {
+            let Some(indent) = indent else {
+                continue;
+            };
+
+            let mut end = row;
+            for (next_row, next_indent) in indents.iter().enumerate().skip(row + 1) {
+                match next_indent {
+                    None => {},
+                    Some(next_indent) if next_indent > indent => end = next_row,
+                    Some(_) => break,
+                }
+            }
+
+            if end > row {
+                regions.push((row, end));
+            }
+        }
+
+        regions
+    }
+
+    /// Moves the closed folds along with the rows that were added and removed since they were last used.
+    /// Folds that an edit replaced the first or last row of are opened,
+    /// and so are all of them when the buffer changed in a way that can't be followed, like an undo.
+    fn follow_closed_folds(&self) -> std::cell::RefMut<'_, BTreeSet<(usize, usize)>> {
+        let version = self.buffer.get_version();
+        std::cell::RefMut::map(self.closed_folds.borrow_mut(), |(folds_version, folds)| {
+            if *folds_version != version {
+                match self.buffer.get_row_edits(*folds_version) {
+                    Some(edits) => {
+                        *folds = folds.iter()
+                            .filter_map(|fold| edits.iter().try_fold(*fold, |fold, edit| edit.follow_region(fold)))
+                            // A fold that was shrunk down to its first row has nothing left to hide
+                            .filter(|(start, end)| start < end)
+                            .collect();
                     }
-                    split_bits += string.len();
-                    line = line.split_off(string.len());
+                    None => folds.clear(),
                 }
+                *folds_version = version;
             }
+            folds
+        })
+    }
+
+    /// Returns the closed folds that are not inside of another closed fold.
+    pub fn get_closed_folds(&self) -> Vec<(usize, usize)> {
+        let closed_folds = self.follow_closed_folds();
+        if closed_folds.is_empty() {
+            return Vec::new();
+        }
+
+        let mut folds: Vec<(usize, usize)> = Vec::new();
+        for region in self.get_fold_regions() {
+            if !closed_folds.contains(&region) {
+                continue;
+            }
+            match folds.last() {
+                Some((_, end)) if region.0 <= *end => {},
+                _ => folds.push(region),
+            }
+        }
+        folds
+    }
+
+    /// Returns the outermost closed fold that contains `row`.
+    pub fn get_closed_fold(&self, row: usize) -> Option<(usize, usize)> {
+        self.get_closed_folds().into_iter().find(|(start, end)| *start <= row && row <= *end)
+    }
+
+    pub fn close_fold(&mut self, row: usize) -> Result<(), String> {
+        let regions = self.get_fold_regions();
+        let mut closed_folds = self.follow_closed_folds();
+        let region = regions.into_iter()
+            .filter(|region| region.0 <= row && row <= region.1 && !closed_folds.contains(region))
+            .min_by_key(|(start, end)| end - start);
+
+        match region {
+            Some(region) => {
+                closed_folds.insert(region);
+                Ok(())
+            }
+            None => Err(String::from("No fold found")),
+        }
+    }
+
+    pub fn open_fold(&mut self, row: usize) -> Result<(), String> {
+        match self.get_closed_fold(row) {
+            Some(region) => {
+                self.follow_closed_folds().remove(&region);
+                Ok(())
+            }
+            None => Err(String::from("No fold found")),
+        }
+    }
+
+    pub fn toggle_fold(&mut self, row: usize) -> Result<(), String> {
+        if self.get_closed_fold(row).is_some() {
+            self.open_fold(row)
+        } else {
+            self.close_fold(row)
+        }
+    }
+
+    pub fn open_all_folds(&mut self) {
+        self.follow_closed_folds().clear();
+    }
+
+    pub fn close_all_folds(&mut self) {
+        let regions = self.get_fold_regions();
+        *self.follow_closed_folds() = regions.into_iter().collect();
+    }
+
+    /// Returns the row that is displayed after `row`, skipping over closed folds.
+    pub fn next_visible_row(&self, row: usize) -> usize {
+        match self.get_closed_fold(row) {
+            Some((_, end)) => end + 1,
+            None => row + 1,
+        }
+    }
+
+    /// Returns the row that is displayed before `row`, skipping over closed folds.
+    pub fn prev_visible_row(&self, row: usize) -> usize {
+        let row = row.saturating_sub(1);
+        match self.get_closed_fold(row) {
+            Some((start, _)) => start,
+            None => row,
+        }
+    }
+
+    /// Returns the number of lines displayed for the rows from `start_row` up to but not including `end_row`.
+    pub fn count_visible_rows(&self, start_row: usize, end_row: usize) -> usize {
+        let mut count = end_row.saturating_sub(start_row);
+        for (start, end) in self.get_closed_folds() {
+            let hidden_start = (start + 1).max(start_row);
+            let hidden_end = (end + 1).min(end_row);
+            count -= hidden_end.saturating_sub(hidden_start);
+        }
+        count
+    }
+
+    /// Returns the rows that are displayed starting at `start_row`.
+    pub fn get_visible_rows(&self, start_row: usize, count: usize) -> Vec<usize> {
+        let line_count = self.get_line_count();
+        let mut row = match self.get_closed_fold(start_row) {
+            Some((start, _)) => start,
+            None => start_row,
+        };
+
+        let mut rows = Vec::new();
+        while rows.len() < count && row < line_count {
+            rows.push(row);
+            row = self.next_visible_row(row);
+        }
+        rows
+    }
+
+    /// Returns the first rows of the scopes whose headers have scrolled above the first line below them.
+    /// The rows are ordered from the outermost scope inward and hold at most `max` of the innermost scopes.
+    /// Since the header covers the top of the view, the scopes are looked up for the line that ends up right below it.
+    pub fn get_sticky_rows(&self, top_row: usize, max: usize) -> Vec<usize> {
+        if max == 0 || !self.has_syntax_tree() {
+            return Vec::new();
+        }
+
+        // Find the smallest header that fits the scopes of the line right below it
+        for (covered, row) in self.get_visible_rows(top_row, max + 1).into_iter().enumerate() {
+            let mut scopes = self.get_scope_rows(row);
+            scopes.drain(..scopes.len().saturating_sub(max));
+            if scopes.len() <= covered {
+                return scopes;
+            }
+        }
+        Vec::new()
+    }
+
+    /// Returns the starting rows of the scopes, like functions, impls and loops, that enclose a row and start above it.
+    /// The scopes are the ancestors of the row's first node in the syntax tree, from the outermost inward.
+    fn get_scope_rows(&self, row: usize) -> Vec<usize> {
+        let (Some(tree), Some(row_start)) = (self.buffer.get_tree(), self.buffer.get_byte_offset(0, row)) else {
+            return Vec::new();
+        };
+        let byte = row_start + self.get_indentation(row).len();
+
+        let mut rows = Vec::new();
+        let mut node = tree.root_node().descendant_for_byte_range(byte, byte);
+        while let Some(current) = node {
+            let start_row = current.start_position().row;
+            if start_row < row && is_scope_kind(current.kind()) && rows.last() != Some(&start_row) {
+                rows.push(start_row);
+            }
+            node = current.parent();
+        }
+        rows.reverse();
+        rows
+    }
+
+    /// Returns the leading whitespace of a row.
+    pub fn get_indentation(&self, row: usize) -> String {
+        match self.buffer.get_row(row) {
+            Some(line) => line.to_string().chars().take_while(|c| *c == ' ' || *c == '\t').collect(),
+            None => String::new(),
+        }
+    }
+
+    /// Returns the text that makes up one level of indentation.
+    pub fn get_indent_unit(&self) -> String {
+        if self.buffer.get_use_spaces() {
+            " ".repeat(self.buffer.get_tab_size() as usize)
+        } else {
+            String::from("\t")
+        }
+    }
+
+    fn shift_indentation(&self, indentation: &str, levels: isize) -> String {
+        let unit = self.get_indent_unit();
+        let mut indentation = indentation.to_string();
+
+        if levels > 0 {
+            indentation.push_str(&unit.repeat(levels as usize));
+        }
+        for _ in levels..0 {
+            if indentation.ends_with(&unit) {
+                indentation.truncate(indentation.len() - unit.len());
+            } else if indentation.ends_with('\t') {
+                indentation.pop();
+            } else {
+                let spaces = indentation.len() - indentation.trim_end_matches(' ').len();
+                indentation.truncate(indentation.len() - spaces.min(unit.len().max(1)));
+            }
+        }
+
+        indentation
+    }
+
+    /// Returns the indentation level of a row according to the language's indent query
+    /// and whether the row starts with a node that closes a level.
+    fn get_indent_level(&self, row: usize) -> Option<(usize, bool)> {
+        let tree = self.buffer.get_tree()?;
+        let query = self.indent_query.as_ref()?;
+        let indent = query.capture_index_for_name("indent");
+        let outdent = query.capture_index_for_name("outdent");
+
+        let first = self.buffer.get_byte_offset(0, row)? + self.get_indentation(row).len();
+
+        let mut cursor = QueryCursor::new();
+        cursor.set_point_range(Point::new(row, 0)..Point::new(row + 1, 0));
+
+        // Nodes that start on the same row only add one level
+        let mut start_rows = BTreeSet::new();
+        let mut outdented = false;
+        for query_match in cursor.matches(query, tree.root_node(), |node: Node| self.buffer.get_chunks(node.byte_range())) {
+            for capture in query_match.captures {
+                let node = capture.node;
+                if Some(capture.index) == indent && node.start_position().row < row && node.end_byte() > first {
+                    start_rows.insert(node.start_position().row);
+                }
+                if Some(capture.index) == outdent && node.start_byte() == first && !node.is_missing() {
+                    outdented = true;
+                }
+            }
+        }
+
+        Some((start_rows.len().saturating_sub(outdented as usize), outdented))
+    }
+
+    /// Returns the indentation for `row` after it was split off of the row above it.
+    /// The indentation of the row above is kept and a level is added or removed based on the syntax tree.
+    pub fn get_newline_indentation(&self, row: usize) -> String {
+        if row == 0 {
+            return String::new();
+        }
+        let indentation = self.get_indentation(row - 1);
+
+        let (Some(language), Some(_)) = (self.language.as_ref(), self.indent_query.as_ref()) else {
+            return indentation;
+        };
+
+        let (mut levels, outdented) = match (self.get_indent_level(row - 1), self.get_indent_level(row)) {
+            (Some((previous_level, _)), Some((level, outdented))) => {
+                let levels = level as isize - previous_level as isize;
+                if levels < 0 && !outdented {
+                    (0, outdented)
+                } else {
+                    (levels.clamp(-1, 1), outdented)
+                }
+            }
+            _ => (0, false),
+        };
+
+        // A block that is still being typed usually doesn't parse into a node, so fall back to how the line ends
+        if levels < 1 && !outdented {
+            let line = self.get_line(row - 1).unwrap_or_default();
+            let line = line.trim_end();
+            let in_comment_or_string = match (self.buffer.get_tree(), self.buffer.get_byte_offset(line.chars().count(), row - 1)) {
+                (Some(tree), Some(end)) if end > 0 => {
+                    tree.root_node().descendant_for_byte_range(end - 1, end)
+                        .map(|node| node.kind().contains("comment") || node.kind().contains("string"))
+                        .unwrap_or(false)
+                }
+                _ => false,
+            };
+            if !in_comment_or_string && language::get_indent_openers(language).iter().any(|opener| line.ends_with(opener)) {
+                levels = 1;
+            }
+        }
+
+        self.shift_indentation(&indentation, levels)
+    }
+
+    /// Returns the indentation `row` should have if it starts with a node that closes a level, like `}`.
+    /// `end_byte` is where that node has to end, which lets typing a closing delimiter dedent its line.
+    pub fn get_outdent_indentation(&self, row: usize, end_byte: usize) -> Option<String> {
+        let first = self.buffer.get_byte_offset(0, row)? + self.get_indentation(row).len();
+        let tree = self.buffer.get_tree()?;
+        let node = tree.root_node().descendant_for_byte_range(first, first)?;
+        if node.end_byte() != end_byte {
+            return None;
+        }
+
+        let (level, outdented) = self.get_indent_level(row)?;
+        if !outdented {
+            return None;
+        }
+
+        let anchor = (0..row).rev().find(|row| !self.get_line(*row).unwrap_or_default().trim().is_empty())?;
+        let (anchor_level, _) = self.get_indent_level(anchor)?;
+
+        Some(self.shift_indentation(&self.get_indentation(anchor), level as isize - anchor_level as isize))
+    }
+
+    /// Re-indents the rows from `start_row` to `end_row` as a single undo step.
+    /// Indentation is relative to the closest non-blank row above the range.
+    pub fn reindent(&mut self, start_row: usize, end_row: usize) -> Result<(), String> {
+        if self.indent_query.is_none() {
+            return Err(String::from("No indentation rules for this file"));
+        }
+
+        let anchor = (0..start_row).rev().find(|row| !self.get_line(*row).unwrap_or_default().trim().is_empty());
+        let (anchor_indentation, anchor_level) = match anchor {
+            Some(row) => {
+                let (level, _) = self.get_indent_level(row).unwrap_or((0, false));
+                (self.get_indentation(row), level)
+            }
+            None => (String::new(), 0),
+        };
+
+        let mut edits = Vec::new();
+        for row in start_row..=end_row.min(self.get_line_count().saturating_sub(1)) {
+            if self.get_line(row).unwrap_or_default().trim().is_empty() {
+                continue;
+            }
+            let Some((level, _)) = self.get_indent_level(row) else {
+                continue;
+            };
+
+            let indentation = self.shift_indentation(&anchor_indentation, level as isize - anchor_level as isize);
+            let current = self.get_indentation(row);
+            if current != indentation {
+                let start = self.buffer.get_byte_offset(0, row).unwrap_or(0);
+                edits.push((start..start + current.len(), indentation));
+            }
+        }
+
+        if !edits.is_empty() {
+            self.buffer.replace_ranges(edits);
+            self.saved = false;
+        }
+        Ok(())
+    }
+
+    /// Comments out the rows or removes their comments if every non-blank row is already commented.
+    /// Line comments are placed at the smallest indentation of the rows so that they line up,
+    /// and languages without line comments wrap the rows in a block comment instead.
+    pub fn toggle_comment(&mut self, start_row: usize, end_row: usize) -> Result<(), String> {
+        let end_row = end_row.min(self.get_line_count().saturating_sub(1));
+        let rows = (start_row..=end_row)
+            .filter(|row| !self.get_line(*row).unwrap_or_default().trim().is_empty())
+            .collect::<Vec<_>>();
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        // Rows inside an injection are commented with the injected language's syntax
+        let first = self.buffer.get_byte_offset(self.get_indentation(rows[0]).len(), rows[0]).unwrap_or(0);
+        let tokens = self.get_language_at(first)
+            .and_then(language::get_comment_tokens)
+            .ok_or(String::from("No comment syntax for this file"))?;
+
+        let edits = match (tokens.line, tokens.block) {
+            (Some(token), _) => self.toggle_line_comment(&rows, token),
+            (None, Some((open, close))) => self.toggle_block_comment(&rows, open, close),
+            (None, None) => return Err(String::from("No comment syntax for this file")),
+        };
+
+        if !edits.is_empty() {
+            self.buffer.replace_ranges(edits);
+            self.saved = false;
+        }
+        Ok(())
+    }
+
+    /// Finds the matches of a structural search pattern, a code template or a tree-sitter query, within `scope`.
+    pub fn find_structural(&self, pattern: &str, scope: Range<usize>) -> Result<Vec<StructuralMatch>, String> {
+        let tree = self.buffer.get_tree().ok_or(String::from("Structural search needs a language with a syntax tree"))?;
+        let pattern = StructuralPattern::new(pattern, tree.language())?;
+
+        Ok(pattern.find_matches(tree, &self.buffer.to_string(), scope))
+    }
+
+    /// Rewrites every match of a structural search pattern within `scope` using a replacement template.
+    /// All of the matches are replaced as a single undo step and the number of replacements is returned.
+    pub fn replace_structural(&mut self, pattern: &str, replacement: &str, scope: Range<usize>) -> Result<usize, String> {
+        let edits = self.find_structural(pattern, scope)?.into_iter()
+            .map(|found| Ok((found.range, structural::expand_template(replacement, &found.bindings)?)))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let count = edits.len();
+        if count > 0 {
+            self.buffer.replace_ranges(edits);
+            self.saved = false;
+        }
+        Ok(count)
+    }
+
+    fn toggle_line_comment(&self, rows: &[usize], token: &str) -> Vec<(Range<usize>, String)> {
+        let commented = rows.iter().all(|row| self.get_line(*row).unwrap_or_default().trim_start().starts_with(token));
+
+        let mut edits = Vec::new();
+        if commented {
+            for row in rows {
+                let line = self.get_line(*row).unwrap_or_default();
+                let indentation = self.get_indentation(*row).len();
+                let mut end = indentation + token.len();
+                if line[end..].starts_with(' ') {
+                    end += 1;
+                }
+                let start = self.buffer.get_byte_offset(0, *row).unwrap_or(0);
+                edits.push((start + indentation..start + end, String::new()));
+            }
+        } else {
+            let indentation = rows.iter().map(|row| self.get_indentation(*row).len()).min().unwrap_or(0);
+            for row in rows {
+                let start = self.buffer.get_byte_offset(0, *row).unwrap_or(0) + indentation;
+                edits.push((start..start, format!("{} ", token)));
+            }
+        }
+        edits
+    }
+
+    fn toggle_block_comment(&self, rows: &[usize], open: &str, close: &str) -> Vec<(Range<usize>, String)> {
+        let first = rows[0];
+        let last = rows[rows.len() - 1];
+        let first_line = self.get_line(first).unwrap_or_default();
+        let last_line = self.get_line(last).unwrap_or_default();
+        let first_line = first_line.trim_end_matches(['\n', '\r']);
+        let last_line = last_line.trim_end_matches(['\n', '\r']);
+        let first_start = self.buffer.get_byte_offset(0, first).unwrap_or(0);
+        let last_start = self.buffer.get_byte_offset(0, last).unwrap_or(0);
+
+        let indentation = self.get_indentation(first).len();
+        let content_end = last_line.trim_end().len();
+        let commented = first_line[indentation..].starts_with(open)
+            && last_line.trim_end().ends_with(close)
+            && (first != last || content_end - indentation >= open.len() + close.len());
+
+        if commented {
+            let mut open_end = indentation + open.len();
+            if first_line[open_end..].starts_with(' ') {
+                open_end += 1;
+            }
+            let mut close_start = content_end - close.len();
+            if close_start > 0 && last_line[..close_start].ends_with(' ') && (first != last || close_start > open_end) {
+                close_start -= 1;
+            }
+            vec![
+                (first_start + indentation..first_start + open_end, String::new()),
+                (last_start + close_start..last_start + content_end, String::new()),
+            ]
+        } else {
+            vec![
+                (first_start + indentation..first_start + indentation, format!("{} ", open)),
+                (last_start + content_end..last_start + content_end, format!(" {}", close)),
+            ]
         }
-        self.highlights.clone()
     }
 
     pub fn get_byte_offset(&self, row: usize, col: usize) -> Option<usize> {
@@ -565,6 +1608,22 @@ impl File {
         self.saved = false;
     }
 
+    pub fn insert_current<T>(&mut self, byte_offset: usize, c: T) where T: AsRef<str> {
+        self.buffer.insert_current(byte_offset, c);
+        self.saved = false;
+    }
+
+    /// Replaces each range with its text as a single undo step.
+    pub fn replace_ranges<T>(&mut self, edits: Vec<(std::ops::Range<usize>, T)>) where T: AsRef<str> {
+        self.buffer.replace_ranges(edits);
+        self.saved = false;
+    }
+
+    pub fn replace_ranges_current<T>(&mut self, edits: Vec<(std::ops::Range<usize>, T)>) where T: AsRef<str> {
+        self.buffer.replace_ranges_current(edits);
+        self.saved = false;
+    }
+
     pub fn insert_after_current<T>(&mut self, byte_offset: usize, c: T) where T: AsRef<str> {
         self.buffer.insert(byte_offset, c);
         self.saved = false;
@@ -626,6 +1685,15 @@ impl File {
         self.saved = false;
     }
 
+    /// Starts gathering the following edits into one undo step, which `end_undo_group` finishes.
+    pub fn start_undo_group(&mut self) -> usize {
+        self.buffer.start_undo_group()
+    }
+
+    pub fn end_undo_group(&mut self, start: usize) {
+        self.buffer.end_undo_group(start);
+    }
+
     pub fn next_word_front(&self, mut byte_position: usize, mut amount: usize) -> usize {
         while amount > 0 {
             byte_position = self.buffer.next_word_front(byte_position);
@@ -662,208 +1730,308 @@ impl File {
         byte_position
     }
 
-    fn is_delimiter(&self, b: usize) -> bool {
-        if let Some(c) = self.buffer.get_char_at(b) {
-            match c {
-                '(' => true,
-                ')' => true,
-                '[' => true,
-                ']' => true,
-                '{' => true,
-                '}' => true,
-                // Despite looking similar, they are different characters
-                '｛' => true,
-                '｝' => true,
-                '（' => true,
-                '）' => true,
-                '［' => true,
-                '］' => true,
-                '【' => true,
-                '】' => true,
-                '「' => true,
-                '」' => true,
-                '『' => true,
-                '』' => true,
-                '〝' => true,
-                '〞' => true,
-                '〈' => true,
-                '〉' => true,
-                '《' => true,
-                '》' => true,
-                '〔' => true,
-                '〕' => true,
-                '〖' => true,
-                '〗' => true,
-                '〘' => true,
-                '〙' => true,
-                '〚' => true,
-                '〛' => true,
-                '«' => true,
-                '»' => true,
-                '‹' => true,
-                '›' => true,
-                '"' => true,
-                '\'' => true,
-                '‘' => true,
-                '’' => true,
-                '“' => true,
-                '”' => true,
-                '⁅' => true,
-                '⁆' => true,
-                '〈' => true,
-                '〉' => true,
-                '⎡' => true,
-                '⎤' => true,
-                '⎣' => true,
-                '⎦' => true,
-                '⎢' => true,
-                '⎥' => true,
-                '⎧' => true,
-                '⎨' => true,
-                '⎩' => true,
-                '⎫' => true,
-                '⎬' => true,
-                '⎭' => true,
-                '⎪' => true,
-                '⎰' => true,
-                '⎱' => true,
-                '❬' => true,
-                '❭' => true,
-                '❮' => true,
-                '❯' => true,
-                '❰' => true,
-                '❱' => true,
-                '❴' => true,
-                '❵' => true,
-                '⟦' => true,
-                '⟧' => true,
-                '⟨' => true,
-                '⟩' => true,
-                '❲' => true,
-                '❳' => true,
-                '⦃' => true,
-                '⦄' => true,
-                '⦅' => true,
-                '⦆' => true,
-                '⦇' => true,
-                '⦈' => true,
-                '⦉' => true,
-                '⦊' => true,
-                '⦋' => true,
-                '⦌' => true,
-                '⦍' => true,
-                '⦎' => true,
-                '⦏' => true,
-                '⦐' => true,
-                '⦑' => true,
-                '⦒' => true,
-                '⦗' => true,
-                '⦘' => true,
-                '⧘' => true,
-                '⧙' => true,
-                '⧚' => true,
-                '⧛' => true,
-                '⧼' => true,
-                '⧽' => true,
-                '⸂' => true,
-                '⸃' => true,
-                '⸄' => true,
-                '⸅' => true,
-                '⸉' => true,
-                '⸊' => true,
-                '⸌' => true,
-                '⸍' => true,
-                '⸜' => true,
-                '⸝' => true,
-                '⸠' => true,
-                '⸡' => true,
-                '⸢' => true,
-                '⸣' => true,
-                '⸤' => true,
-                '⸥' => true,
-                '⸦' => true,
-                '⸧' => true,
-                '⸨' => true,
-                '⸩' => true,
-                //todo: add a way to have the user add more of these
-                _ => false,
+    /// The bracket pairs that are colored by rainbow delimiters and matched by pair jumps.
+    // Despite looking similar, some of these are different characters
+    const BRACKET_PAIRS: [(char, char); 58] = [
+        ('(', ')'),
+        ('{', '}'),
+        ('[', ']'),
+        ('｛', '｝'),
+        ('（', '）'),
+        ('［', '］'),
+        ('【', '】'),
+        ('「', '」'),
+        ('『', '』'),
+        ('〝', '〞'),
+        ('〈', '〉'),
+        ('《', '》'),
+        ('〔', '〕'),
+        ('〖', '〗'),
+        ('〘', '〙'),
+        ('〚', '〛'),
+        ('«', '»'),
+        ('‹', '›'),
+        ('‘', '’'),
+        ('“', '”'),
+        ('⁅', '⁆'),
+        ('〈', '〉'),
+        ('⎡', '⎤'),
+        ('⎢', '⎥'),
+        ('⎣', '⎦'),
+        ('⎧', '⎫'),
+        ('⎨', '⎬'),
+        ('⎩', '⎭'),
+        ('⎰', '⎱'),
+        ('❬', '❭'),
+        ('❮', '❯'),
+        ('❰', '❱'),
+        ('❴', '❵'),
+        ('⟦', '⟧'),
+        ('⟨', '⟩'),
+        ('❲', '❳'),
+        ('⦃', '⦄'),
+        ('⦅', '⦆'),
+        ('⦇', '⦈'),
+        ('⦉', '⦊'),
+        ('⦋', '⦌'),
+        ('⦍', '⦎'),
+        ('⦏', '⦐'),
+        ('⦑', '⦒'),
+        ('⦗', '⦘'),
+        ('⧘', '⧙'),
+        ('⧚', '⧛'),
+        ('⧼', '⧽'),
+        ('⸂', '⸃'),
+        ('⸄', '⸅'),
+        ('⸉', '⸊'),
+        ('⸌', '⸍'),
+        ('⸜', '⸝'),
+        ('⸠', '⸡'),
+        ('⸢', '⸣'),
+        ('⸤', '⸥'),
+        ('⸦', '⸧'),
+        ('⸨', '⸩'),
+    ];
+    //todo: add a way to have the user add more of these
+
+    /// Returns the opening and closing characters of the bracket pair that a character belongs to.
+    fn get_delimiter_pair(chr: char) -> Option<(char, char)> {
+        if chr.is_ascii_alphanumeric() || chr.is_whitespace() {
+            return None;
+        }
+        Self::BRACKET_PAIRS.iter().find(|(open, close)| *open == chr || *close == chr).copied()
+    }
+
+    /// Returns the bracket pairs and the pairs from the settings with the longest tokens first.
+    fn get_pair_tokens(&self) -> Vec<(String, String)> {
+        let mut pairs = Self::BRACKET_PAIRS.iter()
+            .map(|(open, close)| (open.to_string(), close.to_string()))
+            .collect::<Vec<_>>();
+        for (open, close) in self.settings.borrow().editor_settings.pairs.iter() {
+            if !pairs.iter().any(|(o, c)| o == open && c == close) {
+                pairs.push((open.clone(), close.clone()));
+            }
+        }
+        pairs.sort_by_key(|(open, close)| std::cmp::Reverse(open.len().max(close.len())));
+        pairs
+    }
+
+    /// Finds the pair token under or right before a byte and the token it pairs with.
+    /// The first range is the token at the cursor and the second one is its match.
+    /// With a syntax tree, brackets inside of strings and comments are skipped,
+    /// while the delimiters of a string or comment node, like `/* */` and quotes, are matched with each other.
+    /// The pair is drawn on every frame, so the last one found is kept until the buffer or the byte changes.
+    pub fn get_matching_pair(&self, byte_offset: usize) -> Option<(Range<usize>, Range<usize>)> {
+        let version = self.buffer.get_version();
+        if let Some((cached_version, cached_offset, pair)) = self.pair_cache.borrow().as_ref() {
+            if *cached_version == version && *cached_offset == byte_offset {
+                return pair.clone();
+            }
+        }
+
+        let pair = self.find_matching_pair(byte_offset);
+        *self.pair_cache.borrow_mut() = Some((version, byte_offset, pair.clone()));
+        pair
+    }
+
+    fn find_matching_pair(&self, byte_offset: usize) -> Option<(Range<usize>, Range<usize>)> {
+        let pairs = self.get_pair_tokens();
+
+        let mut positions = vec![byte_offset];
+        if let Some(prev) = byte_offset.checked_sub(1).and_then(|before| self.buffer.get_char_at(before)) {
+            positions.push(byte_offset - prev.len_utf8());
+        }
+
+        for position in positions {
+            for (open, close) in pairs.iter() {
+                for (token, is_open) in [(open, true), (close, false)] {
+                    let first = (position + 1).saturating_sub(token.len());
+                    let Some(start) = (first..=position).find(|start| self.buffer.has_text_at(*start, token)) else {
+                        continue;
+                    };
+                    let range = start..start + token.len();
+
+                    let found = match self.buffer.get_tree() {
+                        Some(tree) => self.match_pair_in_tree(tree, range.clone(), open, close, is_open),
+                        None if open != close => self.match_pair_in_text(range.clone(), open, close, is_open),
+                        None => None,
+                    };
+                    if let Some(found) = found {
+                        return Some((range, found));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn match_pair_in_tree(&self, tree: &tree_sitter::Tree, range: Range<usize>, open: &str, close: &str, is_open: bool) -> Option<Range<usize>> {
+        let node = tree.root_node().descendant_for_byte_range(range.start, range.end)?;
+        let node_is = |node: &tree_sitter::Node, token: &str| node.byte_range().len() == token.len() && self.buffer.has_text_at(node.start_byte(), token);
+
+        // A bracket token is matched with a sibling, unless it is part of a string or comment
+        let mut ancestor = Some(node);
+        let mut in_text = false;
+        while let Some(current) = ancestor {
+            in_text |= current.kind().contains("string") || current.kind().contains("comment");
+            ancestor = current.parent();
+        }
+        if node.byte_range() == range && open != close && !in_text {
+            let parent = node.parent()?;
+            let mut cursor = parent.walk();
+            let siblings = parent.children(&mut cursor).collect::<Vec<_>>();
+            let index = siblings.iter().position(|sibling| sibling.byte_range() == range)?;
+
+            let (same, other, ordered) = if is_open {
+                (open, close, siblings[index + 1..].to_vec())
+            } else {
+                (close, open, siblings[..index].iter().rev().copied().collect())
+            };
+
+            let mut depth = 0;
+            for sibling in ordered {
+                if node_is(&sibling, same) {
+                    depth += 1;
+                } else if node_is(&sibling, other) {
+                    if depth == 0 {
+                        return Some(sibling.byte_range());
+                    }
+                    depth -= 1;
+                }
+            }
+            return None;
+        }
+
+        // The delimiters of a node that starts and ends with the pair, like a block comment or a string
+        let mut ancestor = Some(node);
+        while let Some(current) = ancestor {
+            let current_range = current.byte_range();
+            let delimited = current_range.len() >= open.len() + close.len()
+                && self.buffer.has_text_at(current_range.start, open)
+                && self.buffer.has_text_at(current_range.end - close.len(), close);
+            if delimited {
+                if current_range.start == range.start && (is_open || open == close) {
+                    return Some(current_range.end - close.len()..current_range.end);
+                }
+                if current_range.end == range.end && (!is_open || open == close) {
+                    return Some(current_range.start..current_range.start + open.len());
+                }
+            }
+            if current_range.start != range.start && current_range.end != range.end {
+                break;
+            }
+            ancestor = current.parent();
+        }
+        None
+    }
+
+    fn match_pair_in_text(&self, range: Range<usize>, open: &str, close: &str, is_open: bool) -> Option<Range<usize>> {
+        let mut depth = 0;
+        if is_open {
+            let mut position = range.end;
+            while let Some(chr) = self.buffer.get_char_at(position) {
+                if self.buffer.has_text_at(position, close) {
+                    if depth == 0 {
+                        return Some(position..position + close.len());
+                    }
+                    depth -= 1;
+                    position += close.len();
+                } else if self.buffer.has_text_at(position, open) {
+                    depth += 1;
+                    position += open.len();
+                } else {
+                    position += chr.len_utf8();
+                }
             }
         } else {
-            false
-        }
-    }
-    fn is_pair(left: char, right: char) -> bool {
-        match (left, right) {
-            ('(', ')') => true,
-            ('{', '}') => true,
-            ('[', ']') => true,
-            ('｛', '｝') => true,
-            ('（', '）') => true,
-            ('［', '］') => true,
-            ('【', '】') => true,
-            ('「', '」') => true,
-            ('『', '』') => true,
-            ('〝', '〞') => true,
-            ('〈', '〉') => true,
-            ('《', '》') => true,
-            ('〔', '〕') => true,
-            ('〖', '〗') => true,
-            ('〘', '〙') => true,
-            ('〚', '〛') => true,
-            ('«', '»') => true,
-            ('‹', '›') => true,
-            ('"', '"') => true,
-            ('\'', '\'') => true,
-            ('‘', '’') => true,
-            ('“', '”') => true,
-            ('⁅', '⁆') => true,
-            ('〈','〉') => true,
-            ('⎡', '⎤') => true,
-            ('⎢', '⎥') => true,
-            ('⎣', '⎦') => true,
-            ('⎧', '⎫') => true,
-            ('⎨', '⎬') => true,
-            ('⎩', '⎭') => true,
-            ('⎪', '⎪') => true,
-            ('⎰', '⎱') => true,
-            ('⎱', '⎰') => true,
-            ('❬','❭') => true,
-            ('❮', '❯') => true,
-            ('❰', '❱') => true,
-            ('❴', '❵') => true,
-            ('⟦', '⟧') => true,
-            ('⟨', '⟩') => true,
-            ('❲', '❳') => true,
-            ('⦃', '⦄') => true,
-            ('⦅', '⦆') => true,
-            ('⦇', '⦈') => true,
-            ('⦉', '⦊') => true,
-            ('⦋', '⦌') => true,
-            ('⦍', '⦎') => true,
-            ('⦏', '⦐') => true,
-            ('⦑', '⦒') => true,
-            ('⦗', '⦘') => true,
-            ('⧘', '⧙') => true,
-            ('⧚', '⧛') => true,
-            ('⧼', '⧽') => true,
-            ('⸂', '⸃') => true,
-            ('⸄', '⸅') => true,
-            ('⸉', '⸊') => true,
-            ('⸌', '⸍') => true,
-            ('⸜', '⸝') => true,
-            ('⸠', '⸡') => true,
-            ('⸢', '⸣') => true,
-            ('⸤', '⸥') => true,
-            ('⸦', '⸧') => true,
-            ('⸨', '⸩') => true,
-            _ => false,
+            let mut position = range.start;
+            while position > 0 {
+                if position >= open.len() && self.buffer.has_text_at(position - open.len(), open) {
+                    if depth == 0 {
+                        return Some(position - open.len()..position);
+                    }
+                    depth -= 1;
+                    position -= open.len();
+                } else if position >= close.len() && self.buffer.has_text_at(position - close.len(), close) {
+                    depth += 1;
+                    position -= close.len();
+                } else {
+                    position -= self.buffer.get_char_at(position - 1).map_or(1, char::len_utf8);
+                }
+            }
         }
+        None
+    }
+
+    /// Returns the nesting depth of every bracket in the buffer keyed by its byte offset.
+    /// The brackets come from the tokens of the syntax tree, leaving out the ones inside of strings and comments.
+    /// Without a syntax tree every bracket in the buffer is counted.
+    fn get_delimiter_depths(&self) -> Rc<HashMap<usize, usize>> {
+        let version = self.buffer.get_version();
+        if let Some((cached_version, depths)) = self.delimiter_cache.borrow().as_ref() {
+            if *cached_version == version {
+                return depths.clone();
+            }
+        }
+
+        let text = self.buffer.to_string();
+        let delimiters = match self.buffer.get_tree() {
+            Some(tree) => {
+                let mut delimiters = Vec::new();
+                let mut stack = vec![tree.root_node()];
+                while let Some(node) = stack.pop() {
+                    let kind = node.kind();
+                    if kind.contains("string") || kind.contains("comment") {
+                        continue;
+                    }
+                    if node.child_count() == 0 {
+                        let mut chars = text[node.start_byte()..node.end_byte()].chars();
+                        if let (Some(chr), None) = (chars.next(), chars.next()) {
+                            delimiters.push((node.start_byte(), chr));
+                        }
+                        continue;
+                    }
+                    let mut cursor = node.walk();
+                    let children = node.children(&mut cursor).collect::<Vec<_>>();
+                    stack.extend(children.into_iter().rev());
+                }
+                delimiters
+            }
+            None => text.char_indices().collect(),
+        };
+
+        // The stack holds the closing characters that are expected next
+        let mut expected = Vec::new();
+        let mut depths = HashMap::new();
+        for (byte, chr) in delimiters {
+            let Some((open, close)) = Self::get_delimiter_pair(chr) else {
+                continue;
+            };
+            if chr == open {
+                depths.insert(byte, expected.len());
+                expected.push(close);
+            } else if let Some(depth) = expected.iter().rposition(|c| *c == chr) {
+                // Brackets that were never closed end with the first bracket that closes an outer one
+                expected.truncate(depth);
+                depths.insert(byte, depth);
+            } else {
+                depths.insert(byte, expected.len());
+            }
+        }
+
+        let depths = Rc::new(depths);
+        *self.delimiter_cache.borrow_mut() = Some((version, depths.clone()));
+        depths
     }
 
 
     fn internal_display(&self, text: String, offset: usize) -> StyledText {
 
-        let mut rainbow_delimiters = Vec::new();
+        let rainbow_delimiters = if self.settings.borrow().editor_settings.rainbow_delimiters {
+            self.get_delimiter_depths()
+        } else {
+            Rc::new(HashMap::new())
+        };
 
         let mut skip_counter = 0;
 
@@ -872,6 +2040,20 @@ impl File {
         let mut output = StyledText::new();
         let mut line = StyledLine::new();
         let mut highlight = false;
+        let mut error = false;
+
+        let errors = self.get_syntax_errors();
+        let is_error = |i: usize| errors.iter().any(|range| range.contains(&i));
+
+        // Only the text being drawn is searched, so a search costs the same however long the file is
+        let search_matches = match &self.search_highlight {
+            Some(regex) => regex.find_iter(&string)
+                .flat_map(|found| found.range())
+                .map(|i| i + offset)
+                .collect::<BTreeSet<_>>(),
+            None => BTreeSet::new(),
+        };
+        let is_highlighted = |i: usize| self.highlights.contains(&i) || search_matches.contains(&i);
 
         for (i, _) in string.bytes().enumerate() {
             if skip_counter > 0 {
@@ -884,9 +2066,11 @@ impl File {
 
             skip_counter = chr.len_utf8() - 1;
 
-            if self.highlights.contains(&i) && !(self.is_delimiter(i) && self.settings.borrow().editor_settings.rainbow_delimiters){
+            let depth = rainbow_delimiters.get(&i).copied();
+
+            if is_highlighted(i) && depth.is_none() {
                 if !highlight {
-                    line.push(StyledSpan::from(acc.clone()));
+                    line.push(self.plain_span(acc.clone(), error));
                     acc.clear();
                 }
 
@@ -907,8 +2091,7 @@ impl File {
                     acc.clear();
                 } else {
                     if chr == '\t' {
-                        let settings = self.settings.borrow();
-                        let tab_size = settings.editor_settings.tab_size;
+                        let tab_size = self.buffer.get_tab_size();
                         for _ in 0..tab_size {
                             acc.push(' ');
                         }
@@ -920,7 +2103,7 @@ impl File {
                 }
                 highlight = true;
 
-            } else if self.highlights.contains(&i) && self.is_delimiter(i) && self.settings.borrow().editor_settings.rainbow_delimiters {
+            } else if let (true, Some(depth)) = (is_highlighted(i), depth) {
                 let settings = self.settings.clone();
                 let settings = settings.borrow();
 
@@ -932,30 +2115,13 @@ impl File {
                     ));
                     acc.clear();
                 } else {
-                    line.push(StyledSpan::from(acc.clone()));
+                    line.push(self.plain_span(acc.clone(), error));
                     acc.clear();
                 }
                 highlight = true;
 
-                let color = settings.colors.rainbow_delimiters[rainbow_delimiters.len() % settings.colors.rainbow_delimiters.len()];
-
-                let color = if rainbow_delimiters.is_empty() {
-                    rainbow_delimiters.push((chr, color));
-                    acc.push(chr);
-                    color
-
-                } else {
-                    let last = rainbow_delimiters.last().unwrap();
-                    if Self::is_pair(last.0, chr) {
-                        let color = rainbow_delimiters.pop().unwrap().1;
-                        acc.push(chr);
-                        color
-                    } else {
-                        rainbow_delimiters.push((chr, color));
-                        acc.push(chr);
-                        color
-                    }
-                };
+                let color = settings.colors.rainbow_delimiters[depth % settings.colors.rainbow_delimiters.len()];
+                acc.push(chr);
 
                 let selection_color = settings.colors.selected;
                 let selection_color = selection_color.patch(color);
@@ -964,7 +2130,7 @@ impl File {
                                              selection_color
                 ));
                 acc.clear();
-            } else if self.is_delimiter(i) && self.settings.borrow().editor_settings.rainbow_delimiters {
+            } else if let Some(depth) = depth {
                 let settings = self.settings.clone();
                 let settings = settings.borrow();
 
@@ -976,28 +2142,17 @@ impl File {
                     ));
                     acc.clear();
                 } else {
-                    line.push(StyledSpan::from(acc.clone()));
+                    line.push(self.plain_span(acc.clone(), error));
                     acc.clear();
                 }
                 highlight = false;
-                let color = settings.colors.rainbow_delimiters[rainbow_delimiters.len() % settings.colors.rainbow_delimiters.len()];
-
-                let color = if rainbow_delimiters.is_empty() {
-                    rainbow_delimiters.push((chr, color));
-                    acc.push(chr);
-                    color
+                let color = settings.colors.rainbow_delimiters[depth % settings.colors.rainbow_delimiters.len()];
+                acc.push(chr);
 
+                let color = if is_error(i) {
+                    color.patch(settings.colors.syntax_error)
                 } else {
-                    let last = rainbow_delimiters.last().unwrap();
-                    if Self::is_pair(last.0, chr) {
-                        let color = rainbow_delimiters.pop().unwrap().1;
-                        acc.push(chr);
-                        color
-                    } else {
-                        rainbow_delimiters.push((chr, color));
-                        acc.push(chr);
-                        color
-                    }
+                    color
                 };
 
                 line.push(StyledSpan::styled(acc.clone(),
@@ -1013,11 +2168,11 @@ impl File {
                                                  selection_color
                     ));
                 } else {
-                    line.push(StyledSpan::from(acc.clone()));
+                    line.push(self.plain_span(acc.clone(), error));
                 }
                 acc.clear();
                 acc.push(' ');
-                line.push(StyledSpan::from(acc.clone()));
+                line.push(self.plain_span(acc.clone(), is_error(i)));
 
                 output.lines.push(line);
                 line = StyledLine::new();
@@ -1031,12 +2186,15 @@ impl File {
                                                  selection_color
                     ));
                     acc.clear();
+                } else if error != is_error(i) && !acc.is_empty() {
+                    line.push(self.plain_span(acc.clone(), error));
+                    acc.clear();
                 }
                 highlight = false;
+                error = is_error(i);
 
                 if chr == '\t' {
-                    let settings = self.settings.borrow();
-                    let tab_size = settings.editor_settings.tab_size;
+                    let tab_size = self.buffer.get_tab_size();
                     for _ in 0..tab_size {
                         acc.push(' ');
                     }
@@ -1049,7 +2207,7 @@ impl File {
         }
         if !acc.is_empty() {
             if !highlight {
-                line.push(StyledSpan::from(acc.clone()));
+                line.push(self.plain_span(acc.clone(), error));
                 acc.clear();
             } else {
                 let settings = self.settings.borrow();
@@ -1066,6 +2224,14 @@ impl File {
 
         output
     }
+    /// Creates a span for text that isn't selected, underlining it if it is part of a syntax error.
+    fn plain_span<'a>(&self, text: String, error: bool) -> StyledSpan<'a> {
+        if error {
+            StyledSpan::styled(text, self.settings.borrow().colors.syntax_error)
+        } else {
+            StyledSpan::from(text)
+        }
+    }
     pub fn display(&self) -> StyledText {
         self.internal_display(self.buffer.to_string(), 0)
     }
@@ -1265,8 +2431,7 @@ impl File {
                 acc.clear();
             } else {
                 if c == b'\t' {
-                    let settings = self.settings.borrow();
-                    let tab_size = settings.editor_settings.tab_size;
+                    let tab_size = self.buffer.get_tab_size();
                     for _ in 0..tab_size {
                         acc.push(' ');
                     }
@@ -1293,7 +2458,7 @@ impl File {
         output
     }*/
 
-    pub fn display_section(&self, start_row: usize, end_row: usize) -> StyledText {
+    fn display_rows(&self, start_row: usize, end_row: usize) -> StyledText<'_> {
         let mut string = String::new();
 
         for i in start_row..=end_row {
@@ -1305,6 +2470,49 @@ impl File {
         self.internal_display(string, self.buffer.get_byte_offset(0, start_row).unwrap())
     }
 
+    pub fn display_section(&self, start_row: usize, end_row: usize) -> StyledText<'_> {
+        let closed_folds = self.get_closed_folds();
+        if closed_folds.is_empty() {
+            return self.display_rows(start_row, end_row);
+        }
+
+        let mut output = StyledText::new();
+        let mut chunk: Option<(usize, usize)> = None;
+
+        for row in self.get_visible_rows(start_row, end_row.saturating_sub(start_row) + 1) {
+            let fold = closed_folds.iter().find(|(start, _)| *start == row);
+
+            match chunk {
+                Some((chunk_start, chunk_end)) if fold.is_none() && chunk_end + 1 == row => {
+                    chunk = Some((chunk_start, row));
+                    continue;
+                }
+                Some((chunk_start, chunk_end)) => {
+                    output.lines.extend(self.display_rows(chunk_start, chunk_end).lines);
+                    chunk = None;
+                }
+                None => {}
+            }
+
+            match fold {
+                Some((start, end)) => {
+                    let mut summary = self.display_rows(*start, *start);
+                    let mut line = summary.lines.drain(..).next().unwrap_or_else(StyledLine::new);
+                    let settings = self.settings.borrow();
+                    line.push(StyledSpan::styled(format!("··· {} lines", end - start + 1), settings.colors.folded));
+                    output.lines.push(line);
+                }
+                None => chunk = Some((row, row)),
+            }
+        }
+
+        if let Some((chunk_start, chunk_end)) = chunk {
+            output.lines.extend(self.display_rows(chunk_start, chunk_end).lines);
+        }
+
+        output
+    }
+
     pub fn recover(&mut self) -> Result<(), String>{
 
         let path = match self.path {
@@ -1555,7 +2763,7 @@ impl InsertPairs<Vec<(&str, &str)>> for File {
 
 impl Drop for File {
     fn drop(&mut self) {
-        if !self.saved && !self.safe_close {
+        if !self.saved && !self.safe_close && !self.read_only {
             match self.path {
                 Some(ref path) => {
                     let filename = path.file_name().unwrap().to_str().unwrap();
@@ -0,0 +1,121 @@
+//! Tree-sitter queries that ship with Sevi.
+//! The queries are looked up by the language name that `File` assigns when loading a file.
+
+
+pub fn get_fold_query(language: &str) -> Option<&'static str> {
+    match language {
+        "rust" => Some(include_str!("queries/rust/folds.scm")),
+        "c" => Some(include_str!("queries/c/folds.scm")),
+        "cpp" => Some(include_str!("queries/cpp/folds.scm")),
+        "python" => Some(include_str!("queries/python/folds.scm")),
+        "commonlisp" => Some(include_str!("queries/commonlisp/folds.scm")),
+        "swift" => Some(include_str!("queries/swift/folds.scm")),
+        "go" => Some(include_str!("queries/go/folds.scm")),
+        "bash" => Some(include_str!("queries/bash/folds.scm")),
+        "javascript" => Some(include_str!("queries/javascript/folds.scm")),
+        "csharp" => Some(include_str!("queries/csharp/folds.scm")),
+        _ => None,
+    }
+}
+
+pub fn get_tags_query(language: &str) -> Option<&'static str> {
+    match language {
+        "rust" => Some(include_str!("queries/rust/tags.scm")),
+        "c" => Some(include_str!("queries/c/tags.scm")),
+        "cpp" => Some(include_str!("queries/cpp/tags.scm")),
+        "python" => Some(include_str!("queries/python/tags.scm")),
+        "commonlisp" => Some(include_str!("queries/commonlisp/tags.scm")),
+        "swift" => Some(include_str!("queries/swift/tags.scm")),
+        "go" => Some(include_str!("queries/go/tags.scm")),
+        "bash" => Some(include_str!("queries/bash/tags.scm")),
+        "javascript" => Some(include_str!("queries/javascript/tags.scm")),
+        "csharp" => Some(include_str!("queries/csharp/tags.scm")),
+        _ => None,
+    }
+}
+
+pub fn get_injection_query(language: &str) -> Option<&'static str> {
+    match language {
+        "rust" => Some(include_str!("queries/rust/injections.scm")),
+        "python" => Some(include_str!("queries/python/injections.scm")),
+        "bash" => Some(include_str!("queries/bash/injections.scm")),
+        "javascript" => Some(include_str!("queries/javascript/injections.scm")),
+        _ => None,
+    }
+}
+
+/// Looks up a grammar by a language name as it appears in an injection, such as `python3`, `sh` or `js`.
+/// Returns the name that `File` uses for the language along with the grammar.
+pub fn get_language(name: &str) -> Option<(&'static str, tree_sitter::Language)> {
+    let name = name.trim_start_matches(|c: char| !c.is_alphanumeric())
+        .trim_end_matches(|c: char| !c.is_alphanumeric() && c != '+' && c != '#')
+        .to_lowercase();
+    let name = name.strip_prefix("language=").unwrap_or(&name);
+
+    match name {
+        "rust" | "rs" => Some(("rust", tree_sitter_rust::language())),
+        "c" | "h" => Some(("c", tree_sitter_c::language())),
+        "cpp" | "c++" | "hpp" => Some(("cpp", tree_sitter_cpp::language())),
+        "python" | "python3" | "py" => Some(("python", tree_sitter_python::language())),
+        "commonlisp" | "lisp" => Some(("commonlisp", tree_sitter_commonlisp::language())),
+        "swift" => Some(("swift", tree_sitter_swift::language())),
+        "go" | "golang" => Some(("go", tree_sitter_go::language())),
+        "bash" | "sh" | "shell" | "zsh" => Some(("bash", tree_sitter_bash::language())),
+        "javascript" | "js" | "node" => Some(("javascript", tree_sitter_javascript::language())),
+        "csharp" | "cs" | "c#" => Some(("csharp", tree_sitter_c_sharp::language())),
+        _ => None,
+    }
+}
+
+pub fn get_indent_query(language: &str) -> Option<&'static str> {
+    match language {
+        "rust" => Some(include_str!("queries/rust/indents.scm")),
+        "c" => Some(include_str!("queries/c/indents.scm")),
+        "cpp" => Some(include_str!("queries/cpp/indents.scm")),
+        "python" => Some(include_str!("queries/python/indents.scm")),
+        "commonlisp" => Some(include_str!("queries/commonlisp/indents.scm")),
+        "swift" => Some(include_str!("queries/swift/indents.scm")),
+        "go" => Some(include_str!("queries/go/indents.scm")),
+        "bash" => Some(include_str!("queries/bash/indents.scm")),
+        "javascript" => Some(include_str!("queries/javascript/indents.scm")),
+        "csharp" => Some(include_str!("queries/csharp/indents.scm")),
+        _ => None,
+    }
+}
+
+/// The line endings that open a new indentation level.
+/// These are used when the syntax tree has errors, which is usually the case while a block is being typed.
+pub fn get_indent_openers(language: &str) -> &'static [&'static str] {
+    match language {
+        "python" => &[":", "{", "(", "["],
+        "bash" => &["then", "do", "else", "{", "("],
+        "commonlisp" => &["("],
+        _ => &["{", "(", "["],
+    }
+}
+
+/// The comment syntax of a language.
+/// `line` starts a comment that runs to the end of the line and `block` opens and closes a comment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommentTokens {
+    pub line: Option<&'static str>,
+    pub block: Option<(&'static str, &'static str)>,
+}
+
+pub fn get_comment_tokens(language: &str) -> Option<CommentTokens> {
+    match language {
+        "rust" | "c" | "cpp" | "swift" | "go" | "javascript" | "csharp" => Some(CommentTokens {
+            line: Some("//"),
+            block: Some(("/*", "*/")),
+        }),
+        "python" | "bash" => Some(CommentTokens {
+            line: Some("#"),
+            block: None,
+        }),
+        "commonlisp" | "scheme" => Some(CommentTokens {
+            line: Some(";"),
+            block: Some(("#|", "|#")),
+        }),
+        _ => None,
+    }
+}
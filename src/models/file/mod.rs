@@ -1,5 +1,10 @@
 
 pub mod buffer;
 pub mod file;
+pub mod language;
+pub mod range;
+pub mod search;
+pub mod structural;
+pub mod symbol;
 
 pub use file::File;
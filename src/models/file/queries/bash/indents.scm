@@ -0,0 +1,19 @@
+[
+  (compound_statement)
+  (if_statement)
+  (for_statement)
+  (c_style_for_statement)
+  (while_statement)
+  (case_statement)
+  (case_item)
+  (function_definition)
+] @indent
+
+[
+  (elif_clause)
+  (else_clause)
+  "fi"
+  "done"
+  "esac"
+  "}"
+] @outdent
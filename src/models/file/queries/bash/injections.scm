@@ -0,0 +1,22 @@
+; Code passed to an interpreter: python3 -c "print(1)"
+((command
+   name: (command_name) @injection.language
+   argument: (word) @_flag
+   .
+   argument: (string (string_content) @injection.content))
+ (#match? @injection.language "^(python3?|bash|sh|zsh|node)$")
+ (#match? @_flag "^-[ce]$"))
+
+((command
+   name: (command_name) @injection.language
+   argument: (word) @_flag
+   .
+   argument: (raw_string) @injection.content)
+ (#match? @injection.language "^(python3?|bash|sh|zsh|node)$")
+ (#match? @_flag "^-[ce]$")
+ (#offset! @injection.content 0 1 0 -1))
+
+; Here-documents named after their language: cat <<PYTHON
+(heredoc_redirect
+  (heredoc_start) @injection.language
+  (heredoc_body) @injection.content)
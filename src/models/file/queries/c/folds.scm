@@ -0,0 +1,18 @@
+[
+  (function_definition)
+  (struct_specifier)
+  (enum_specifier)
+  (union_specifier)
+  (compound_statement)
+  (for_statement)
+  (while_statement)
+  (do_statement)
+  (if_statement)
+  (switch_statement)
+  (case_statement)
+  (initializer_list)
+  (preproc_if)
+  (preproc_ifdef)
+  (preproc_else)
+  (comment)
+] @fold
@@ -0,0 +1 @@
+(list_lit) @fold
@@ -0,0 +1,3 @@
+(list_lit) @indent
+
+")" @outdent
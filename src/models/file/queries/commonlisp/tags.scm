@@ -0,0 +1,11 @@
+(defun
+  (defun_header
+    keyword: (defun_keyword) @_keyword
+    function_name: (_) @name)
+  (#match? @_keyword "^(defun|defgeneric|defmethod)$")) @definition.function
+
+(defun
+  (defun_header
+    keyword: (defun_keyword) @_keyword
+    function_name: (_) @name)
+  (#eq? @_keyword "defmacro")) @definition.macro
@@ -0,0 +1,23 @@
+[
+  (function_definition)
+  (class_specifier)
+  (struct_specifier)
+  (enum_specifier)
+  (union_specifier)
+  (namespace_definition)
+  (template_declaration)
+  (compound_statement)
+  (for_statement)
+  (for_range_loop)
+  (while_statement)
+  (do_statement)
+  (if_statement)
+  (switch_statement)
+  (case_statement)
+  (initializer_list)
+  (lambda_expression)
+  (preproc_if)
+  (preproc_ifdef)
+  (preproc_else)
+  (comment)
+] @fold
@@ -0,0 +1,20 @@
+(function_definition
+  declarator: (function_declarator declarator: (identifier) @name)) @definition.function
+(function_definition
+  declarator: (pointer_declarator
+    declarator: (function_declarator declarator: (identifier) @name))) @definition.function
+(function_definition
+  declarator: (function_declarator declarator: (qualified_identifier) @name)) @definition.method
+(function_definition
+  declarator: (function_declarator declarator: (field_identifier) @name)) @definition.method
+
+(namespace_definition name: (_) @name) @definition.module
+
+(class_specifier name: (type_identifier) @name body: (_)) @definition.class
+(struct_specifier name: (type_identifier) @name body: (_)) @definition.class
+(union_specifier name: (type_identifier) @name body: (_)) @definition.class
+(enum_specifier name: (type_identifier) @name body: (_)) @definition.class
+(type_definition declarator: (type_identifier) @name) @definition.class
+
+(preproc_def name: (identifier) @name) @definition.constant
+(preproc_function_def name: (identifier) @name) @definition.macro
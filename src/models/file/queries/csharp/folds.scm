@@ -0,0 +1,15 @@
+[
+  (namespace_declaration)
+  (class_declaration)
+  (struct_declaration)
+  (interface_declaration)
+  (enum_declaration)
+  (record_declaration)
+  (method_declaration)
+  (constructor_declaration)
+  (property_declaration)
+  (block)
+  (switch_statement)
+  (initializer_expression)
+  (comment)
+] @fold
@@ -0,0 +1,17 @@
+[
+  (block)
+  (declaration_list)
+  (enum_member_declaration_list)
+  (accessor_list)
+  (argument_list)
+  (parameter_list)
+  (initializer_expression)
+  (switch_body)
+  (switch_section)
+] @indent
+
+[
+  "}"
+  ")"
+  "]"
+] @outdent
@@ -0,0 +1,13 @@
+(namespace_declaration name: (_) @name) @definition.module
+
+(class_declaration name: (identifier) @name) @definition.class
+(struct_declaration name: (identifier) @name) @definition.class
+(enum_declaration name: (identifier) @name) @definition.class
+(record_declaration name: (identifier) @name) @definition.class
+
+(interface_declaration name: (identifier) @name) @definition.interface
+
+(method_declaration name: (identifier) @name) @definition.method
+(constructor_declaration name: (identifier) @name) @definition.method
+
+(property_declaration name: (identifier) @name) @definition.constant
@@ -0,0 +1,26 @@
+[
+  (block)
+  (literal_value)
+  (argument_list)
+  (parameter_list)
+  (field_declaration_list)
+  (interface_type)
+  (import_spec_list)
+  (expression_switch_statement)
+  (type_switch_statement)
+  (select_statement)
+  (expression_case)
+  (default_case)
+  (type_case)
+  (communication_case)
+] @indent
+
+[
+  (expression_case)
+  (default_case)
+  (type_case)
+  (communication_case)
+  "}"
+  ")"
+  "]"
+] @outdent
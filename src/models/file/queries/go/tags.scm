@@ -0,0 +1,9 @@
+(function_declaration name: (identifier) @name) @definition.function
+
+(method_declaration name: (field_identifier) @name) @definition.method
+
+(type_spec name: (type_identifier) @name type: (interface_type)) @definition.interface
+(type_spec name: (type_identifier) @name type: (struct_type)) @definition.class
+(type_spec name: (type_identifier) @name) @definition.class
+
+(const_spec name: (identifier) @name) @definition.constant
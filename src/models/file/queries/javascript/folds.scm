@@ -0,0 +1,17 @@
+[
+  (function_declaration)
+  (generator_function_declaration)
+  (function_expression)
+  (arrow_function)
+  (class_declaration)
+  (class_body)
+  (method_definition)
+  (statement_block)
+  (switch_statement)
+  (switch_case)
+  (object)
+  (array)
+  (template_string)
+  (import_statement)
+  (comment)
+] @fold
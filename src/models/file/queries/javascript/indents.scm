@@ -0,0 +1,20 @@
+[
+  (statement_block)
+  (class_body)
+  (object)
+  (object_pattern)
+  (array)
+  (array_pattern)
+  (arguments)
+  (formal_parameters)
+  (named_imports)
+  (switch_body)
+  (switch_case)
+  (switch_default)
+] @indent
+
+[
+  "}"
+  ")"
+  "]"
+] @outdent
@@ -0,0 +1,9 @@
+; Strings tagged with their language: /* python */ `print(1)`
+((comment) @injection.language
+  .
+  (template_string) @injection.content
+  (#offset! @injection.content 0 1 0 -1))
+
+((comment) @injection.language
+  .
+  (string (string_fragment) @injection.content))
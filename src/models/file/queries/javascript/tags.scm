@@ -0,0 +1,11 @@
+(function_declaration name: (identifier) @name) @definition.function
+(generator_function_declaration name: (identifier) @name) @definition.function
+
+(lexical_declaration
+  (variable_declarator
+    name: (identifier) @name
+    value: [(arrow_function) (function_expression)]) @definition.function)
+
+(class_declaration name: (identifier) @name) @definition.class
+
+(method_definition name: (property_identifier) @name) @definition.method
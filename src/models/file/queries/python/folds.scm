@@ -0,0 +1,24 @@
+[
+  (function_definition)
+  (class_definition)
+  (decorated_definition)
+  (for_statement)
+  (while_statement)
+  (if_statement)
+  (elif_clause)
+  (else_clause)
+  (with_statement)
+  (try_statement)
+  (except_clause)
+  (finally_clause)
+  (import_from_statement)
+  (parameters)
+  (argument_list)
+  (list)
+  (dictionary)
+  (set)
+  (tuple)
+  (list_comprehension)
+  (dictionary_comprehension)
+  (string)
+] @fold
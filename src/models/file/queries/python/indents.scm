@@ -0,0 +1,30 @@
+[
+  (function_definition)
+  (class_definition)
+  (if_statement)
+  (for_statement)
+  (while_statement)
+  (with_statement)
+  (try_statement)
+  (list)
+  (dictionary)
+  (set)
+  (tuple)
+  (argument_list)
+  (parameters)
+  (parenthesized_expression)
+  (list_comprehension)
+  (dictionary_comprehension)
+  (set_comprehension)
+  (generator_expression)
+] @indent
+
+[
+  (elif_clause)
+  (else_clause)
+  (except_clause)
+  (finally_clause)
+  "}"
+  ")"
+  "]"
+] @outdent
@@ -0,0 +1,29 @@
+; Shell commands: subprocess.run("ls -l", shell=True)
+(call
+  function: (attribute attribute: (identifier) @_function)
+  arguments: (argument_list
+    .
+    (string (string_content) @injection.content)
+    (keyword_argument
+      name: (identifier) @_keyword
+      value: (true)))
+  (#match? @_function "^(run|call|check_call|check_output|Popen)$")
+  (#eq? @_keyword "shell")
+  (#set! injection.language "bash"))
+
+; os.system("ls -l")
+(call
+  function: (attribute
+    object: (identifier) @_module
+    attribute: (identifier) @_function)
+  arguments: (argument_list . (string (string_content) @injection.content))
+  (#eq? @_module "os")
+  (#match? @_function "^(system|popen)$")
+  (#set! injection.language "bash"))
+
+; Strings tagged with their language: # language=bash
+((comment) @injection.language
+  .
+  (expression_statement
+    (assignment right: (string (string_content) @injection.content)))
+  (#match? @injection.language "^#\\s*language="))
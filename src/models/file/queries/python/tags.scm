@@ -0,0 +1,13 @@
+(class_definition name: (identifier) @name) @definition.class
+
+(function_definition name: (identifier) @name) @definition.function
+
+(class_definition
+  body: (block
+    [(function_definition name: (identifier) @name) @definition.method
+     (decorated_definition (function_definition name: (identifier) @name) @definition.method)]))
+
+(module
+  (expression_statement
+    (assignment left: (identifier) @name) @definition.constant)
+  (#match? @name "^[A-Z][A-Z0-9_]*$"))
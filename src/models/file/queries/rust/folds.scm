@@ -0,0 +1,15 @@
+[
+  (mod_item)
+  (foreign_mod_item)
+  (function_item)
+  (impl_item)
+  (trait_item)
+  (struct_item)
+  (enum_item)
+  (union_item)
+  (macro_definition)
+  (match_expression)
+  (block)
+  (use_declaration)
+  (block_comment)
+] @fold
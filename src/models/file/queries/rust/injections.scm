@@ -0,0 +1,17 @@
+; Strings tagged with their language: /* python */ r#"print(1)"#
+((block_comment) @injection.language
+  .
+  (string_literal) @injection.content
+  (#offset! @injection.content 0 1 0 -1))
+
+((block_comment) @injection.language
+  .
+  (raw_string_literal) @injection.content
+  (#match? @injection.content "^r\"")
+  (#offset! @injection.content 0 2 0 -1))
+
+((block_comment) @injection.language
+  .
+  (raw_string_literal) @injection.content
+  (#match? @injection.content "^r#\"")
+  (#offset! @injection.content 0 3 0 -2))
@@ -0,0 +1,8 @@
+[
+  (class_declaration)
+  (protocol_declaration)
+  (function_declaration)
+  (lambda_literal)
+  (statements)
+  (multiline_comment)
+] @fold
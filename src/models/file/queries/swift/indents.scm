@@ -0,0 +1,23 @@
+[
+  (class_body)
+  (protocol_body)
+  (function_body)
+  (lambda_literal)
+  (if_statement)
+  (guard_statement)
+  (for_statement)
+  (while_statement)
+  (repeat_while_statement)
+  (switch_statement)
+  (switch_entry)
+  (do_statement)
+  (value_arguments)
+  (array_literal)
+  (dictionary_literal)
+] @indent
+
+[
+  "}"
+  ")"
+  "]"
+] @outdent
@@ -0,0 +1,13 @@
+(class_declaration name: (type_identifier) @name) @definition.class
+(class_declaration name: (user_type) @name) @definition.implementation
+
+(protocol_declaration name: (type_identifier) @name) @definition.interface
+
+(function_declaration name: (simple_identifier) @name) @definition.function
+
+(class_body (function_declaration name: (simple_identifier) @name) @definition.method)
+(enum_class_body (function_declaration name: (simple_identifier) @name) @definition.method)
+
+(source_file
+  (property_declaration
+    name: (pattern (simple_identifier) @name)) @definition.constant)
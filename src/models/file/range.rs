@@ -0,0 +1,240 @@
+//! Ex-style line ranges like `10,20`, `.,$`, `'a,'b` and `/start/,/end/`.
+use crate::models::file::buffer::RowEdit;
+
+/// What an address counts from before its offset is added.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AddressBase {
+    /// A line number starting at 1, where 0 is before the first line
+    Line(usize),
+    /// `.`, the line of the cursor
+    Current,
+    /// `$`, the last line
+    Last,
+    /// `'a`, a line remembered with `:mark`, or `'<` and `'>` for the last selection
+    Mark(char),
+    /// `/pattern/` for the next line that matches or `?pattern?` for the previous one
+    Pattern {
+        pattern: String,
+        forward: bool,
+    },
+}
+
+/// A single line of a range, like `.+3` or `/fn main/-1`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Address {
+    pub base: AddressBase,
+    /// The lines added with `+` and `-`
+    pub offset: isize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LineRange {
+    /// No range was given, so the command uses its default
+    Default,
+    /// `%`, every line of the file
+    Whole,
+    Single(Address),
+    /// Two addresses, where a `;` between them counts the second one from the first rather than from the cursor
+    Pair(Address, Address, bool),
+}
+
+/// Splits the line range off of the start of a command, returning the range and the rest of the command.
+pub fn parse_range(command: &str) -> Result<(LineRange, &str), String> {
+    if let Some(rest) = command.strip_prefix('%') {
+        return Ok((LineRange::Whole, rest.trim_start()));
+    }
+
+    let (first, rest) = match parse_address(command)? {
+        Some((address, rest)) => (Some(address), rest),
+        None => (None, command),
+    };
+
+    let separator = rest.chars().next().filter(|chr| *chr == ',' || *chr == ';');
+    let Some(separator) = separator else {
+        return match first {
+            Some(first) => Ok((LineRange::Single(first), rest.trim_start())),
+            None => Ok((LineRange::Default, rest)),
+        };
+    };
+
+    // Like in Vim, a missing address next to the separator is the current line
+    let current = Address {
+        base: AddressBase::Current,
+        offset: 0,
+    };
+    let rest = &rest[1..];
+    let (second, rest) = match parse_address(rest)? {
+        Some((address, rest)) => (address, rest),
+        None => (current.clone(), rest),
+    };
+    Ok((LineRange::Pair(first.unwrap_or(current), second, separator == ';'), rest.trim_start()))
+}
+
+/// Parses one address from the start of `text`, or returns `None` if it doesn't start with one.
+pub fn parse_address(text: &str) -> Result<Option<(Address, &str)>, String> {
+    let base = match text.chars().next() {
+        Some('0'..='9') => {
+            let end = text.find(|chr: char| !chr.is_ascii_digit()).unwrap_or(text.len());
+            let line = text[..end].parse::<usize>().map_err(|_| format!("Invalid line number: {}", &text[..end]))?;
+            Some((AddressBase::Line(line), end))
+        }
+        Some('.') => Some((AddressBase::Current, 1)),
+        Some('$') => Some((AddressBase::Last, 1)),
+        Some('\'') => match text[1..].chars().next() {
+            Some(mark) if mark.is_ascii_lowercase() || mark == '<' || mark == '>' => {
+                Some((AddressBase::Mark(mark), 1 + mark.len_utf8()))
+            }
+            Some(mark) => return Err(format!("Invalid mark: {}", mark)),
+            None => return Err(String::from("Missing mark name")),
+        },
+        Some(delimiter @ ('/' | '?')) => {
+            let (pattern, end) = parse_pattern(&text[1..], delimiter);
+            Some((AddressBase::Pattern {
+                pattern,
+                forward: delimiter == '/',
+            }, 1 + end))
+        }
+        _ => None,
+    };
+
+    let (base, mut end) = match base {
+        Some((base, end)) => (Some(base), end),
+        None => (None, 0),
+    };
+
+    let mut offset = 0isize;
+    let mut has_offset = false;
+    while let Some(sign) = text[end..].chars().next().filter(|chr| *chr == '+' || *chr == '-') {
+        end += 1;
+        let digits = text[end..].find(|chr: char| !chr.is_ascii_digit()).unwrap_or(text.len() - end);
+        let amount = match digits {
+            0 => 1,
+            _ => text[end..end + digits].parse::<isize>().map_err(|_| String::from("Invalid offset"))?,
+        };
+        end += digits;
+        offset += if sign == '+' { amount } else { -amount };
+        has_offset = true;
+    }
+
+    match (base, has_offset) {
+        (Some(base), _) => Ok(Some((Address { base, offset }, &text[end..]))),
+        (None, true) => Ok(Some((Address { base: AddressBase::Current, offset }, &text[end..]))),
+        (None, false) => Ok(None),
+    }
+}
+
+/// Reads a pattern up to an unescaped `delimiter`, returning it and the byte after the delimiter.
+/// A pattern that runs to the end of the text doesn't need a closing delimiter.
+pub fn parse_pattern(text: &str, delimiter: char) -> (String, usize) {
+    let mut pattern = String::new();
+    let mut chars = text.char_indices();
+    while let Some((index, chr)) = chars.next() {
+        match chr {
+            '\\' => match chars.next() {
+                Some((_, escaped)) if escaped == delimiter => pattern.push(escaped),
+                Some((_, escaped)) => {
+                    pattern.push('\\');
+                    pattern.push(escaped);
+                }
+                None => pattern.push('\\'),
+            },
+            chr if chr == delimiter => return (pattern, index + chr.len_utf8()),
+            chr => pattern.push(chr),
+        }
+    }
+    (pattern, text.len())
+}
+
+/// Finds where `rows` are after the row edits of a buffer, in the order they were made,
+/// leaving out the rows whose lines were replaced or deleted.
+pub fn follow_rows(edits: &[RowEdit], rows: &[usize]) -> Vec<usize> {
+    rows.iter()
+        .filter_map(|row| edits.iter().try_fold(*row, |row, edit| edit.follow_row(row)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::file::buffer::Buffer;
+
+    /// Makes the edits to a buffer with `text` as a single undo step and returns where `rows` are after them.
+    fn rows_after(text: &str, edits: Vec<(std::ops::Range<usize>, &str)>, rows: &[usize]) -> Vec<usize> {
+        let mut buffer = Buffer::from(text);
+        let version = buffer.get_version();
+        buffer.replace_ranges(edits);
+        follow_rows(&buffer.get_row_edits(version).unwrap(), rows)
+    }
+
+    fn address(base: AddressBase, offset: isize) -> Address {
+        Address { base, offset }
+    }
+
+    #[test]
+    fn follows_rows_past_a_deletion() {
+        assert_eq!(rows_after("a\nb\nc\nd\n", vec![(2..6, "")], &[0, 1, 2, 3]), vec![0, 1]);
+    }
+
+    #[test]
+    fn follows_duplicate_lines_by_position() {
+        // Deleting the first of two equal lines keeps the second one rather than the line it was equal to
+        assert_eq!(rows_after("x\ny\nx\ny\n", vec![(0..2, "")], &[0, 2]), vec![1]);
+        assert_eq!(rows_after("x\nx\nx\n", vec![(2..4, "")], &[0, 1, 2]), vec![0, 1]);
+    }
+
+    #[test]
+    fn follows_rows_past_an_insertion() {
+        assert_eq!(rows_after("a\nb\nc\n", vec![(2..2, "new\nnew\n")], &[0, 1, 2]), vec![0, 3, 4]);
+        // A line split in two keeps its row and moves the rows after it
+        assert_eq!(rows_after("ab\nc\n", vec![(1..1, "\n")], &[0, 1]), vec![0, 2]);
+    }
+
+    #[test]
+    fn follows_rows_past_a_move() {
+        // Moving `a` below `c` deletes it and inserts it again, so `b` and `c` move up
+        let rows = rows_after("a\nb\nc\nd\n", vec![(6..6, "a\n"), (0..2, "")], &[0, 1, 2, 3]);
+        assert_eq!(rows, vec![0, 1, 3]);
+        // Moving `d` to the top moves every other line down
+        let rows = rows_after("a\nb\nc\nd", vec![(0..0, "d\n"), (5..7, "")], &[0, 1, 2, 3]);
+        assert_eq!(rows, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(parse_range("d"), Ok((LineRange::Default, "d")));
+        assert_eq!(parse_range("%s/a/b/"), Ok((LineRange::Whole, "s/a/b/")));
+        assert_eq!(parse_range("3d"), Ok((LineRange::Single(address(AddressBase::Line(3), 0)), "d")));
+        assert_eq!(parse_range(".,$-2 sort"), Ok((LineRange::Pair(
+            address(AddressBase::Current, 0),
+            address(AddressBase::Last, -2),
+            false,
+        ), "sort")));
+        assert_eq!(parse_range("'a;+3y"), Ok((LineRange::Pair(
+            address(AddressBase::Mark('a'), 0),
+            address(AddressBase::Current, 3),
+            true,
+        ), "y")));
+        assert_eq!(parse_range(",'>d"), Ok((LineRange::Pair(
+            address(AddressBase::Current, 0),
+            address(AddressBase::Mark('>'), 0),
+            false,
+        ), "d")));
+    }
+
+    #[test]
+    fn parses_pattern_addresses() {
+        let pattern = |pattern: &str, forward| AddressBase::Pattern { pattern: pattern.to_string(), forward };
+        assert_eq!(parse_range("/fn main/-1,?a\\?b?d"), Ok((LineRange::Pair(
+            address(pattern("fn main", true), -1),
+            address(pattern("a?b", false), 0),
+            false,
+        ), "d")));
+        assert_eq!(parse_range("/open"), Ok((LineRange::Single(address(pattern("open", true), 0)), "")));
+    }
+
+    #[test]
+    fn rejects_bad_marks() {
+        assert!(parse_range("'Ad").is_err());
+        assert!(parse_range("'").is_err());
+    }
+}
@@ -0,0 +1,202 @@
+//! Regular expression search patterns and substitutions.
+use std::ops::Range;
+use regex::{Regex, RegexBuilder};
+use crate::models::settings::editor_settings::SearchCase;
+
+/// How a search pattern is turned into a regular expression.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchOptions {
+    pub case: SearchCase,
+    /// Only match whole words
+    pub whole_word: bool,
+}
+
+/// A search that `n` and `N` repeat after Search Mode is left.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Search {
+    pub pattern: String,
+    pub options: SearchOptions,
+    /// Whether the search was started with `/` rather than `?`
+    pub forward: bool,
+}
+
+/// Compiles a search pattern into a regular expression that can match across lines.
+/// A `\c` anywhere in the pattern ignores case and a `\C` respects it, overriding the options.
+/// With smart case the search ignores case unless the pattern has an uppercase letter.
+pub fn compile_pattern(pattern: &str, options: SearchOptions) -> Result<Regex, String> {
+    let mut case = options.case;
+    let mut source = String::with_capacity(pattern.len());
+    let mut has_uppercase = false;
+
+    let mut chars = pattern.chars();
+    while let Some(chr) = chars.next() {
+        if chr != '\\' {
+            has_uppercase |= chr.is_uppercase();
+            source.push(chr);
+            continue;
+        }
+        match chars.next() {
+            Some('c') => case = SearchCase::Insensitive,
+            Some('C') => case = SearchCase::Sensitive,
+            Some(escaped) => {
+                source.push('\\');
+                source.push(escaped);
+            }
+            None => source.push('\\'),
+        }
+    }
+
+    if source.is_empty() {
+        return Err(String::from("Empty pattern"));
+    }
+    if options.whole_word {
+        source = format!(r"\b(?:{})\b", source);
+    }
+
+    let ignore_case = match case {
+        SearchCase::Sensitive => false,
+        SearchCase::Insensitive => true,
+        SearchCase::Smart => !has_uppercase,
+    };
+
+    RegexBuilder::new(&source)
+        .case_insensitive(ignore_case)
+        .multi_line(true)
+        .crlf(true)
+        .build()
+        .map_err(|error| {
+            // Syntax errors draw a caret under the pattern, the last line has the reason
+            let message = error.to_string();
+            let reason = message.lines().last().unwrap_or_default().trim_start_matches("error: ");
+            format!("Invalid pattern: {}", reason)
+        })
+}
+
+/// A parsed `:s/pattern/replacement/flags` command.
+pub struct Substitution {
+    regex: Regex,
+    /// The replacement in the syntax of `regex::Captures::expand`
+    replacement: String,
+    /// Replace every match on a line instead of only the first one
+    pub global: bool,
+    /// Ask before making each replacement
+    pub confirm: bool,
+}
+
+impl Substitution {
+    /// Parses the text after the `s`, where the first character is the delimiter.
+    /// The flags are `g` for every match on a line, `i` and `I` to ignore or respect case, and `c` to confirm.
+    pub fn parse(text: &str, case: SearchCase) -> Result<Self, String> {
+        let mut chars = text.chars();
+        let delimiter = chars.next().ok_or(String::from("No pattern given"))?;
+
+        // Split on the delimiters that aren't escaped, `\/` stands for a plain `/`
+        let mut parts = vec![String::new()];
+        let mut escaped = false;
+        for chr in chars {
+            if chr == delimiter && !escaped && parts.len() < 3 {
+                parts.push(String::new());
+                continue;
+            }
+            let part = parts.last_mut().unwrap();
+            if escaped {
+                if chr != delimiter {
+                    part.push('\\');
+                }
+                part.push(chr);
+                escaped = false;
+            } else if chr == '\\' {
+                escaped = true;
+            } else {
+                part.push(chr);
+            }
+        }
+        if escaped {
+            parts.last_mut().unwrap().push('\\');
+        }
+
+        let pattern = &parts[0];
+        let replacement = parts.get(1).map_or("", String::as_str);
+        let flags = parts.get(2).map_or("", String::as_str);
+        if pattern.is_empty() {
+            return Err(String::from("No pattern given"));
+        }
+
+        let mut options = SearchOptions {
+            case,
+            whole_word: false,
+        };
+        let mut global = false;
+        let mut confirm = false;
+        for flag in flags.trim().chars() {
+            match flag {
+                'g' => global = true,
+                'i' => options.case = SearchCase::Insensitive,
+                'I' => options.case = SearchCase::Sensitive,
+                'c' => confirm = true,
+                _ => return Err(format!("Unknown flag: {}", flag)),
+            }
+        }
+
+        Ok(Substitution {
+            regex: compile_pattern(pattern, options)?,
+            replacement: expand_syntax(replacement),
+            global,
+            confirm,
+        })
+    }
+
+    /// Finds the matches in `text`, which are the rows being substituted, along with the text that replaces each of them.
+    /// `offset` is where the rows start in the buffer, which the returned ranges count from.
+    pub fn find_edits(&self, text: &str, offset: usize) -> Vec<(Range<usize>, String)> {
+        let mut edits = Vec::new();
+        let mut searched_to = 0;
+        let mut line_start = 0;
+        let mut last_line = None;
+
+        for captures in self.regex.captures_iter(text) {
+            let Some(found) = captures.get(0).map(|found| found.range()) else {
+                continue;
+            };
+
+            if !self.global {
+                if let Some(newline) = text[searched_to..found.start].rfind('\n') {
+                    line_start = searched_to + newline + 1;
+                }
+                searched_to = found.start;
+                if last_line == Some(line_start) {
+                    continue;
+                }
+                last_line = Some(line_start);
+            }
+
+            let mut replacement = String::new();
+            captures.expand(&self.replacement, &mut replacement);
+            edits.push((found.start + offset..found.end + offset, replacement));
+        }
+        edits
+    }
+}
+
+/// Turns the `\1` groups and `&` whole match of a replacement into the syntax that `regex` expands.
+/// A `$` is kept as it is, like in Vim.
+fn expand_syntax(replacement: &str) -> String {
+    let mut output = String::new();
+    let mut chars = replacement.chars();
+    while let Some(chr) = chars.next() {
+        match chr {
+            '\\' => match chars.next() {
+                Some(digit @ '0'..='9') => output.push_str(&format!("${{{}}}", digit)),
+                Some('n') => output.push('\n'),
+                Some('t') => output.push('\t'),
+                Some('$') => output.push_str("$$"),
+                Some(other) => output.push(other),
+                None => output.push('\\'),
+            },
+            '&' => output.push_str("${0}"),
+            '$' => output.push_str("$$"),
+            _ => output.push(chr),
+        }
+    }
+    output
+}
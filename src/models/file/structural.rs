@@ -0,0 +1,260 @@
+//! Structural search and replace with code templates or tree-sitter queries.
+use std::collections::HashMap;
+use std::ops::Range;
+use tree_sitter::{Language, Node, Parser, Query, QueryCursor, Tree};
+
+/// The prefix that metavariables are renamed to so that a template parses as code.
+const PLACEHOLDER_PREFIX: &str = "__ssr_";
+
+/// Code that a template is put inside of when it doesn't parse on its own, like a statement in C.
+/// The first context that parses without errors is used.
+const TEMPLATE_CONTEXTS: [&str; 5] = [
+    "fn __ssr() { {}; }",
+    "void __ssr(void) { {}; }",
+    "func __ssr() { {} }",
+    "class __ssr { void f() { {}; } }",
+    "{}",
+];
+
+/// A part of the buffer that matched a pattern and the text bound to each of its metavariables.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructuralMatch {
+    pub range: Range<usize>,
+    pub bindings: HashMap<String, String>,
+}
+
+pub enum StructuralPattern {
+    /// A snippet of code where `$name` stands for any syntax node
+    Template {
+        tree: Tree,
+        source: String,
+        /// The byte range of the pattern within `source`
+        range: Range<usize>,
+        /// Maps placeholder identifiers back to the metavariable names
+        placeholders: HashMap<String, String>,
+    },
+    /// A tree-sitter query whose captures are the metavariables
+    Query(Query),
+}
+
+impl StructuralPattern {
+    /// Parses a pattern for a language.
+    /// A pattern that compiles as a query with captures is a query, anything else is a code template.
+    pub fn new(pattern: &str, language: Language) -> Result<Self, String> {
+        let pattern = pattern.trim();
+        if pattern.is_empty() {
+            return Err(String::from("No pattern given"));
+        }
+
+        if pattern.starts_with('(') || pattern.starts_with('[') {
+            if let Ok(query) = Query::new(language, pattern) {
+                if !query.capture_names().is_empty() {
+                    return Ok(StructuralPattern::Query(query));
+                }
+            }
+        }
+
+        let mut placeholders = HashMap::new();
+        let code = replace_metavariables(pattern, |name| {
+            let placeholder = format!("{}{}", PLACEHOLDER_PREFIX, name);
+            placeholders.insert(placeholder.clone(), name.to_string());
+            Some(placeholder)
+        })?;
+
+        let mut parser = Parser::new();
+        parser.set_language(language).map_err(|e| e.to_string())?;
+
+        for context in TEMPLATE_CONTEXTS {
+            let offset = context.find("{}").unwrap_or(0);
+            let source = context.replacen("{}", &code, 1);
+            let Some(tree) = parser.parse(&source, None) else {
+                continue;
+            };
+            let is_last = context == "{}";
+            if tree.root_node().has_error() && !is_last {
+                continue;
+            }
+
+            let range = offset..offset + code.len();
+            let Some(root) = tree.root_node().descendant_for_byte_range(range.start, range.end) else {
+                continue;
+            };
+            if root.has_error() {
+                break;
+            }
+            if placeholders.contains_key(&source[root.byte_range()]) {
+                return Err(String::from("The pattern can't be just a metavariable"));
+            }
+
+            return Ok(StructuralPattern::Template {
+                range: root.byte_range(),
+                tree,
+                source,
+                placeholders,
+            });
+        }
+
+        Err(format!("Could not parse the pattern: {}", pattern))
+    }
+
+    /// Finds the matches in a tree that lie within `scope`.
+    /// Matches don't overlap, an outer match hides any matches inside of it.
+    pub fn find_matches(&self, tree: &Tree, text: &str, scope: Range<usize>) -> Vec<StructuralMatch> {
+        let mut matches = match self {
+            StructuralPattern::Query(query) => Self::find_query_matches(query, tree, text),
+            StructuralPattern::Template { tree: pattern_tree, source, range, placeholders } => {
+                let pattern = pattern_tree.root_node().descendant_for_byte_range(range.start, range.end);
+                let Some(pattern) = pattern else {
+                    return Vec::new();
+                };
+
+                let mut matches = Vec::new();
+                let mut stack = vec![tree.root_node()];
+                while let Some(node) = stack.pop() {
+                    if node.end_byte() <= scope.start || node.start_byte() >= scope.end {
+                        continue;
+                    }
+                    if node.kind_id() == pattern.kind_id() {
+                        let mut bindings = HashMap::new();
+                        if match_node(pattern, source, node, text, placeholders, &mut bindings) {
+                            matches.push(StructuralMatch {
+                                range: node.byte_range(),
+                                bindings,
+                            });
+                            continue;
+                        }
+                    }
+                    let mut cursor = node.walk();
+                    let children = node.children(&mut cursor).collect::<Vec<_>>();
+                    stack.extend(children.into_iter().rev());
+                }
+                matches
+            }
+        };
+
+        matches.retain(|found| found.range.start >= scope.start && found.range.end <= scope.end);
+        matches.sort_by(|a, b| a.range.start.cmp(&b.range.start).then(b.range.end.cmp(&a.range.end)));
+        let mut end = 0;
+        matches.retain(|found| {
+            let keep = found.range.start >= end;
+            if keep {
+                end = found.range.end;
+            }
+            keep
+        });
+        matches
+    }
+
+    /// The range of a query match is its `@match` capture, or the span of all of its captures.
+    fn find_query_matches(query: &Query, tree: &Tree, text: &str) -> Vec<StructuralMatch> {
+        let match_index = query.capture_index_for_name("match");
+        let mut cursor = QueryCursor::new();
+
+        let mut matches = Vec::new();
+        for query_match in cursor.matches(query, tree.root_node(), text.as_bytes()) {
+            let mut bindings = HashMap::new();
+            let mut range: Option<Range<usize>> = None;
+            for capture in query_match.captures {
+                let capture_range = capture.node.byte_range();
+                let name = &query.capture_names()[capture.index as usize];
+                bindings.insert(name.clone(), text[capture_range.clone()].to_string());
+
+                range = match (range, match_index) {
+                    (_, Some(index)) if index == capture.index => Some(capture_range),
+                    (Some(range), Some(_)) => Some(range),
+                    (Some(range), None) => Some(range.start.min(capture_range.start)..range.end.max(capture_range.end)),
+                    (None, _) => Some(capture_range),
+                };
+            }
+            if let Some(range) = range {
+                matches.push(StructuralMatch {
+                    range,
+                    bindings,
+                });
+            }
+        }
+        matches
+    }
+}
+
+/// Checks if a node of the buffer has the same shape as a node of the pattern, binding the metavariables as it goes.
+/// Comments and other extra nodes in the buffer are ignored.
+fn match_node(pattern: Node, source: &str, node: Node, text: &str, placeholders: &HashMap<String, String>, bindings: &mut HashMap<String, String>) -> bool {
+    if let Some(name) = placeholders.get(&source[pattern.byte_range()]) {
+        let value = &text[node.byte_range()];
+        return match bindings.get(name) {
+            Some(bound) => bound == value,
+            None => {
+                bindings.insert(name.clone(), value.to_string());
+                true
+            }
+        };
+    }
+
+    if pattern.kind_id() != node.kind_id() {
+        return false;
+    }
+
+    let pattern_children = children(pattern);
+    let node_children = children(node);
+    if pattern_children.is_empty() && node_children.is_empty() {
+        return source[pattern.byte_range()] == text[node.byte_range()];
+    }
+    if pattern_children.len() != node_children.len() {
+        return false;
+    }
+
+    pattern_children.into_iter().zip(node_children)
+        .all(|(pattern, node)| match_node(pattern, source, node, text, placeholders, bindings))
+}
+
+/// Returns the children of a node without comments and the zero width nodes that error recovery inserts.
+fn children(node: Node) -> Vec<Node> {
+    let mut cursor = node.walk();
+    node.children(&mut cursor)
+        .filter(|child| !child.is_extra() && !child.is_missing())
+        .collect()
+}
+
+/// Fills in the `$name` metavariables of a replacement template with the text they were bound to.
+pub fn expand_template(template: &str, bindings: &HashMap<String, String>) -> Result<String, String> {
+    replace_metavariables(template, |name| bindings.get(name).cloned())
+}
+
+/// Replaces every `$name` in a string using `replace`, where `$$` stands for a plain `$`.
+fn replace_metavariables<F>(template: &str, mut replace: F) -> Result<String, String> where F: FnMut(&str) -> Option<String> {
+    let mut output = String::new();
+    let mut chars = template.char_indices().peekable();
+    while let Some((index, chr)) = chars.next() {
+        if chr != '$' {
+            output.push(chr);
+            continue;
+        }
+        if let Some((_, '$')) = chars.peek() {
+            chars.next();
+            output.push('$');
+            continue;
+        }
+
+        let start = index + 1;
+        let mut end = start;
+        while let Some((next, c)) = chars.peek() {
+            if !(c.is_alphanumeric() || *c == '_') {
+                break;
+            }
+            end = next + c.len_utf8();
+            chars.next();
+        }
+        if start == end {
+            output.push('$');
+            continue;
+        }
+
+        let name = &template[start..end];
+        match replace(name) {
+            Some(value) => output.push_str(&value),
+            None => return Err(format!("Unknown metavariable: ${}", name)),
+        }
+    }
+    Ok(output)
+}
@@ -0,0 +1,70 @@
+//! Definitions found in a file by the language's tags query.
+use std::fmt;
+use std::ops::Range;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Module,
+    Type,
+    Interface,
+    Implementation,
+    Function,
+    Method,
+    Constant,
+    Macro,
+}
+
+impl SymbolKind {
+    /// Converts the suffix of a `@definition.<kind>` capture into a kind.
+    pub fn from_capture(name: &str) -> Option<SymbolKind> {
+        match name.strip_prefix("definition.")? {
+            "module" => Some(SymbolKind::Module),
+            "class" => Some(SymbolKind::Type),
+            "interface" => Some(SymbolKind::Interface),
+            "implementation" => Some(SymbolKind::Implementation),
+            "function" => Some(SymbolKind::Function),
+            "method" => Some(SymbolKind::Method),
+            "constant" => Some(SymbolKind::Constant),
+            "macro" => Some(SymbolKind::Macro),
+            _ => None,
+        }
+    }
+
+    /// How specific the kind is when several patterns capture the same node.
+    /// A function inside an impl is captured as both a function and a method, and the method wins.
+    pub fn specificity(&self) -> u8 {
+        match self {
+            SymbolKind::Method => 2,
+            SymbolKind::Interface => 1,
+            _ => 0,
+        }
+    }
+}
+
+impl fmt::Display for SymbolKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymbolKind::Module => write!(f, "mod"),
+            SymbolKind::Type => write!(f, "type"),
+            SymbolKind::Interface => write!(f, "trait"),
+            SymbolKind::Implementation => write!(f, "impl"),
+            SymbolKind::Function => write!(f, "fn"),
+            SymbolKind::Method => write!(f, "method"),
+            SymbolKind::Constant => write!(f, "const"),
+            SymbolKind::Macro => write!(f, "macro"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// The bytes of the whole definition
+    pub range: Range<usize>,
+    /// The byte where the symbol's name starts
+    pub name_start: usize,
+    /// The number of symbols that this symbol is nested in
+    pub depth: usize,
+}
@@ -2,11 +2,12 @@ use std::cell::RefCell;
 use std::path::PathBuf;
 use std::rc::Rc;
 use crate::models::file::File;
+use crate::models::key::key_sequence_to_string;
 use crate::models::settings::Settings;
 
 //---------------------------------------|----------------------------------------
 pub static TITLE_TEXT: &str = "\n                             SEVI - main help file\n\n";
-pub static HELP_TEXT: [&str;199] = ["You can save this file by typing \":w<Enter>\".\n",
+pub static HELP_TEXT: [&str; 371] = ["You can save this file by typing \":w<Enter>\".\n",
     "Move around:\n",
     "    You can use the arrow keys to move around.\n",
     "    You can also use the 'h', 'j', 'k', and 'l' keys to move left, down, up, and right respectively.\n",
@@ -40,7 +41,14 @@ pub static HELP_TEXT: [&str;199] = ["You can save this file by typing \":w<Enter
     "    \"C-u\" - move up half a page\n",
     "    \"C-d\" - move down half a page\n",
     "    \"-\" - move up one line\n",
-    "    \"+\" - move down one line\n\n",
+    "    \"+\" - move down one line\n",
+    "    \"%\" - move to the matching bracket or pair, like \"/*\" and \"*/\"\n",
+    "          The pair under or next to the cursor and its match are highlighted. Brackets\n",
+    "          inside of strings and comments are skipped when the language is known.\n\n",
+    "Syntax Selection Keybindings for Selection Mode:\n",
+    "    \"M-o\" - expand the selection to the enclosing syntax node\n",
+    "    \"M-i\" - shrink the selection back to the previous selection\n",
+    "    Expansion requires a language with a syntax tree (e.g. Rust or C).\n\n",
     "Mode Change Keybindings:\n",
     "    Normal Mode:     \"Esc\" (Usually)\n",
     "    Insert Mode:     \"i\", \"I\", \"a\", \"A\", \"o\", \"O\"\n",
@@ -97,7 +105,105 @@ pub static HELP_TEXT: [&str;199] = ["You can save this file by typing \":w<Enter
     "    \"C-l\" - move to the next search result\n",
     "    \"C-h\" - move to the previous search result\n",
     "    \"C-j\" - move to the next search result\n",
-    "    \"C-k\" - move to the previous search result\n\n",
+    "    \"C-k\" - move to the previous search result\n",
+    "    \"M-c\" - cycle between case sensitive, case insensitive and smart case\n",
+    "    \"M-w\" - toggle matching whole words only\n",
+    "    \"<Enter>\" - stop at the current match, the matches stay highlighted until \"<Esc>\"\n",
+    "    \"<Esc>\" - cancel the search and go back to where it started\n",
+    "    \"n\" - in Normal Mode, move to the next match of the last search\n",
+    "    \"N\" - in Normal Mode, move to the previous match of the last search\n",
+    "    \"*\" - search forward for the word under the cursor, \"#\" searches backward\n",
+    "    \"g*\", \"g#\" - the same, but also match the word inside of longer words\n",
+    "    In Selection Mode, \"*\" and \"#\" search for the selected text.\n",
+    "    Searches are regular expressions and matches can span lines.\n",
+    "    The cursor jumps to the nearest match while typing and the search wraps around the file.\n",
+    "    \"\\c\" in the pattern ignores case and \"\\C\" respects it.\n\n",
+    "Indentation:\n",
+    "    New lines keep the indentation of the line above and gain or lose a level based on the\n",
+    "    syntax tree. Typing a closing delimiter at the start of a line dedents it.\n",
+    "    \"==\" - re-indent the current line\n",
+    "    \"=a\" - re-indent the whole file\n",
+    "    \"=\" - re-indent the selected lines (Selection Mode)\n",
+    "    \":reindent file<Enter>\" also re-indents the whole file.\n\n",
+    "Comments:\n",
+    "    \"gcc\" - toggle the comment on the current line, or on count lines (e.g. \"3gcc\")\n",
+    "    \"gc\" - toggle the comment on the selected lines (Selection Mode)\n",
+    "    Comments use the language's line comment token and line up at the smallest indentation.\n\n",
+    "Embedded Languages:\n",
+    "    Code in another language, such as \"python3 -c '...'\" in a shell script or a string tagged\n",
+    "    with a comment like \"/* python */\", is parsed as that language. Comment toggling and\n",
+    "    syntax tree selection inside it use the embedded language.\n\n",
+    "Symbols:\n",
+    "    \"gs\" - open a fuzzy picker of the functions, types and impls in the file\n",
+    "    \"gO\" - toggle the outline pane, which highlights the symbol under the cursor\n",
+    "    In the picker, type to filter, use \"C-n\"/\"C-p\" or the arrow keys to choose and\n",
+    "    \"<Enter>\" to jump. \":symbols<Enter>\" and \":outline<Enter>\" do the same.\n\n",
+    "Syntax Errors:\n",
+    "    Parse errors are underlined and their rows are marked with \"E\" in the gutter.\n",
+    "    \"]e\" - move to the next syntax error\n",
+    "    \"[e\" - move to the previous syntax error\n\n",
+    "History:\n",
+    "    In Command Mode and Search Mode, \"<Up>\" and \"<Down>\" go through the earlier commands or\n",
+    "    searches that start with what has been typed. The history is kept between sessions.\n",
+    "    \":history<Enter>\" opens a picker of the commands and \":history search<Enter>\" one of the\n",
+    "    searches, where \"<Enter>\" runs the chosen entry again.\n\n",
+    "Completion:\n",
+    "    In Command Mode, \"<Tab>\" completes the word before the cursor: command names, paths after\n",
+    "    \":e\" and \":w\" (where \"~\" is the home directory), the names of the other open buffers after\n",
+    "    \":e\" and setting names and values after \":set\". Pressing \"<Tab>\" again goes to the next\n",
+    "    candidate and \"<S-Tab>\" to the previous one. A popup lists the candidates when there are\n",
+    "    several.\n\n",
+    "Line Ranges:\n",
+    "    Some commands can start with a range of lines, like \":10,20d\" or \":%y\". Without one they\n",
+    "    work on the current line, or on the selected lines when \":\" is pressed in Selection Mode.\n",
+    "    \"5\" is line 5, \".\" the current line, \"$\" the last line and \"%\" every line.\n",
+    "    \"'<\" and \"'>\" are the ends of the last selection and \"'a\" is a line marked with \":mark a\".\n",
+    "    \"/pattern/\" is the next line that matches and \"?pattern?\" the previous one.\n",
+    "    \"+3\" and \"-3\" count from an address or from the current line, so \".,.+5\" is six lines.\n",
+    "    With \";\" instead of \",\" the second address counts from the first one.\n",
+    "    \":d [register]\"     - delete the lines\n",
+    "    \":y [register]\"     - copy the lines\n",
+    "    \":m <address>\"      - move the lines below the address, where \"0\" is the top of the file\n",
+    "    \":t <address>\"      - copy the lines below the address, also \":co\"\n",
+    "    \":>\" and \":<\"       - indent or outdent the lines, once for each \">\" or \"<\"\n",
+    "    \":sort[!] [inu]\"    - sort the lines, reversed with \"!\", ignoring case with \"i\", by the\n",
+    "                         first number with \"n\" and keeping one of equal lines with \"u\"\n",
+    "    \":w[!] <path>\"      - write the lines to a file, \"!\" overwrites it\n",
+    "    \":mark <a-z>\"       - remember the last line of the range, also \":k\"\n",
+    "    \":normal <keys>\"    - type the keys in Normal Mode on each line, with keys like \"<Esc>\"\n",
+    "                         or \"<C-r>\" written in angle brackets\n",
+    "    A range on its own, like \":42\", moves to its last line.\n",
+    "    \":y A\" with an uppercase register adds the lines to the end of register \"a\".\n\n",
+    "Global Commands:\n",
+    "    \":g/pattern/command<Enter>\" runs a command on every line that matches the pattern, for\n",
+    "    example \":g/TODO/d\" deletes those lines and \":g/^fn/normal o<Esc>\" adds a blank line\n",
+    "    below each one. \":v/pattern/command\" or \":g!/pattern/command\" runs it on the lines that\n",
+    "    don't match. The lines are found before the command runs, the whole file is searched\n",
+    "    unless a range is given, and all of the changes are undone together.\n\n",
+    "Substitute:\n",
+    "    \":s/pattern/replacement/flags<Enter>\" replaces the first match of a regular expression on\n",
+    "    the current line, or on the lines of a range like \":%s/old/new/g\".\n",
+    "    In the replacement, \"\\1\" is a capture group and \"&\" is the whole match.\n",
+    "    The flags are \"g\" for every match on a line, \"i\" to ignore case and \"c\" to confirm each\n",
+    "    match with \"y\" (yes), \"n\" (no), \"a\" (all) or \"q\" (quit). All of the replacements are\n",
+    "    one undo step.\n\n",
+    "Structural Search and Replace:\n",
+    "    \":ssr <pattern> ==>> <replacement><Enter>\" highlights every match of the pattern as a preview\n",
+    "    and \":ssr!<Enter>\" then rewrites them all as one undo step. \":ssr! <pattern> ==>> <replacement>\"\n",
+    "    skips the preview and \":ssr <pattern>\" only highlights the matches.\n",
+    "    The pattern is code where \"$name\" stands for any syntax node, for example:\n",
+    "        :ssr $x.unwrap() ==>> $x.expect(\"...\")\n",
+    "    It can also be a tree-sitter query whose captures are used as \"$name\" in the replacement.\n",
+    "    The \"@match\" capture, or else all of the captures, is the text that gets replaced.\n",
+    "    Pressing \":\" in Selection Mode limits the command to the selection.\n\n",
+    "Folding Keybindings:\n",
+    "    \"za\" - toggle the fold under the cursor\n",
+    "    \"zo\" - open the fold under the cursor\n",
+    "    \"zc\" - close the fold under the cursor\n",
+    "    \"zR\" - open all folds\n",
+    "    \"zM\" - close all folds\n",
+    "    Folds come from the syntax tree when the language is known and from indentation\n",
+    "    otherwise. A closed fold is shown as one line with the number of lines it hides.\n\n",
     "Undo/Redo Keybindings:\n",
     "    \"u\" - undo\n",
     "    \"C-r\" - redo\n\n",
@@ -140,20 +246,61 @@ pub static HELP_TEXT: [&str;199] = ["You can save this file by typing \":w<Enter
     "                It is a boolean value. Rainbow delimiters, or Rainbow Parenthesis is a plugin\n",
     "                for Emacs that highlights matching parenthesis and other symbol pairs with \n",
     "                different colors.\n",
+    "        sticky_scroll - This setting controls how many header lines of the enclosing functions,\n",
+    "                impls and other scopes are pinned to the top of the buffer once they scroll\n",
+    "                out of view. It is an integer and 0 turns it off.\n",
+    "        search_case - This setting controls how searches start out treating letter case.\n",
+    "            Possible values are:\n",
+    "                \"Sensitive\" - Uppercase and lowercase letters are different.\n",
+    "                \"Insensitive\" - Uppercase and lowercase letters are the same.\n",
+    "                \"Smart\" - Case is ignored unless the pattern has an uppercase letter.\n",
     "        default_mode - This setting controls the default mode that the editor starts in.\n",
     "            Possible values are:\n",
     "                \"Normal\" - The editor starts in Normal Mode.\n",
     "                \"Insert\" - The editor starts in Insert Mode.\n",
+    "        key_timeout - This setting controls how many milliseconds Sevi waits for the next key\n",
+    "                when the keys typed so far start a longer binding, like \"g\" before \"gg\".\n",
+    "                Once it runs out the keys are run on their own, or typed in modes like Insert.\n",
+    "                It is an integer and defaults to 1000.\n",
+    "        which_key_delay - This setting controls how many milliseconds Sevi waits after such a key\n",
+    "                before showing a popup with the keys that can come next and what they run.\n",
+    "                It is an integer and defaults to 500.\n",
+    "        leader - This setting is the key that \"<leader>\" stands for in bindings, written like\n",
+    "                the keys of \":bind\", like \"<Space>\" or \",\". It defaults to \"\\\".\n",
+    "                Bindings use the leader that was set when they were made.\n",
+    "        keymap_preset - This setting picks the built-in bindings that keybindings.toml is added on\n",
+    "                top of. Changing it with \":set\" loads keybindings.toml again.\n",
+    "            Possible values are:\n",
+    "                \"Vim\" - An operator comes before the motion, like \"dw\". This is the default.\n",
+    "                \"Helix\" - Motions like \"w\" select the text that they move over and an\n",
+    "                        operator like \"d\" or \"c\" acts on the selection, like in Helix and\n",
+    "                        Kakoune. \"x\" selects lines and \"gh\", \"gl\", \"gg\" and \"ge\" go to\n",
+    "                        the start and end of the line and the file.\n",
+    "                \"Emacs\" - Sevi starts in Insert Mode and moves with chords like \"<C-f>\",\n",
+    "                        \"<C-a>\" and \"<M-f>\". \"<C-Space>\" starts a selection, \"<M-w>\" and\n",
+    "                        \"<C-w>\" copy and cut it, \"<C-y>\" pastes, \"<C-g>\" cancels, \"<M-x>\"\n",
+    "                        opens the command line and \"<C-x><C-s>\" saves.\n",
+    "                Setting default_mode as well overrides the mode that a preset starts in.\n",
     "        pairs - This setting holds the data for the pairs that are used in Pair Mode.\n",
     "            The format is an array of arrays of strings. Each array of strings is a pair.\n",
     "            For example, one of the pairs is:\n",
-    "                [\"(\", \")\"]\n\n",
+    "                [\"(\", \")\"]\n",
+    "        All of these except for pairs can also be changed while Sevi is running with \":set\":\n",
+    "            \":set tab_size=2\"   - change a setting\n",
+    "            \":set tab_size?\"    - show a setting\n",
+    "            \":set use_spaces\"   - turn a true or false setting on, \":set nouse_spaces\" turns\n",
+    "                                 it off and \":set use_spaces!\" toggles it\n",
+    "            \":set tab_size&\"    - go back to the default\n",
+    "            \":set\"              - list the settings that differ from the defaults\n",
+    "        \":setlocal\" changes tab_size and use_spaces for the current buffer only.\n\n",
     "    colors.toml:\n",
     "        The colors.toml uses toml tables extensively to organize the colors.\n",
     "        rainbow_delimiters - This is the only array but it holds the colors for the rainbow\n",
     "               delimiters.\n",
     "        selected - The color scheme for the selected text.\n",
     "        buffer_color - The color scheme for displayed text.\n",
+    "        sticky_scroll - The color scheme for the scope headers pinned to the top of the buffer.\n",
+    "        matching_pair - The color scheme for the pair at the cursor and its match.\n",
     "        number_bar - The color scheme for the number bar.\n",
     "            This one has two settings:\n",
     "                current_line - The color scheme for the line the cursor is on.\n",
@@ -193,6 +340,8 @@ pub static HELP_TEXT: [&str;199] = ["You can save this file by typing \":w<Enter
     "        The keybindings are quite extensive and will not be listed here.\n",
     "        You can however generate a configuration file with the default settings by running\n",
     "        Sevi with the \"--generate-default-settings\" (\"-g\") flag.\n",
+    "        That file holds the Vim bindings, so with another keymap_preset only keep the bindings\n",
+    "        that you changed.\n",
     "        The format is as follows:\n",
     "            \"<action> = <keybinding>\"\n",
     "             Where a keybinding can be one of the following:\n",
@@ -207,6 +356,30 @@ pub static HELP_TEXT: [&str;199] = ["You can save this file by typing \":w<Enter
     "                       a chain of keys to be used for a binding.\n",
     "                 - An array of the above table. This is to allow for multiple keybindings\n",
     "                   for a single action.\n",
+    "        Bindings can also be changed while Sevi is running, with keys written like \"dd\" or \"<C-s>\":\n",
+    "            \":bind Normal <C-s> w\"   - make Ctrl-s run \":w\" in Normal Mode, where the command can\n",
+    "                                     also be in quotes\n",
+    "            \":unbind Insert jj\"      - remove a binding, \"Universal\" is the mode for the bindings\n",
+    "                                     that every mode shares\n",
+    "            \":bindings [mode]\"       - list the bindings of a mode and whether each one is a\n",
+    "                                     default or was changed by you\n",
+    "        \":bind!\" and \":unbind!\" also save the change to keybindings.toml.\n",
+    "        Keys that don't lead to any binding are dropped right away, so a mistyped key never\n",
+    "        gets stuck in front of the next one.\n",
+    "        \"<leader>\" in the keys of a binding is the leader key, like \"<leader>w\" in\n",
+    "        keybindings.toml or \":bind Normal <leader>w w\".\n",
+    "    Aliases:\n",
+    "        The [Aliases] table of keybindings.toml gives names to commands or arrays of commands,\n",
+    "        which then run in order from \":\" or from a binding, like:\n",
+    "            reindent_and_save = [\"reindent\", \"w\"]\n",
+    "        An alias can run other aliases, but not itself, and doesn't take arguments.\n",
+    "        Aliases can be bound in Universal, Normal, Insert and Selection Mode.\n",
+    "    Checking the configuration:\n",
+    "        Settings, bindings and colors with a problem are skipped when Sevi starts, and the\n",
+    "        problems are shown in the info bar. Running Sevi with the \"--check-config\" flag\n",
+    "        lists every problem with its file and line, including bindings that hide another\n",
+    "        binding or that have to wait for the key timeout, and exits with an error if there\n",
+    "        are any.\n",
 
 
 
@@ -217,6 +390,35 @@ pub static HELP_TEXT: [&str;199] = ["You can save this file by typing \":w<Enter
 
 
 
+/// Creates a read-only file that lists the key bindings in effect for a mode and where each one comes from.
+pub fn create_bindings_file(settings: Rc<RefCell<Settings>>, mode: &str) -> File {
+    let entries = settings.borrow().mode_keybindings.list(mode);
+    let keys = entries.iter().map(|entry| key_sequence_to_string(&entry.keys)).collect::<Vec<_>>();
+    let keys_width = keys.iter().map(|keys| keys.chars().count()).max().unwrap_or(0).max(4);
+    let command_width = entries.iter().map(|entry| entry.command.chars().count()).max().unwrap_or(0).max(7);
+
+    let mut text = format!("{} key bindings\n", mode);
+    text.push_str("Change them with \":bind <mode> <keys> <command>\" and \":unbind <mode> <keys>\".\n\n");
+    text.push_str(&format!("{:<keys_width$}  {:<command_width$}  From\n", "Keys", "Command"));
+    for (entry, keys) in entries.iter().zip(keys) {
+        let from = match (entry.default, entry.universal) {
+            (true, false) => "default",
+            (false, false) => "user",
+            (true, true) => "default (Universal)",
+            (false, true) => "user (Universal)",
+        };
+        text.push_str(&format!("{:<keys_width$}  {:<command_width$}  {}\n", keys, entry.command, from));
+    }
+
+    let mut file = File::new(None, settings).unwrap();
+
+    file.set_path(PathBuf::from(format!("{}-bindings.txt", mode.to_lowercase())));
+    file.insert_after(0, text);
+    file.set_read_only(true);
+
+    file
+}
+
 pub fn create_help_file(settings: Rc<RefCell<Settings>>) -> File {
     let total_text = String::from(TITLE_TEXT) + &HELP_TEXT.join("");
 
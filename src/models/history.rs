@@ -0,0 +1,136 @@
+use std::fs;
+use std::fmt;
+use std::fmt::Formatter;
+use std::path::PathBuf;
+
+/// The most entries that a history keeps.
+const MAX_ENTRIES: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryKind {
+    /// Commands run from Command Mode
+    Command,
+    /// Patterns searched for in Search Mode
+    Search,
+}
+
+impl fmt::Display for HistoryKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            HistoryKind::Command => write!(f, "Command History"),
+            HistoryKind::Search => write!(f, "Search History"),
+        }
+    }
+}
+
+/// A history of command lines or search patterns that is kept in the XDG state directory.
+/// Up and Down walk through the entries that start with what was typed before the first press.
+pub struct History {
+    kind: HistoryKind,
+    /// The oldest entry comes first
+    entries: Vec<String>,
+    /// The text that was typed before walking through the history
+    prefix: Option<String>,
+    /// The entry that is shown, `None` when the typed text is shown
+    position: Option<usize>,
+}
+
+impl History {
+    pub fn new(kind: HistoryKind) -> Self {
+        Self {
+            kind,
+            entries: Vec::new(),
+            prefix: None,
+            position: None,
+        }
+    }
+
+    /// Reads the history from the state directory, which picks up the entries added by other panes.
+    pub fn load(kind: HistoryKind) -> Self {
+        let mut history = History::new(kind);
+        if let Some(contents) = Self::path(kind).and_then(|path| fs::read_to_string(path).ok()) {
+            history.entries = contents.lines()
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        history
+    }
+
+    fn path(kind: HistoryKind) -> Option<PathBuf> {
+        if crate::arg_parser::IGNORE_USER_SETTINGS.load(std::sync::atomic::Ordering::Relaxed) {
+            return None;
+        }
+
+        let file_name = match kind {
+            HistoryKind::Command => "command_history",
+            HistoryKind::Search => "search_history",
+        };
+        let xdg_dirs = xdg::BaseDirectories::with_prefix("sevi").ok()?;
+        xdg_dirs.place_state_file(file_name).ok()
+    }
+
+    /// Adds an entry as the newest one and saves the history.
+    /// An entry that was already in the history is moved to the end.
+    pub fn add(&mut self, entry: &str) {
+        self.reset();
+        if entry.trim().is_empty() || entry.contains('\n') {
+            return;
+        }
+
+        self.entries.retain(|old| old != entry);
+        self.entries.push(entry.to_string());
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.drain(..self.entries.len() - MAX_ENTRIES);
+        }
+
+        if let Some(path) = Self::path(self.kind) {
+            // The history is a convenience, so failing to save it isn't worth interrupting the user for
+            let _ = fs::write(path, self.entries.join("\n") + "\n");
+        }
+    }
+
+    /// The entries with the oldest first.
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Moves to an older entry that starts with the typed text and returns it.
+    pub fn older(&mut self, typed: &str) -> Option<String> {
+        let prefix = self.prefix.get_or_insert_with(|| typed.to_string());
+        let end = self.position.unwrap_or(self.entries.len());
+
+        let index = self.entries[..end].iter()
+            .rposition(|entry| entry.starts_with(prefix.as_str()) && entry != typed)?;
+        self.position = Some(index);
+        Some(self.entries[index].clone())
+    }
+
+    /// Moves to a newer entry that starts with the typed text and returns it.
+    /// Going past the newest entry gives back the text that was typed.
+    pub fn newer(&mut self) -> Option<String> {
+        let position = self.position?;
+        let prefix = self.prefix.clone().unwrap_or_default();
+
+        let found = self.entries.iter().enumerate()
+            .skip(position + 1)
+            .find(|(_, entry)| entry.starts_with(&prefix) && **entry != self.entries[position]);
+        match found {
+            Some((index, entry)) => {
+                let entry = entry.clone();
+                self.position = Some(index);
+                Some(entry)
+            }
+            None => {
+                self.reset();
+                Some(prefix)
+            }
+        }
+    }
+
+    /// Forgets the walk through the history, which is done when the text is edited.
+    pub fn reset(&mut self) {
+        self.prefix = None;
+        self.position = None;
+    }
+}
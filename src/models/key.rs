@@ -217,6 +217,132 @@ pub fn key_event_to_string(key_event: &KeyEvent) -> String {
     format!("{{ key = \"{}\", mod = [{}] }}", key_event.key, key_event.modifiers)
 }
 
+impl Key {
+    /// Looks up a key by the name that it is displayed with, like `Esc` or `F5`,
+    /// or by one of the names that Vim uses, like `CR` or `BS`.
+    pub fn from_name(name: &str) -> Option<Key> {
+        let key = match name {
+            "Space" => Key::Char(' '),
+            "lt" => Key::Char('<'),
+            "gt" => Key::Char('>'),
+            "Backspace" | "BS" => Key::Backspace,
+            "Enter" | "CR" | "Return" => Key::Enter,
+            "Left" => Key::Left,
+            "Right" => Key::Right,
+            "Up" => Key::Up,
+            "Down" => Key::Down,
+            "Home" => Key::Home,
+            "End" => Key::End,
+            "PageUp" => Key::PageUp,
+            "PageDown" => Key::PageDown,
+            "Tab" => Key::Tab,
+            "BackTab" => Key::BackTab,
+            "Delete" | "Del" => Key::Delete,
+            "Insert" => Key::Insert,
+            "Esc" => Key::Esc,
+            name => match name.strip_prefix('F').and_then(|number| number.parse::<u8>().ok()) {
+                Some(number) if (1..=24).contains(&number) => Key::F(number),
+                _ => {
+                    let mut chars = name.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(chr), None) => Key::Char(chr),
+                        _ => return None,
+                    }
+                }
+            },
+        };
+        Some(key)
+    }
+}
+
+/// Parses keys written the way Vim writes them in mappings, like `dd`, `A;<Esc>` or `<C-r>`.
+/// Inside angle brackets `C-` is Ctrl, `M-` or `A-` is Alt and `S-` is Shift.
+/// A `<` that doesn't start a key name is typed as it is.
+pub fn parse_key_sequence(text: &str) -> Vec<KeyEvent> {
+    let mut keys = Vec::new();
+    let mut rest = text;
+    while let Some(chr) = rest.chars().next() {
+        let special = match chr {
+            '<' => rest[1..].find('>').and_then(|end| {
+                let name = &rest[1..end + 1];
+                parse_key_name(name).map(|key| (key, name.len() + 2))
+            }),
+            _ => None,
+        };
+        match special {
+            Some((key, length)) => {
+                keys.push(key);
+                rest = &rest[length..];
+            }
+            None => {
+                keys.push(KeyEvent::new(Key::Char(chr), KeyModifiers::NONE));
+                rest = &rest[chr.len_utf8()..];
+            }
+        }
+    }
+    keys
+}
+
+/// Replaces each `<leader>` in keys written like `parse_key_sequence` reads them with the leader key.
+pub fn expand_leader(text: &str, leader: &str) -> String {
+    let mut expanded = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match rest.get(..8) {
+            Some(name) if name.eq_ignore_ascii_case("<leader>") => {
+                expanded.push_str(leader);
+                rest = &rest[8..];
+            }
+            _ => {
+                expanded.push('<');
+                rest = &rest[1..];
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Writes keys the way that `parse_key_sequence` reads them, like `gg` or `<C-s>`.
+pub fn key_sequence_to_string(keys: &[KeyEvent]) -> String {
+    keys.iter()
+        .map(|key| match (key.key, key.modifiers) {
+            (Key::Char('<'), KeyModifiers::NONE) => String::from("<lt>"),
+            (Key::Char(' '), KeyModifiers::NONE) => String::from("<Space>"),
+            (Key::Char(chr), KeyModifiers::NONE) => chr.to_string(),
+            (Key::BackTab, _) => String::from("<S-Tab>"),
+            (Key::Char(' '), _) => format!("<{}Space>", key.to_string().trim_end_matches(' ')),
+            _ => format!("<{}>", key),
+        })
+        .collect()
+}
+
+/// Parses what is between the angle brackets of a key like `<C-r>`.
+fn parse_key_name(name: &str) -> Option<KeyEvent> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut key_name = name;
+    while key_name.len() > 2 && key_name.as_bytes()[1] == b'-' {
+        modifiers |= match &key_name[..1] {
+            "C" | "c" => KeyModifiers::CTRL,
+            "M" | "m" | "A" | "a" => KeyModifiers::ALT,
+            "S" | "s" => KeyModifiers::SHIFT,
+            _ => return None,
+        };
+        key_name = &key_name[2..];
+    }
+
+    let key = match (key_name, modifiers.contains(KeyModifiers::SHIFT)) {
+        // Terminals send Shift-Tab as its own key
+        ("Tab", true) => Key::BackTab,
+        // A single character only has a name when it has modifiers, like `<C-r>`
+        (key_name, _) if key_name.chars().count() == 1 && modifiers == KeyModifiers::NONE => return None,
+        (key_name, _) => Key::from_name(key_name)?,
+    };
+    Some(KeyEvent::new(key, modifiers))
+}
+
 
 
 
@@ -13,6 +13,9 @@ pub mod cursor;
 pub mod status_bar;
 pub mod text_buffer;
 pub mod help;
+pub mod history;
+pub mod completion;
+pub mod command_registry;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Id {
@@ -20,6 +23,8 @@ pub enum Id {
     Status,
     NumberLine,
     Gutter,
+    Outline,
+    Picker,
     Input,
 }
 
@@ -39,6 +44,8 @@ pub enum AppEvent {
     Message(Box<str>),
     RemoveInfoDisplay,
     CreateHelpFile,
+    /// Opens the list of key bindings for a mode
+    OpenBindings(Box<str>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -55,6 +62,7 @@ pub enum Message {
     Tick,
     RemoveInfoDisplay,
     OpenHelpFile,
+    OpenBindings(Box<str>),
 }
 
 pub enum ModelMessage {
@@ -1,16 +1,24 @@
 use std::any::Any;
 use std::cell::RefCell;
 use std::rc::Rc;
+use crate::models::completion::{self, Completion};
+use crate::models::history::{History, HistoryKind};
 use crate::models::key::{Key, KeyEvent};
 use crate::models::mode::{Mode, TextMode};
+use crate::models::mode::pending_keys::{KeyAction, PendingKeys};
+use crate::models::mode::symbols::PickerItems;
 use crate::models::pane::TextPane;
 use crate::models::settings::Settings;
 
 pub struct CommandMode {
     command_buffer: String,
     edit_pos: usize,
-    key_buffer: Vec<KeyEvent>,
-    settings: Option<Rc<RefCell<Settings>>>
+    pending_keys: PendingKeys,
+    settings: Option<Rc<RefCell<Settings>>>,
+    history: History,
+    /// The candidates that Tab cycles through, kept until the command line is edited
+    completion: Option<Completion>,
+    picker: PickerItems,
 }
 
 
@@ -20,7 +28,14 @@ impl CommandMode {
             command_buffer: String::new(),
             edit_pos: 0,
             settings: None,
-            key_buffer: Vec::new(),
+            pending_keys: PendingKeys::new(),
+            history: History::new(HistoryKind::Command),
+            completion: None,
+            picker: PickerItems {
+                title: "Completions".to_string(),
+                items: Vec::new(),
+                selected: 0,
+            },
         }
     }
 
@@ -36,45 +51,91 @@ impl CommandMode {
                 pane.execute_command(&format!("change_mode {}", settings.editor_settings.default_mode));
             }
             "left" => {
-                //Todo: make sure that we move by the right byte size
-                self.edit_pos = self.edit_pos.saturating_sub(1);
+                self.edit_pos -= self.command_buffer[..self.edit_pos].chars().next_back().map_or(0, char::len_utf8);
             }
             "right" => {
-                if self.edit_pos < self.command_buffer.len() {
-                    //Todo: make sure that we move by the right byte size
-                    self.edit_pos += 1;
-                }
+                self.edit_pos += self.command_buffer[self.edit_pos..].chars().next().map_or(0, char::len_utf8);
             }
             "up" => {
-                self.edit_pos = 0;
+                if let Some(entry) = self.history.older(&self.command_buffer) {
+                    self.command_buffer = entry;
+                }
+                self.edit_pos = self.command_buffer.len();
             }
             "down" => {
-
+                if let Some(entry) = self.history.newer() {
+                    self.command_buffer = entry;
+                }
                 self.edit_pos = self.command_buffer.len();
             }
             "backspace" => {
                 if self.edit_pos > 0 {
-                    self.edit_pos -= 1;
+                    self.edit_pos -= self.command_buffer[..self.edit_pos].chars().next_back().map_or(0, char::len_utf8);
                     self.command_buffer.remove(self.edit_pos);
                 }
+                self.history.reset();
             }
             "delete" => {
                 if self.edit_pos < self.command_buffer.len() {
                     self.command_buffer.remove(self.edit_pos);
                 }
+                self.history.reset();
             }
             "execute" => {
-                pane.execute_command(&self.command_buffer);
-                self.command_buffer.clear();
+                let command = std::mem::take(&mut self.command_buffer);
                 self.edit_pos = 0;
+                self.history.add(&command);
 
+                // Leave command mode first so that commands like `:symbols` can switch to another mode
                 let settings = self.settings.clone().unwrap();
-                let settings = settings.borrow();
-                pane.execute_command(&format!("change_mode {}", settings.editor_settings.default_mode));
+                let default_mode = settings.borrow().editor_settings.default_mode.clone();
+                pane.execute_command(&format!("change_mode {}", default_mode));
+                pane.execute_command_line(&command);
+            }
+            "complete" => {
+                self.complete(false, pane);
+            }
+            "complete_previous" => {
+                self.complete(true, pane);
             }
             _ => {}
         }
     }
+
+    /// Completes the word before the cursor, or moves on to the next candidate when Tab is pressed again.
+    fn complete(&mut self, reverse: bool, pane: &mut dyn TextPane) {
+        let start = match &self.completion {
+            Some(completion) => {
+                let count = completion.candidates.len();
+                self.picker.selected = if reverse {
+                    (self.picker.selected + count - 1) % count
+                } else {
+                    (self.picker.selected + 1) % count
+                };
+                completion.start
+            }
+            None => {
+                let aliases = pane.get_settings().borrow().mode_keybindings.alias_names();
+                let completion = completion::complete(&self.command_buffer[..self.edit_pos], &pane.get_buffer_names(), &aliases);
+                if completion.candidates.is_empty() {
+                    pane.send_info_message("No completions");
+                    return;
+                }
+                let start = completion.start;
+                self.picker.selected = if reverse { completion.candidates.len() - 1 } else { 0 };
+                self.picker.items = completion.candidates.clone();
+                // A single candidate is inserted right away so that the next Tab completes from there
+                if completion.candidates.len() > 1 {
+                    self.completion = Some(completion);
+                }
+                start
+            }
+        };
+
+        let candidate = &self.picker.items[self.picker.selected];
+        self.command_buffer.replace_range(start..self.edit_pos, candidate);
+        self.edit_pos = start + candidate.len();
+    }
 }
 
 impl Mode for CommandMode {
@@ -95,44 +156,30 @@ impl Mode for CommandMode {
     }
 
     fn get_special(&self) -> Option<&dyn Any> {
-        None
+        self.completion.as_ref().map(|_| &self.picker as &dyn Any)
     }
 
     fn influence_cursor(&self) -> Option<usize> {
-        let offset = self.get_name().chars().count() + 2 + self.edit_pos;
+        let offset = self.get_name().chars().count() + 2 + self.command_buffer[..self.edit_pos].chars().count();
         Some(offset)
     }
-}
 
-impl TextMode for CommandMode {
-    fn process_keypress(&mut self, key: KeyEvent, pane: &mut dyn TextPane) {
-        match key {
-            key => {
-                self.key_buffer.push(key);
+    fn get_pending_keys(&self) -> &PendingKeys {
+        &self.pending_keys
+    }
 
-                let settings = self.settings.clone().unwrap();
-                let mut settings = settings.borrow_mut();
+    fn get_pending_keys_mut(&mut self) -> &mut PendingKeys {
+        &mut self.pending_keys
+    }
 
-                if let Some(command) = settings.mode_keybindings.get(&self.get_name(), &self.key_buffer) {
-                    let command = command.clone();
-                    drop(settings);
-                    self.execute_command(&command, pane);
-                    self.key_buffer.clear();
-                } else {
-                    drop(settings);
-                    match key.key {
-                        Key::Char(c) => {
-                            self.command_buffer.insert(self.edit_pos, c);
-                            //TODO: make sure that we move by the right byte size
-                            self.edit_pos += 1;
-                            self.key_buffer.clear();
-                        }
-                        _ => {}
-                    }
-                }
+    fn get_settings(&self) -> Rc<RefCell<Settings>> {
+        self.settings.clone().unwrap()
+    }
+}
 
-            }
-        }
+impl TextMode for CommandMode {
+    fn process_keypress(&mut self, key: KeyEvent, pane: &mut dyn TextPane) {
+        self.push_key(key, pane);
     }
 
     fn update_status(&self, _pane: &dyn TextPane) -> (String, String, String) {
@@ -143,5 +190,27 @@ impl TextMode for CommandMode {
     }
 
     fn start(&mut self, _pane: &mut dyn TextPane) {
+        self.history = History::load(HistoryKind::Command);
+        self.completion = None;
+    }
+
+    fn run_key_actions(&mut self, actions: Vec<KeyAction>, pane: &mut dyn TextPane) {
+        for action in actions {
+            match action {
+                KeyAction::Command(command) => {
+                    if !command.starts_with("complete") {
+                        self.completion = None;
+                    }
+                    self.execute_command(&command, pane);
+                }
+                KeyAction::Unbound(KeyEvent { key: Key::Char(c), .. }) => {
+                    self.command_buffer.insert(self.edit_pos, c);
+                    self.edit_pos += c.len_utf8();
+                    self.history.reset();
+                    self.completion = None;
+                }
+                KeyAction::Unbound(_) => {}
+            }
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,168 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::ops::Range;
+use std::rc::Rc;
+use crate::models::key::KeyEvent;
+use crate::models::mode::{Mode, TextMode};
+use crate::models::mode::pending_keys::{KeyAction, PendingKeys};
+use crate::models::pane::TextPane;
+use crate::models::settings::Settings;
+
+/// Steps through the matches of a `:s///c` substitution, asking whether to replace each one.
+/// The accepted replacements are made together as a single undo step once every match has been answered.
+pub struct ConfirmMode {
+    /// The matches and the text that would replace each of them
+    edits: Vec<(Range<usize>, String)>,
+    /// The match that is being asked about
+    index: usize,
+    accepted: Vec<(Range<usize>, String)>,
+    settings: Option<Rc<RefCell<Settings>>>,
+    pending_keys: PendingKeys,
+}
+
+impl ConfirmMode {
+    pub fn new() -> Self {
+        Self {
+            edits: Vec::new(),
+            index: 0,
+            accepted: Vec::new(),
+            settings: None,
+            pending_keys: PendingKeys::new(),
+        }
+    }
+
+    fn execute_command(&mut self, command: &str, pane: &mut dyn TextPane) {
+        let mut command_args = command.split_whitespace();
+        let command_name = command_args.next().unwrap_or("");
+        match command_name {
+            "yes" => {
+                if let Some(edit) = self.edits.get(self.index) {
+                    self.accepted.push(edit.clone());
+                }
+                self.index += 1;
+                self.show_match(pane);
+            }
+            "no" => {
+                self.index += 1;
+                self.show_match(pane);
+            }
+            "all" => {
+                self.accepted.extend(self.edits.drain(self.index..));
+                self.finish(pane);
+            }
+            "quit" | "cancel" => {
+                self.finish(pane);
+            }
+            _ => {}
+        }
+    }
+
+    /// Highlights the current match and moves the cursor to it, or finishes when there are none left.
+    fn show_match(&mut self, pane: &mut dyn TextPane) {
+        let Some((range, _)) = self.edits.get(self.index) else {
+            self.finish(pane);
+            return;
+        };
+        let range = range.clone();
+
+        let file = pane.borrow_current_file_mut();
+        file.clear_highlights();
+        file.add_highlight(range.start, range.end.max(range.start + 1) - 1);
+        pane.execute_command(&format!("move to_byte {}", range.start));
+    }
+
+    fn finish(&mut self, pane: &mut dyn TextPane) {
+        let accepted = std::mem::take(&mut self.accepted);
+        let count = accepted.len();
+        self.edits.clear();
+
+        let file = pane.borrow_current_file_mut();
+        file.clear_highlights();
+        if count > 0 {
+            file.replace_ranges(accepted);
+        }
+
+        let settings = self.settings.clone().unwrap();
+        let default_mode = settings.borrow().editor_settings.default_mode.clone();
+        pane.execute_command(&format!("change_mode {}", default_mode));
+        pane.send_info_message(&format!("{} substitutions", count));
+    }
+}
+
+impl Default for ConfirmMode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mode for ConfirmMode {
+    fn get_name(&self) -> String {
+        "Confirm".to_string()
+    }
+
+    fn add_settings(&mut self, settings: Rc<RefCell<Settings>>) {
+        self.settings = Some(settings);
+    }
+
+    fn refresh(&mut self) {
+
+    }
+
+    fn add_special(&mut self, something: &dyn Any) {
+        if let Some(edits) = something.downcast_ref::<Vec<(Range<usize>, String)>>() {
+            self.edits = edits.clone();
+        }
+    }
+
+    fn get_special(&self) -> Option<&dyn Any> {
+        None
+    }
+
+    fn influence_cursor(&self) -> Option<usize> {
+        None
+    }
+
+    fn get_pending_keys(&self) -> &PendingKeys {
+        &self.pending_keys
+    }
+
+    fn get_pending_keys_mut(&mut self) -> &mut PendingKeys {
+        &mut self.pending_keys
+    }
+
+    fn get_settings(&self) -> Rc<RefCell<Settings>> {
+        self.settings.clone().unwrap()
+    }
+}
+
+impl TextMode for ConfirmMode {
+    fn process_keypress(&mut self, key: KeyEvent, pane: &mut dyn TextPane) {
+        self.push_key(key, pane);
+    }
+
+    fn update_status(&self, _pane: &dyn TextPane) -> (String, String, String) {
+        let first = match self.edits.get(self.index) {
+            Some((_, replacement)) => format!("replace with {:?}? (y/n/a/q)", replacement),
+            None => String::new(),
+        };
+        let second = format!("{}/{}", (self.index + 1).min(self.edits.len()), self.edits.len());
+
+        (self.get_name(), first, second)
+    }
+
+    fn start(&mut self, pane: &mut dyn TextPane) {
+        self.index = 0;
+        self.accepted.clear();
+        self.pending_keys.clear();
+        self.show_match(pane);
+    }
+
+    fn run_key_actions(&mut self, actions: Vec<KeyAction>, pane: &mut dyn TextPane) {
+        for action in actions {
+            match action {
+                KeyAction::Command(command) => self.execute_command(&command, pane),
+                KeyAction::Unbound(_) => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::models::file::search::{Search, SearchOptions};
+use crate::models::history::{History, HistoryKind};
+use crate::models::key::{Key, KeyEvent};
+use crate::models::mode::{Mode, TextMode};
+use crate::models::mode::pending_keys::{KeyAction, PendingKeys};
+use crate::models::mode::symbols::{fuzzy_score, PickerItems};
+use crate::models::pane::TextPane;
+use crate::models::settings::Settings;
+
+/// A picker over the command or search history that runs the chosen entry again.
+pub struct HistoryMode {
+    kind: HistoryKind,
+    query: String,
+    edit_pos: usize,
+    /// The entries with the newest first
+    entries: Vec<String>,
+    /// The indices of the entries that match the query, best match first
+    matches: Vec<usize>,
+    picker: PickerItems,
+    settings: Option<Rc<RefCell<Settings>>>,
+    pending_keys: PendingKeys,
+}
+
+impl HistoryMode {
+    pub fn new() -> Self {
+        Self {
+            kind: HistoryKind::Command,
+            query: String::new(),
+            edit_pos: 0,
+            entries: Vec::new(),
+            matches: Vec::new(),
+            picker: PickerItems {
+                title: HistoryKind::Command.to_string(),
+                items: Vec::new(),
+                selected: 0,
+            },
+            settings: None,
+            pending_keys: PendingKeys::new(),
+        }
+    }
+
+    fn execute_command(&mut self, command: &str, pane: &mut dyn TextPane) {
+        let mut command_args = command.split_whitespace();
+        let command_name = command_args.next().unwrap_or("");
+        match command_name {
+            "cancel" => {
+                self.leave(pane);
+            }
+            "left" => {
+                self.edit_pos -= self.query[..self.edit_pos].chars().next_back().map_or(0, char::len_utf8);
+            }
+            "right" => {
+                self.edit_pos += self.query[self.edit_pos..].chars().next().map_or(0, char::len_utf8);
+            }
+            "up" => {
+                self.picker.selected = self.picker.selected.saturating_sub(1);
+            }
+            "down" => {
+                self.picker.selected = (self.picker.selected + 1).min(self.matches.len().saturating_sub(1));
+            }
+            "start" => {
+                self.edit_pos = 0;
+            }
+            "end" => {
+                self.edit_pos = self.query.len();
+            }
+            "backspace" if self.edit_pos > 0 => {
+                self.edit_pos -= self.query[..self.edit_pos].chars().next_back().map_or(0, char::len_utf8);
+                self.query.remove(self.edit_pos);
+                self.filter();
+            }
+            "delete" if self.edit_pos < self.query.len() => {
+                self.query.remove(self.edit_pos);
+                self.filter();
+            }
+            "execute" => {
+                let entry = self.matches.get(self.picker.selected).map(|index| self.entries[*index].clone());
+                self.leave(pane);
+                match entry {
+                    Some(entry) => self.run(&entry, pane),
+                    None => pane.send_info_message("No matching entry"),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Runs a command again, or repeats a search forward from the cursor.
+    fn run(&self, entry: &str, pane: &mut dyn TextPane) {
+        // Opening the history from inside of the history would only show it again
+        if self.kind == HistoryKind::Command && entry.split_whitespace().next() == Some("history") {
+            return;
+        }
+        let mut history = History::load(self.kind);
+        history.add(entry);
+        match self.kind {
+            HistoryKind::Command => pane.execute_command_line(entry),
+            HistoryKind::Search => {
+                let settings = self.settings.clone().unwrap();
+                let case = settings.borrow().editor_settings.search_case;
+                pane.set_last_search(Search {
+                    pattern: entry.to_string(),
+                    options: SearchOptions {
+                        case,
+                        whole_word: false,
+                    },
+                    forward: true,
+                });
+                pane.execute_command("search next");
+            }
+        }
+    }
+
+    fn leave(&mut self, pane: &mut dyn TextPane) {
+        self.query.clear();
+        self.edit_pos = 0;
+        let settings = self.settings.clone().unwrap();
+        let settings = settings.borrow();
+        pane.execute_command(&format!("change_mode {}", settings.editor_settings.default_mode));
+    }
+
+    /// Ranks the entries against the query and rebuilds the list that the picker shows.
+    fn filter(&mut self) {
+        let mut scored = self.entries.iter().enumerate()
+            .filter_map(|(index, entry)| fuzzy_score(&self.query, entry).map(|score| (index, score)))
+            .collect::<Vec<_>>();
+        // Keep the newest entries first between entries that match equally well
+        scored.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        if self.query.is_empty() {
+            scored.sort_by_key(|(index, _)| *index);
+        }
+
+        self.matches = scored.into_iter().map(|(index, _)| index).collect();
+        self.picker.items = self.matches.iter().map(|index| self.entries[*index].clone()).collect();
+        self.picker.selected = 0;
+    }
+}
+
+impl Default for HistoryMode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mode for HistoryMode {
+    fn get_name(&self) -> String {
+        "History".to_string()
+    }
+
+    fn add_settings(&mut self, settings: Rc<RefCell<Settings>>) {
+        self.settings = Some(settings);
+    }
+
+    fn refresh(&mut self) {
+
+    }
+
+    fn add_special(&mut self, something: &dyn Any) {
+        if let Some(kind) = something.downcast_ref::<HistoryKind>() {
+            self.kind = *kind;
+        }
+    }
+
+    fn get_special(&self) -> Option<&dyn Any> {
+        Some(&self.picker)
+    }
+
+    fn influence_cursor(&self) -> Option<usize> {
+        let offset = self.get_name().chars().count() + 2 + self.query[..self.edit_pos].chars().count();
+        Some(offset)
+    }
+
+    fn get_pending_keys(&self) -> &PendingKeys {
+        &self.pending_keys
+    }
+
+    fn get_pending_keys_mut(&mut self) -> &mut PendingKeys {
+        &mut self.pending_keys
+    }
+
+    fn get_settings(&self) -> Rc<RefCell<Settings>> {
+        self.settings.clone().unwrap()
+    }
+}
+
+impl TextMode for HistoryMode {
+    fn process_keypress(&mut self, key: KeyEvent, pane: &mut dyn TextPane) {
+        self.push_key(key, pane);
+    }
+
+    fn update_status(&self, _pane: &dyn TextPane) -> (String, String, String) {
+        let first = format!(">{}", self.query);
+        let second = format!("{}/{}", self.matches.len(), self.entries.len());
+
+        (self.get_name(), first, second)
+    }
+
+    fn start(&mut self, pane: &mut dyn TextPane) {
+        self.query.clear();
+        self.edit_pos = 0;
+        self.pending_keys.clear();
+        self.picker.title = self.kind.to_string();
+        self.entries = History::load(self.kind).entries().iter().rev().cloned().collect();
+        self.filter();
+        if self.entries.is_empty() {
+            pane.send_info_message("The history is empty");
+        }
+    }
+
+    fn run_key_actions(&mut self, actions: Vec<KeyAction>, pane: &mut dyn TextPane) {
+        for action in actions {
+            match action {
+                KeyAction::Command(command) => self.execute_command(&command, pane),
+                KeyAction::Unbound(KeyEvent { key: Key::Char(c), .. }) => {
+                    self.query.insert(self.edit_pos, c);
+                    self.edit_pos += c.len_utf8();
+                    self.filter();
+                }
+                KeyAction::Unbound(_) => {}
+            }
+        }
+    }
+}
@@ -3,11 +3,12 @@ use std::cell::RefCell;
 use std::rc::Rc;
 use crate::models::key::{Key, KeyEvent};
 use crate::models::mode::{Mode, TextMode};
+use crate::models::mode::pending_keys::{KeyAction, PendingKeys};
 use crate::models::pane::TextPane;
 use crate::models::settings::Settings;
 
 pub struct InsertMode {
-    key_buffer: Vec<KeyEvent>,
+    pending_keys: PendingKeys,
     number_buffer: String,
     settings: Option<Rc<RefCell<Settings>>>,
 }
@@ -15,7 +16,7 @@ pub struct InsertMode {
 impl InsertMode {
     pub fn new() -> InsertMode {
         InsertMode {
-            key_buffer: Vec::new(),
+            pending_keys: PendingKeys::new(),
             number_buffer: String::new(),
             settings: None,
         }
@@ -25,10 +26,10 @@ impl InsertMode {
         match command {
             "cancel" => {
 
-                self.key_buffer.clear();
+                self.pending_keys.clear();
 
                 let settings = self.settings.clone().unwrap();
-                let settings = settings.borrow_mut();
+                let settings = settings.borrow();
                 if self.get_name() != settings.editor_settings.default_mode {
                     pane.execute_command("change_mode Normal");
                     pane.execute_command("move right 1");
@@ -97,10 +98,10 @@ impl InsertMode {
                 pane.execute_command("move next_word_back");
             },
             "previous_word_front" => {
-                pane.execute_command("move previous_word_front");
+                pane.execute_command("move prev_word_front");
             },
             "previous_word_back" => {
-                pane.execute_command("move previous_word_back");
+                pane.execute_command("move prev_word_back");
             },
             "command_mode" => {
                 pane.execute_command("change_mode Command");
@@ -218,11 +219,9 @@ impl InsertMode {
             "redo" => {
                 pane.execute_command("redo");
             }
-            _ => {
-
-            }
+            // Anything else, like an ex command bound with `:bind`, is run by the pane
+            _ => pane.execute_command_line(command),
         }
-        self.key_buffer.clear();
         self.number_buffer.clear();
     }
 }
@@ -251,6 +250,18 @@ impl Mode for InsertMode {
     fn influence_cursor(&self) -> Option<usize> {
         None
     }
+
+    fn get_pending_keys(&self) -> &PendingKeys {
+        &self.pending_keys
+    }
+
+    fn get_pending_keys_mut(&mut self) -> &mut PendingKeys {
+        &mut self.pending_keys
+    }
+
+    fn get_settings(&self) -> Rc<RefCell<Settings>> {
+        self.settings.clone().unwrap()
+    }
 }
 
 impl TextMode for InsertMode {
@@ -260,45 +271,16 @@ impl TextMode for InsertMode {
                 key: Key::Esc,
                 ..
             } => {
-                self.key_buffer.clear();
+                self.pending_keys.clear();
                 let settings = self.settings.clone().unwrap();
-                let settings = settings.borrow_mut();
+                let settings = settings.borrow();
                 if self.get_name() != settings.editor_settings.default_mode {
                     pane.execute_command("change_mode Normal");
                     pane.execute_command("move right 1");
                 }
             }
             key => {
-                self.key_buffer.push(key);
-
-                let settings = self.settings.clone().unwrap();
-                let mut settings = settings.borrow_mut();
-
-                let command = if let Some(command) = settings.mode_keybindings.get(&self.get_name(), &self.key_buffer) {
-                    let command = command.clone();
-                    drop(settings);
-                    Some(command)
-                } else {
-                    drop(settings);
-                    match key.key {
-                        Key::Char(c) => {
-                            let index = pane.get_current_byte_position();
-                            pane.insert_char(index, c);
-
-                            self.key_buffer.clear();
-                        }
-                        _ => {}
-                    }
-                    None
-                };
-                match command {
-                    Some(command) => {
-                        self.execute_command(&command, pane);
-                        self.key_buffer.clear();
-                    }
-                    None => {}
-                }
-
+                self.push_key(key, pane);
             }
         }
     }
@@ -309,8 +291,8 @@ impl TextMode for InsertMode {
         let first = format!("{}:{}", row + 1, col + 1);
 
         let mut second = String::new();
-        if !self.key_buffer.is_empty() {
-            for key in &self.key_buffer {
+        if !self.pending_keys.is_empty() {
+            for key in self.pending_keys.keys() {
                 second.push_str(&format!("{} ", key));
             }
         }
@@ -321,4 +303,17 @@ impl TextMode for InsertMode {
 
     fn start(&mut self, _pane: &mut dyn TextPane) {
     }
+
+    fn run_key_actions(&mut self, actions: Vec<KeyAction>, pane: &mut dyn TextPane) {
+        for action in actions {
+            match action {
+                KeyAction::Command(command) => self.execute_command(&command, pane),
+                KeyAction::Unbound(KeyEvent { key: Key::Char(c), .. }) => {
+                    let index = pane.get_current_byte_position();
+                    pane.insert_char(index, c);
+                }
+                KeyAction::Unbound(_) => {}
+            }
+        }
+    }
 }
\ No newline at end of file
@@ -4,6 +4,7 @@ use std::rc::Rc;
 use crate::models::key::KeyEvent;
 use crate::models::pane::TextPane;
 use crate::models::settings::Settings;
+use self::pending_keys::{KeyAction, PendingKeys};
 
 
 pub mod normal;
@@ -13,6 +14,10 @@ pub mod selection;
 pub mod search;
 pub mod mirror;
 pub mod pair;
+pub mod symbols;
+pub mod confirm;
+pub mod history;
+pub mod pending_keys;
 
 
 pub trait Mode {
@@ -28,6 +33,12 @@ pub trait Mode {
 
     fn influence_cursor(&self) -> Option<usize>;
 
+    /// The keys that have been typed but haven't made up a binding yet
+    fn get_pending_keys(&self) -> &PendingKeys;
+
+    fn get_pending_keys_mut(&mut self) -> &mut PendingKeys;
+
+    fn get_settings(&self) -> Rc<RefCell<Settings>>;
 
 }
 
@@ -39,5 +50,32 @@ pub trait TextMode: Mode {
 
     /// This is called when the mode is started
     fn start(&mut self, pane: &mut dyn TextPane);
+
+    /// Runs what the pending keys resolved to, either the command of a binding or a key that the mode handles itself
+    fn run_key_actions(&mut self, actions: Vec<KeyAction>, pane: &mut dyn TextPane);
+
+    /// Adds a key to the pending keys and runs whatever they resolve to
+    fn push_key(&mut self, key: KeyEvent, pane: &mut dyn TextPane) {
+        let name = self.get_name();
+        let settings = self.get_settings();
+        // The settings are only borrowed while resolving, since the commands that run may change them
+        let actions = self.get_pending_keys_mut().push(key, &name, &settings.borrow().mode_keybindings);
+        self.run_key_actions(actions, pane);
+    }
+
+    /// This is called once the key timeout runs out, so that pending keys that
+    /// are a binding on their own stop waiting for a longer one
+    fn flush_keys(&mut self, pane: &mut dyn TextPane) {
+        let name = self.get_name();
+        let settings = self.get_settings();
+        let actions = self.get_pending_keys_mut().flush(&name, &settings.borrow().mode_keybindings);
+        self.run_key_actions(actions, pane);
+    }
+
+    /// This is called on every tick, so that the mode can carry on with work that it does in steps.
+    /// Returns whether the pane needs to be drawn again.
+    fn idle(&mut self, _pane: &mut dyn TextPane) -> bool {
+        false
+    }
 }
 
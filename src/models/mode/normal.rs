@@ -10,13 +10,14 @@ use crate::models::pane::TextPane;
 use crate::models::settings::Settings;
 
 use super::{Mode, TextMode};
+use super::pending_keys::{KeyAction, PendingKeys};
 
 
 
 pub struct NormalMode {
     number_buffer: String,
     settings: Option<Rc<RefCell<Settings>>>,
-    key_buffer: Vec<KeyEvent>,
+    pending_keys: PendingKeys,
 }
 
 
@@ -26,7 +27,7 @@ impl NormalMode {
         NormalMode {
             number_buffer: String::new(),
             settings: None,
-            key_buffer: Vec::new(),
+            pending_keys: PendingKeys::new(),
         }
     }
 
@@ -37,7 +38,8 @@ impl NormalMode {
         match command_name {
             "cancel" => {
                 self.number_buffer.clear();
-                self.key_buffer.clear();
+                self.pending_keys.clear();
+                pane.execute_command("clear_selection");
             },
             "left" => {
                 pane.execute_command(&format!("move left {}", self.number_buffer));
@@ -105,6 +107,42 @@ impl NormalMode {
                 pane.execute_command(format!("move prev_word_back {}", self.number_buffer).as_str());
                 self.number_buffer.clear();
             },
+            "select_next_word_front" | "select_next_word_back" | "select_previous_word_front" | "select_previous_word_back" => {
+                // Selects what the motion moves over, so that an operator in Selection mode can act on it
+                let (col, row) = pane.get_cursor();
+                let motion = command_name.trim_start_matches("select_").replace("previous", "prev");
+                pane.execute_command(&format!("move {} {}", motion, self.number_buffer));
+                self.number_buffer.clear();
+                // The selection stops before the word that the cursor moved to, not on its first letter
+                if command_name == "select_next_word_front" && pane.get_cursor().0 > 0 {
+                    pane.execute_command("move left 1");
+                }
+                pane.execute_command(&format!("change_mode selection_normal {},{}", col, row));
+                return;
+            },
+            "search_next" => {
+                pane.execute_command(&format!("search next {}", self.number_buffer));
+                self.number_buffer.clear();
+            },
+            "search_previous" => {
+                pane.execute_command(&format!("search previous {}", self.number_buffer));
+                self.number_buffer.clear();
+            },
+            "search_word_forward" => {
+                pane.execute_command("search_word forward");
+            },
+            "search_word_backward" => {
+                pane.execute_command("search_word backward");
+            },
+            "search_partial_word_forward" => {
+                pane.execute_command("search_word forward partial");
+            },
+            "search_partial_word_backward" => {
+                pane.execute_command("search_word backward partial");
+            },
+            "goto_pair" => {
+                pane.execute_command("move to_pair");
+            },
             "insert_before" => {
                 pane.execute_command("change_mode insert_before");
             },
@@ -239,10 +277,46 @@ impl NormalMode {
             "redo" => {
                 pane.execute_command("redo");
             }
-            _ => {},
+            "reindent_line" => {
+                pane.execute_command("reindent line");
+            }
+            "reindent_file" => {
+                pane.execute_command("reindent file");
+            }
+            "toggle_comment" => {
+                pane.execute_command(&format!("comment {}", self.number_buffer));
+            }
+            "next_syntax_error" => {
+                pane.execute_command("syntax_error next");
+            }
+            "prev_syntax_error" => {
+                pane.execute_command("syntax_error prev");
+            }
+            "open_symbols" => {
+                pane.execute_command("symbols");
+            }
+            "toggle_outline" => {
+                pane.execute_command("outline toggle");
+            }
+            "toggle_fold" => {
+                pane.execute_command("fold toggle");
+            }
+            "open_fold" => {
+                pane.execute_command("fold open");
+            }
+            "close_fold" => {
+                pane.execute_command("fold close");
+            }
+            "open_all_folds" => {
+                pane.execute_command("fold open_all");
+            }
+            "close_all_folds" => {
+                pane.execute_command("fold close_all");
+            }
+            // Anything else, like an ex command bound with `:bind`, is run by the pane
+            _ => pane.execute_command_line(command),
         }
 
-        self.key_buffer.clear();
         self.number_buffer.clear();
 
     }
@@ -272,6 +346,18 @@ impl Mode for NormalMode {
     fn influence_cursor(&self) -> Option<usize> {
         None
     }
+
+    fn get_pending_keys(&self) -> &PendingKeys {
+        &self.pending_keys
+    }
+
+    fn get_pending_keys_mut(&mut self) -> &mut PendingKeys {
+        &mut self.pending_keys
+    }
+
+    fn get_settings(&self) -> Rc<RefCell<Settings>> {
+        self.settings.clone().unwrap()
+    }
 }
 
 
@@ -290,17 +376,7 @@ impl TextMode for NormalMode {
                     
                         if let Some(digit) = code.to_digit(10) {
                             if digit == 0 && self.number_buffer.is_empty() {
-                                self.key_buffer.push(KeyEvent {
-                                    key: Key::Char('0'),
-                                    modifiers: KeyModifiers::NONE,
-                                });
-                                let settings = self.settings.clone().unwrap();
-                                let mut settings = settings.borrow_mut();
-                                if let Some(command) = settings.mode_keybindings.get(&self.get_name(), &self.key_buffer) {
-                                    let command = command.to_string();
-                                    drop(settings);
-                                    self.execute_command(&command, pane);
-                                }
+                                self.push_key(key, pane);
                                 return;
                             }
 
@@ -318,20 +394,11 @@ impl TextMode for NormalMode {
                 key: Key::Esc,
                 ..
             } => {
-                self.key_buffer.clear();
+                self.pending_keys.clear();
                 self.number_buffer.clear();
             },
             key => {
-
-                self.key_buffer.push(key);
-
-                let settings = self.settings.clone().unwrap();
-                let mut settings = settings.borrow_mut();
-                if let Some(command) = settings.mode_keybindings.get(&self.get_name(), &self.key_buffer) {
-                    let command = command.to_string();
-                    drop(settings);
-                    self.execute_command(&command, pane);
-                }
+                self.push_key(key, pane);
             }
         }
     }
@@ -347,8 +414,8 @@ impl TextMode for NormalMode {
         }
 
         let mut second = String::new();
-        if !self.key_buffer.is_empty() {
-            for key in &self.key_buffer {
+        if !self.pending_keys.is_empty() {
+            for key in self.pending_keys.keys() {
                 second.push_str(&format!("{} ", key));
             }
         }
@@ -361,9 +428,19 @@ impl TextMode for NormalMode {
     }
 
     fn start(&mut self, _pane: &mut dyn TextPane) {
-        self.key_buffer.clear();
+        self.pending_keys.clear();
         self.number_buffer.clear();
     }
+
+    fn run_key_actions(&mut self, actions: Vec<KeyAction>, pane: &mut dyn TextPane) {
+        for action in actions {
+            match action {
+                KeyAction::Command(command) => self.execute_command(&command, pane),
+                // Keys that aren't bound do nothing in Normal mode
+                KeyAction::Unbound(_) => {}
+            }
+        }
+    }
 }
 
 
@@ -4,12 +4,13 @@ use std::rc::Rc;
 use crate::models::file::file::InsertPairs;
 use crate::models::key::{Key, KeyEvent};
 use crate::models::mode::{Mode, TextMode};
+use crate::models::mode::pending_keys::{KeyAction, PendingKeys};
 use crate::models::pane::TextPane;
 use crate::models::settings::Settings;
 
 pub struct PairMode {
     settings: Option<Rc<RefCell<Settings>>>,
-    key_buffer: Vec<KeyEvent>,
+    pending_keys: PendingKeys,
     text: String,
     edit_pos: usize,
     return_to: String
@@ -19,7 +20,7 @@ impl PairMode {
     pub fn new() -> Self {
         Self {
             settings: None,
-            key_buffer: Vec::new(),
+            pending_keys: PendingKeys::new(),
             text: String::new(),
             edit_pos: 0,
             return_to: String::from("Selection"),
@@ -35,7 +36,7 @@ impl PairMode {
                 self.edit_pos = 0;
                 eprintln!("returning to {}", self.return_to);
                 pane.execute_command(&format!("change_mode {}", self.return_to));
-                self.key_buffer.clear();
+                self.pending_keys.clear();
             }
             "left" => {
                 //Todo: make sure that we move by the right byte size
@@ -81,7 +82,7 @@ impl PairMode {
 
                 pane.execute_command("clear_selection");
                 pane.execute_command(&format!("change_mode {}", settings.editor_settings.default_mode));
-                self.key_buffer.clear();
+                self.pending_keys.clear();
             }
             _ => {}
         }
@@ -114,40 +115,24 @@ impl Mode for PairMode {
         let offset = self.get_name().chars().count() + 2 + self.edit_pos;
         Some(offset)
     }
+
+    fn get_pending_keys(&self) -> &PendingKeys {
+        &self.pending_keys
+    }
+
+    fn get_pending_keys_mut(&mut self) -> &mut PendingKeys {
+        &mut self.pending_keys
+    }
+
+    fn get_settings(&self) -> Rc<RefCell<Settings>> {
+        self.settings.clone().unwrap()
+    }
 }
 
 
 impl TextMode for PairMode {
     fn process_keypress(&mut self, key: KeyEvent, pane: &mut dyn TextPane) {
-        match key {
-            key => {
-                self.key_buffer.push(key);
-
-                let settings = self.settings.clone().unwrap();
-                let mut settings = settings.borrow_mut();
-
-                if let Some(command) = settings.mode_keybindings.get(&self.get_name(), &self.key_buffer) {
-                    let command = command.clone();
-                    drop(settings);
-                    self.execute_command(&command, pane);
-                    self.key_buffer.clear();
-                } else {
-                    drop(settings);
-                    match key.key {
-                        Key::Char(c) => {
-                            self.text.insert(self.edit_pos, c);
-                            self.edit_pos += 1;
-                        }
-                        Key::Tab => {
-                            self.text.insert(self.edit_pos, '\t');
-                            self.edit_pos += 1;
-                        }
-                        _ => {}
-                    }
-                    self.key_buffer.clear();
-                }
-            }
-        }
+        self.push_key(key, pane);
     }
 
     fn update_status(&self, _pane: &dyn TextPane) -> (String, String, String) {
@@ -169,6 +154,23 @@ impl TextMode for PairMode {
     fn start(&mut self, _pane: &mut dyn TextPane) {
         self.text.clear();
         self.edit_pos = 0;
-        self.key_buffer.clear();
+        self.pending_keys.clear();
+    }
+
+    fn run_key_actions(&mut self, actions: Vec<KeyAction>, pane: &mut dyn TextPane) {
+        for action in actions {
+            match action {
+                KeyAction::Command(command) => self.execute_command(&command, pane),
+                KeyAction::Unbound(KeyEvent { key: Key::Char(c), .. }) => {
+                    self.text.insert(self.edit_pos, c);
+                    self.edit_pos += 1;
+                }
+                KeyAction::Unbound(KeyEvent { key: Key::Tab, .. }) => {
+                    self.text.insert(self.edit_pos, '\t');
+                    self.edit_pos += 1;
+                }
+                KeyAction::Unbound(_) => {}
+            }
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,95 @@
+use std::time::{Duration, Instant};
+
+use crate::models::key::KeyEvent;
+use crate::models::settings::mode_keybindings::ModeKeybindings;
+
+
+/// What a mode should do with keys that are no longer pending.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyAction {
+    /// The keys made up a binding
+    Command(String),
+    /// The key doesn't start any binding, so the mode handles it itself, like typing it
+    Unbound(KeyEvent),
+}
+
+
+/// The keys typed so far in a mode that could still become a binding.
+pub struct PendingKeys {
+    keys: Vec<KeyEvent>,
+    last_key: Instant,
+}
+
+impl PendingKeys {
+    pub fn new() -> PendingKeys {
+        PendingKeys {
+            keys: Vec::new(),
+            last_key: Instant::now(),
+        }
+    }
+
+    pub fn keys(&self) -> &[KeyEvent] {
+        &self.keys
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.keys.clear();
+    }
+
+    /// When the last key was typed.
+    pub fn last_key(&self) -> Instant {
+        self.last_key
+    }
+
+    /// How long it has been since the last key was typed.
+    pub fn elapsed(&self) -> Duration {
+        self.last_key.elapsed()
+    }
+
+    /// Adds a key and resolves as many of the pending keys as can be resolved.
+    /// Keys that are both a binding and the start of a longer one are kept until
+    /// another key comes in or they are flushed.
+    pub fn push(&mut self, key: KeyEvent, mode: &str, bindings: &ModeKeybindings) -> Vec<KeyAction> {
+        self.keys.push(key);
+        self.last_key = Instant::now();
+        self.resolve(mode, bindings, false)
+    }
+
+    /// Resolves all of the pending keys without waiting for a longer binding,
+    /// which is what happens once the key timeout runs out.
+    pub fn flush(&mut self, mode: &str, bindings: &ModeKeybindings) -> Vec<KeyAction> {
+        self.resolve(mode, bindings, true)
+    }
+
+    fn resolve(&mut self, mode: &str, bindings: &ModeKeybindings, flush: bool) -> Vec<KeyAction> {
+        let mut actions = Vec::new();
+        while !self.keys.is_empty() {
+            if !flush && bindings.lookup(mode, &self.keys).is_prefix {
+                break;
+            }
+
+            // The keys are a dead end, so run the longest binding at the start of them
+            // and go over the rest again, or hand the first key back to the mode
+            let longest = (1..=self.keys.len()).rev()
+                .find_map(|length| bindings.lookup(mode, &self.keys[..length]).command.map(|command| (length, command)));
+            match longest {
+                Some((length, command)) => {
+                    self.keys.drain(..length);
+                    actions.push(KeyAction::Command(command));
+                }
+                None => actions.push(KeyAction::Unbound(self.keys.remove(0))),
+            }
+        }
+        actions
+    }
+}
+
+impl Default for PendingKeys {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,12 +1,17 @@
 use std::any::Any;
 use std::cell::RefCell;
-use std::collections::BTreeSet;
 use std::fmt;
+use std::ops::Range;
 use std::rc::Rc;
+use regex::Regex;
+use crate::models::file::search::{self, Search, SearchOptions};
+use crate::models::history::{History, HistoryKind};
 use crate::models::key::{Key, KeyEvent};
 use crate::models::mode::{Mode, TextMode};
+use crate::models::mode::pending_keys::{KeyAction, PendingKeys};
 use crate::models::pane::TextPane;
 use crate::models::settings::Settings;
+use crate::models::settings::editor_settings::SearchCase;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SearchType {
@@ -23,16 +28,42 @@ impl fmt::Display for SearchType {
     }
 }
 
+/// How many bytes of rows are counted for matches in one step.
+const COUNT_STEP: usize = 1 << 20;
+
+/// The matches of a search that have been counted so far. They are counted a step at a time
+/// so that typing a pattern in a large file doesn't wait for the whole file to be searched.
+#[derive(Default)]
+struct MatchCount {
+    /// Where each counted match starts
+    starts: Vec<usize>,
+    /// Where the rows that are left to count start
+    counted_to: usize,
+    done: bool,
+}
+
 pub struct SearchMode {
     search_type: SearchType,
     search_string: String,
     edit_pos: usize,
     settings: Option<Rc<RefCell<Settings>>>,
-    key_buffer: Vec<KeyEvent>,
-    found_pos: BTreeSet<usize>,
+    pending_keys: PendingKeys,
+    /// The compiled pattern, which is `None` while it is empty or invalid
+    regex: Option<Regex>,
+    /// The matches counted so far, which only the status shows
+    count: MatchCount,
+    case: SearchCase,
+    whole_word: bool,
+    /// The match that the cursor is on
+    current: Option<Range<usize>>,
+    /// The byte that the search starts from
+    origin: usize,
+    /// Where the cursor was when the search started, so that cancelling can go back to it.
+    /// It is kept as a position since it can be past the last byte, like at the end of an empty buffer
+    origin_cursor: (usize, usize),
     number_buffer: String,
-    searched_whole_file: bool,
     moving_cursor: bool,
+    history: History,
 }
 
 
@@ -43,31 +74,27 @@ impl SearchMode {
             search_string: String::new(),
             edit_pos: 0,
             settings: None,
-            key_buffer: Vec::new(),
-            found_pos: BTreeSet::new(),
+            pending_keys: PendingKeys::new(),
+            regex: None,
+            count: MatchCount::default(),
+            case: SearchCase::Sensitive,
+            whole_word: false,
+            current: None,
+            origin: 0,
+            origin_cursor: (0, 0),
             number_buffer: String::new(),
-            searched_whole_file: false,
             moving_cursor: false,
+            history: History::new(HistoryKind::Search),
         }
     }
 
-    fn get_search_query(&self) -> String {
-        // Remove the escape characters
-        let mut out = self.search_string.clone();
-        out = out.replace("\\n", "\n");
-        out = out.replace("\\t", "\t");
-        out = out.replace("\\r", "\r");
-        out = out.replace("\\\\", "\\");
-
-        out
-    }
-
     fn execute_command(&mut self, command: &str, pane: &mut dyn TextPane) {
         let mut command_args = command.split_whitespace();
         let command_name = command_args.next().unwrap_or("");
 
         match command_name {
             "cancel" => {
+                self.move_to_origin(pane);
                 self.search_string.clear();
                 self.edit_pos = 0;
                 let settings = self.settings.clone().unwrap();
@@ -76,30 +103,35 @@ impl SearchMode {
                 pane.execute_command("clear_selection");
             }
             "left" => {
-                //Todo: make sure that we move by the right byte size
-                self.edit_pos = self.edit_pos.saturating_sub(1);
+                self.edit_pos -= self.search_string[..self.edit_pos].chars().next_back().map_or(0, char::len_utf8);
                 self.moving_cursor = false;
             }
             "right" => {
-                if self.edit_pos < self.search_string.len() {
-                    //Todo: make sure that we move by the right byte size
-                    self.edit_pos += 1;
-                }
+                self.edit_pos += self.search_string[self.edit_pos..].chars().next().map_or(0, char::len_utf8);
                 self.moving_cursor = false;
             }
             "up" => {
-                self.edit_pos = 0;
+                if let Some(entry) = self.history.older(&self.search_string) {
+                    self.search_string = entry;
+                    self.try_search(pane);
+                }
+                self.edit_pos = self.search_string.len();
                 self.moving_cursor = false;
             }
             "down" => {
+                if let Some(entry) = self.history.newer() {
+                    self.search_string = entry;
+                    self.try_search(pane);
+                }
                 self.edit_pos = self.search_string.len();
                 self.moving_cursor = false;
             }
             "backspace" => {
                 if self.edit_pos > 0 {
-                    self.edit_pos -= 1;
+                    self.edit_pos -= self.search_string[..self.edit_pos].chars().next_back().map_or(0, char::len_utf8);
                     self.search_string.remove(self.edit_pos);
                 } else if self.search_string.len() == 0 && self.edit_pos == 0 {
+                    self.move_to_origin(pane);
                     self.search_string.clear();
                     self.edit_pos = 0;
                     let settings = self.settings.clone().unwrap();
@@ -107,6 +139,7 @@ impl SearchMode {
                     pane.execute_command(&format!("change_mode {}", settings.editor_settings.default_mode));
                     pane.execute_command("clear_selection");
                 }
+                self.history.reset();
                 self.try_search(pane);
                 self.moving_cursor = false;
             }
@@ -114,9 +147,28 @@ impl SearchMode {
                 if self.edit_pos < self.search_string.len() {
                     self.search_string.remove(self.edit_pos);
                 }
+                self.history.reset();
+                self.try_search(pane);
                 self.moving_cursor = false;
             }
+            "accept" => {
+                // The matches stay highlighted and n/N in Normal Mode carry on from here
+                if !self.search_string.is_empty() {
+                    self.history.add(&self.search_string);
+                    pane.set_last_search(Search {
+                        pattern: self.search_string.clone(),
+                        options: self.options(),
+                        forward: self.search_type == SearchType::Forward,
+                    });
+                }
+                self.search_string.clear();
+                self.edit_pos = 0;
+                let settings = self.settings.clone().unwrap();
+                let settings = settings.borrow();
+                pane.execute_command(&format!("change_mode {}", settings.editor_settings.default_mode));
+            }
             "copy" => {
+                self.select_matches(pane);
                 pane.execute_command(&format!("copy selection {}", self.number_buffer));
                 let settings = self.settings.clone().unwrap();
                 let settings = settings.borrow();
@@ -126,6 +178,7 @@ impl SearchMode {
                 self.edit_pos = 0;
             }
             "delete_search" => {
+                self.select_matches(pane);
                 pane.execute_command("delete selection");
                 let settings = self.settings.clone().unwrap();
                 let settings = settings.borrow();
@@ -135,6 +188,7 @@ impl SearchMode {
                 self.edit_pos = 0;
             }
             "cut" => {
+                self.select_matches(pane);
                 pane.execute_command(&format!("copy selection"));
                 pane.execute_command("delete selection");
                 let settings = self.settings.clone().unwrap();
@@ -145,6 +199,7 @@ impl SearchMode {
                 self.edit_pos = 0;
             }
             "paste" => {
+                self.select_matches(pane);
                 pane.execute_command(&format!("paste selection {}", self.number_buffer));
                 let settings = self.settings.clone().unwrap();
                 let settings = settings.borrow();
@@ -154,16 +209,27 @@ impl SearchMode {
                 self.edit_pos = 0;
             }
             "next_match" => {
-                self.next_match(pane);
-                self.key_buffer.clear();
+                self.move_to_match(pane, false);
                 self.moving_cursor = true;
             }
             "previous_match" => {
-                self.previous_match(pane);
-                self.key_buffer.clear();
+                self.move_to_match(pane, true);
                 self.moving_cursor = true;
             }
+            "toggle_case" => {
+                self.case = match self.case {
+                    SearchCase::Sensitive => SearchCase::Insensitive,
+                    SearchCase::Insensitive => SearchCase::Smart,
+                    SearchCase::Smart => SearchCase::Sensitive,
+                };
+                self.try_search(pane);
+            }
+            "toggle_whole_word" => {
+                self.whole_word = !self.whole_word;
+                self.try_search(pane);
+            }
             "mirror_mode" => {
+                self.select_matches(pane);
                 let command = match self.search_type {
                     SearchType::Forward => "search_down",
                     SearchType::Backward => "search_up",
@@ -172,6 +238,7 @@ impl SearchMode {
                 pane.execute_command(&format!("change_mode mirror {}", command));
             }
             "pair_mode" => {
+                self.select_matches(pane);
                 let command = match self.search_type {
                     SearchType::Forward => "search_down",
                     SearchType::Backward => "search_up",
@@ -184,111 +251,93 @@ impl SearchMode {
         self.number_buffer.clear();
     }
 
-    fn try_search(&mut self, pane: &mut dyn TextPane) {
-        pane.execute_command("clear_selection");
-        if self.search_string.len() > 0 {
-
-            let (col, row) = pane.get_cursor();
-
-            let file = pane.borrow_current_file_mut();
-
-            let down = match self.search_type {
-                SearchType::Forward => true,
-                SearchType::Backward => false,
-            };
-
-            let search_string = self.get_search_query();
-
-            self.found_pos = file.find(col, row, &search_string, down);
-
-        } else {
-            self.found_pos.clear();
+    fn options(&self) -> SearchOptions {
+        SearchOptions {
+            case: self.case,
+            whole_word: self.whole_word,
         }
     }
 
-    fn search_rest(&mut self, pane: &mut dyn TextPane) -> bool {
-        if self.search_string.len() > 0 {
-
-            let (col, row) = pane.get_cursor();
-
-            let file = pane.borrow_current_file_mut();
-
-            let down = match self.search_type {
-                SearchType::Forward => false,
-                SearchType::Backward => true,
-            };
-
-            let search_string = self.get_search_query();
-
-            let found = file.find(col, row, &search_string, down);
-
-            self.found_pos.extend(found);
-
-        } else {
-            self.found_pos.clear();
+    /// Highlights the matches of the pattern and previews the one nearest to where the search started.
+    /// Only the first step of the matches is counted here, the rest is counted between keys.
+    fn try_search(&mut self, pane: &mut dyn TextPane) {
+        pane.execute_command("clear_selection");
+        self.regex = None;
+        self.count = MatchCount::default();
+        self.current = None;
+        if self.search_string.is_empty() {
+            self.move_to_origin(pane);
+            return;
         }
-        let out = self.searched_whole_file;
 
-        self.searched_whole_file = true;
+        match search::compile_pattern(&self.search_string, self.options()) {
+            Ok(regex) => {
+                pane.borrow_current_file_mut().set_search_highlight(regex.clone());
+                self.regex = Some(regex);
+            }
+            Err(message) => pane.send_info_message(&message),
+        }
 
-        out
+        let forward = self.search_type == SearchType::Forward;
+        let found = self.regex.as_ref()
+            .and_then(|regex| pane.borrow_current_file().find_next_regex(regex, self.origin, forward));
+        match found {
+            Some((found, _)) => {
+                pane.execute_command(&format!("move to_byte {}", found.start));
+                self.current = Some(found);
+            }
+            None => self.move_to_origin(pane),
+        }
+        self.count_matches(pane);
     }
 
-    fn next_match(&mut self, pane: &mut dyn TextPane) {
-        if self.found_pos.len() > 0 {
-
-            let mut iter = self.found_pos.iter();
-
-
-            let mut counter = 0;
-            while let Some(byte) = iter.next() {
-
-                if *byte < pane.get_current_byte_position() {
-                    continue
-                } else if *byte >= pane.get_current_byte_position() && *byte - counter == pane.get_current_byte_position() {
-                    counter += 1;
-                } else {
-                    pane.execute_command(&format!("move to_byte {}", *byte));
-                    return;
-                }
+    /// Moves to the nearest match from the cursor, in the direction of the search unless `reverse` is set.
+    fn move_to_match(&mut self, pane: &mut dyn TextPane, reverse: bool) {
+        let Some(regex) = &self.regex else {
+            return;
+        };
+        let forward = (self.search_type == SearchType::Forward) != reverse;
+        let cursor = pane.get_current_byte_position();
+
+        if let Some((found, wrapped)) = pane.borrow_current_file().find_next_regex(regex, cursor, forward) {
+            pane.execute_command(&format!("move to_byte {}", found.start));
+            self.current = Some(found);
+            if wrapped {
+                pane.send_info_message("search wrapped");
             }
-            self.search_rest(pane);
-
-            let mut iter = self.found_pos.iter();
-            let byte = iter.next().unwrap();
-            pane.execute_command(&format!("move to_byte {}", *byte));
-
         }
     }
 
-    fn previous_match(&mut self, pane: &mut dyn TextPane) {
-        if self.found_pos.len() > 0 {
-
-            loop {
-                let mut iter = self.found_pos.iter().rev();
-
-                let mut counter = 0;
-                while let Some(byte) = iter.next() {
-
-                    if *byte > pane.get_current_byte_position() {
-                        continue
-                    } else if *byte <= pane.get_current_byte_position() && *byte + counter == pane.get_current_byte_position() {
-                        counter += 1;
-                    } else {
-                        pane.execute_command(&format!("move to_byte {}", *byte));
-                        return;
-                    }
-                }
-
-                if self.search_rest(pane) {
-                    let mut iter = self.found_pos.iter().rev();
-                    let byte = iter.next().unwrap();
-                    pane.execute_command(&format!("move to_byte {}", *byte));
-                    return;
-                }
-            }
+    /// Puts the cursor back where it was when the search started.
+    fn move_to_origin(&self, pane: &mut dyn TextPane) {
+        let (col, row) = self.origin_cursor;
+        pane.execute_command(&format!("move to {},{}", col, row));
+    }
 
+    /// Counts the matches in the next step of rows.
+    fn count_matches(&mut self, pane: &dyn TextPane) {
+        let Some(regex) = &self.regex else {
+            return;
+        };
+        if self.count.done {
+            return;
+        }
+        let file = pane.borrow_current_file();
+        let start = self.count.counted_to;
+        let end = file.get_next_row_start(start + COUNT_STEP);
+        self.count.starts.extend(file.find_regex_in(regex, start..end).iter().map(|found| found.start));
+        self.count.counted_to = end;
+        self.count.done = end >= file.get_byte_count();
+    }
 
+    /// Highlights every match of the pattern as the selection, for the commands that act on all of them.
+    fn select_matches(&self, pane: &mut dyn TextPane) {
+        let Some(regex) = &self.regex else {
+            return;
+        };
+        let file = pane.borrow_current_file_mut();
+        for found in file.find_regex(regex) {
+            file.add_highlight(found.start, found.end - 1);
         }
     }
 }
@@ -320,54 +369,91 @@ impl Mode for SearchMode {
         if self.moving_cursor {
             return None;
         }
-        let offset = self.get_name().chars().count() + 2 + self.edit_pos;
+        let offset = self.get_name().chars().count() + 2 + self.search_string[..self.edit_pos].chars().count();
         Some(offset)
     }
+
+    fn get_pending_keys(&self) -> &PendingKeys {
+        &self.pending_keys
+    }
+
+    fn get_pending_keys_mut(&mut self) -> &mut PendingKeys {
+        &mut self.pending_keys
+    }
+
+    fn get_settings(&self) -> Rc<RefCell<Settings>> {
+        self.settings.clone().unwrap()
+    }
 }
 
 impl TextMode for SearchMode {
     fn process_keypress(&mut self, key: KeyEvent, pane: &mut dyn TextPane) {
-        match key {
-            key => {
-                self.key_buffer.push(key);
-
-                let settings = self.settings.clone().unwrap();
-                let mut settings = settings.borrow_mut();
-
-                if let Some(command) = settings.mode_keybindings.get(&self.get_name(), &self.key_buffer) {
-                    let command = command.clone();
-                    drop(settings);
-                    self.execute_command(&command, pane);
-                    self.key_buffer.clear();
-                } else {
-                    self.moving_cursor = false;
-                    drop(settings);
-                    match key.key {
-                        Key::Char(c) => {
-                            self.search_string.insert(self.edit_pos, c);
-                            //TODO: make sure that we move by the right byte size
-                            self.edit_pos += 1;
-                            self.key_buffer.clear();
-                            self.try_search(pane);
-                        }
-                        _ => {}
-                    }
-                }
-
-            }
-        }
+        self.push_key(key, pane);
     }
 
     fn update_status(&self, _pane: &dyn TextPane) -> (String, String, String) {
         let first = format!("{}{} ", self.search_type ,self.search_string);
-        let second = String::new();
+        let mut second = format!("{} case", self.case);
+        if self.whole_word {
+            second.push_str(", whole word");
+        }
+        match &self.current {
+            _ if self.search_string.is_empty() => {}
+            None => second.push_str(", no matches"),
+            Some(current) if current.start < self.count.counted_to => {
+                let index = self.count.starts.partition_point(|start| *start < current.start);
+                // The total is only a lower bound until the whole file has been counted
+                let more = if self.count.done { "" } else { "+" };
+                second.push_str(&format!(", match {} of {}{}", index + 1, self.count.starts.len(), more));
+            }
+            Some(_) => second.push_str(", counting matches"),
+        }
 
         (self.get_name(), first, second)
     }
 
-    fn start(&mut self, _pane: &mut dyn TextPane) {
+    fn start(&mut self, pane: &mut dyn TextPane) {
         self.moving_cursor = false;
-        self.searched_whole_file = false;
-        self.found_pos.clear();
+        if !self.search_string.is_empty() {
+            // Coming back from Mirror or Pair Mode, which may have changed the text
+            self.try_search(pane);
+            return;
+        }
+
+        // Starting a new search clears the highlighting of the last one
+        pane.execute_command("clear_selection");
+        self.history = History::load(HistoryKind::Search);
+        self.origin = pane.get_current_byte_position();
+        self.origin_cursor = pane.get_cursor();
+        self.regex = None;
+        self.count = MatchCount::default();
+        self.current = None;
+        self.whole_word = false;
+        let settings = self.settings.clone().unwrap();
+        self.case = settings.borrow().editor_settings.search_case;
+    }
+
+    fn idle(&mut self, pane: &mut dyn TextPane) -> bool {
+        if self.regex.is_none() || self.count.done {
+            return false;
+        }
+        self.count_matches(pane);
+        true
+    }
+
+    fn run_key_actions(&mut self, actions: Vec<KeyAction>, pane: &mut dyn TextPane) {
+        for action in actions {
+            match action {
+                KeyAction::Command(command) => self.execute_command(&command, pane),
+                KeyAction::Unbound(KeyEvent { key: Key::Char(c), .. }) => {
+                    self.moving_cursor = false;
+                    self.search_string.insert(self.edit_pos, c);
+                    self.edit_pos += c.len_utf8();
+                    self.history.reset();
+                    self.try_search(pane);
+                }
+                KeyAction::Unbound(_) => self.moving_cursor = false,
+            }
+        }
     }
 }
\ No newline at end of file
@@ -3,6 +3,7 @@ use std::cell::RefCell;
 use std::rc::Rc;
 use crate::models::key::{Key, KeyEvent};
 use crate::models::mode::{Mode, TextMode};
+use crate::models::mode::pending_keys::{KeyAction, PendingKeys};
 use crate::models::pane::TextPane;
 use crate::models::settings::Settings;
 
@@ -13,13 +14,16 @@ pub enum SelectionType {
     Block,
 }
 
+/// The selection type, start, and cursor position before a syntax node expansion.
+type ExpansionStep = (SelectionType, (usize, usize), (usize, usize));
 
 pub struct SelectionMode {
     selection_type: SelectionType,
     start: (usize, usize),
     settings: Option<Rc<RefCell<Settings>>>,
-    key_buffer: Vec<KeyEvent>,
+    pending_keys: PendingKeys,
     number_buffer: String,
+    expansion_history: Vec<ExpansionStep>,
 }
 
 impl SelectionMode {
@@ -28,8 +32,9 @@ impl SelectionMode {
             selection_type: SelectionType::Normal,
             start: (0, 0),
             settings: None,
-            key_buffer: Vec::new(),
+            pending_keys: PendingKeys::new(),
             number_buffer: String::new(),
+            expansion_history: Vec::new(),
         }
     }
 
@@ -97,13 +102,74 @@ impl SelectionMode {
         }
     }
 
+    /// Returns the first and last byte of the current selection.
+    fn get_selection_bytes(&self, pane: &dyn TextPane) -> Option<(usize, usize)> {
+        let (col, row) = pane.get_cursor();
+        let (start_col, start_row) = self.start;
+        let file = pane.borrow_current_file();
+
+        match self.selection_type {
+            SelectionType::Line => {
+                let first_row = row.min(start_row);
+                let last_row = row.max(start_row);
+                let last_len = file.get_row_len(last_row).unwrap_or(0);
+                Some((file.get_byte_offset(first_row, 0)?, file.get_byte_offset(last_row, last_len)?))
+            },
+            SelectionType::Normal | SelectionType::Block => {
+                let cursor = file.get_byte_offset(row, col)?;
+                let start = file.get_byte_offset(start_row, start_col)?;
+                Some((cursor.min(start), cursor.max(start)))
+            },
+        }
+    }
+
+    fn expand_selection(&mut self, pane: &mut dyn TextPane) {
+        let Some((first, last)) = self.get_selection_bytes(pane) else {
+            return;
+        };
+
+        let file = pane.borrow_current_file();
+        // The selection ends after the last byte of its last character
+        let end = last + file.get_char_at(last).map_or(0, char::len_utf8);
+        let Some((node_start, node_end)) = file.get_enclosing_node(first, end) else {
+            pane.send_info_message("No larger syntax node");
+            return;
+        };
+        let Some(start) = file.get_cursor(node_start) else {
+            return;
+        };
+        // The cursor goes on the node's last character rather than its last byte
+        let last_char = node_end.checked_sub(1)
+            .and_then(|byte| file.get_char_at(byte))
+            .map_or(node_end, |c| node_end - c.len_utf8());
+
+        self.expansion_history.push((self.selection_type, self.start, pane.get_cursor()));
+
+        self.selection_type = SelectionType::Normal;
+        self.start = start;
+        pane.execute_command(&format!("move to_byte {}", last_char.max(node_start)));
+        self.add_selection(pane);
+    }
+
+    fn shrink_selection(&mut self, pane: &mut dyn TextPane) {
+        let Some((selection_type, start, (col, row))) = self.expansion_history.pop() else {
+            pane.send_info_message("No smaller selection");
+            return;
+        };
+
+        self.selection_type = selection_type;
+        self.start = start;
+        pane.execute_command(&format!("move to {},{}", col, row));
+        self.add_selection(pane);
+    }
+
     pub fn execute_command(&mut self, command: &str, pane: &mut dyn TextPane) {
         let mut command_args = command.split_whitespace();
         let command_name = command_args.next().unwrap_or("");
 
         match command_name {
             "cancel" => {
-                self.key_buffer.clear();
+                self.pending_keys.clear();
                 let settings = self.settings.clone().unwrap();
                 let settings = settings.borrow();
                 pane.execute_command(&format!("change_mode {}", settings.editor_settings.default_mode));
@@ -188,6 +254,10 @@ impl SelectionMode {
                 self.number_buffer.clear();
                 self.add_selection(pane);
             },
+            "goto_pair" => {
+                pane.execute_command("move to_pair");
+                self.add_selection(pane);
+            },
             "copy" => {
                 pane.execute_command(&format!("copy selection"));
                 let settings = self.settings.clone().unwrap();
@@ -216,6 +286,24 @@ impl SelectionMode {
                 pane.execute_command(&format!("change_mode {}", settings.editor_settings.default_mode));
                 pane.execute_command("clear_selection");
             }
+            "reindent" => {
+                let (_, row) = pane.get_cursor();
+                let (_, start_row) = self.start;
+                pane.execute_command(&format!("reindent {},{}", start_row, row));
+                let settings = self.settings.clone().unwrap();
+                let settings = settings.borrow();
+                pane.execute_command(&format!("change_mode {}", settings.editor_settings.default_mode));
+                pane.execute_command("clear_selection");
+            }
+            "toggle_comment" => {
+                let (_, row) = pane.get_cursor();
+                let (_, start_row) = self.start;
+                pane.execute_command(&format!("comment {},{}", start_row, row));
+                let settings = self.settings.clone().unwrap();
+                let settings = settings.borrow();
+                pane.execute_command(&format!("change_mode {}", settings.editor_settings.default_mode));
+                pane.execute_command("clear_selection");
+            }
             "mirror_mode" => {
                 let command = match self.selection_type {
                     SelectionType::Normal => "selection_normal_mirror",
@@ -234,10 +322,34 @@ impl SelectionMode {
 
                 pane.execute_command(&format!("change_mode pair {}", command));
             }
-            _ => {}
+            "command_mode" => {
+                self.pending_keys.clear();
+                pane.execute_command("change_mode command_selection");
+            }
+            "search_selection_forward" | "search_selection_backward" => {
+                let direction = match command_name {
+                    "search_selection_forward" => "forward",
+                    _ => "backward",
+                };
+                let settings = self.settings.clone().unwrap();
+                let default_mode = settings.borrow().editor_settings.default_mode.clone();
+                pane.execute_command(&format!("change_mode {}", default_mode));
+                pane.execute_command(&format!("search_selection {}", direction));
+            }
+            "expand_selection" => {
+                self.expand_selection(pane);
+            }
+            "shrink_selection" => {
+                self.shrink_selection(pane);
+            }
+            // Anything else, like an ex command bound with `:bind`, is run by the pane
+            _ => pane.execute_command_line(command),
+        }
+
+        if command_name != "expand_selection" && command_name != "shrink_selection" {
+            self.expansion_history.clear();
         }
 
-        self.key_buffer.clear();
         self.number_buffer.clear();
     }
 }
@@ -275,6 +387,18 @@ impl Mode for SelectionMode {
     fn influence_cursor(&self) -> Option<usize> {
         None
     }
+
+    fn get_pending_keys(&self) -> &PendingKeys {
+        &self.pending_keys
+    }
+
+    fn get_pending_keys_mut(&mut self) -> &mut PendingKeys {
+        &mut self.pending_keys
+    }
+
+    fn get_settings(&self) -> Rc<RefCell<Settings>> {
+        self.settings.clone().unwrap()
+    }
 }
 
 impl TextMode for SelectionMode {
@@ -285,7 +409,7 @@ impl TextMode for SelectionMode {
                 key: Key::Esc,
                 ..
             } => {
-                self.key_buffer.clear();
+                self.pending_keys.clear();
                 let settings = self.settings.clone().unwrap();
                 let settings = settings.borrow();
                 pane.execute_command(&format!("change_mode {}", settings.editor_settings.default_mode));
@@ -293,15 +417,7 @@ impl TextMode for SelectionMode {
             },
             key => {
 
-                self.key_buffer.push(key);
-
-                let settings = self.settings.clone().unwrap();
-                let mut settings = settings.borrow_mut();
-                if let Some(command) = settings.mode_keybindings.get(&self.get_name(), &self.key_buffer) {
-                    let command = command.clone();
-                    drop(settings);
-                    self.execute_command(&command, pane);
-                }
+                self.push_key(key, pane);
             }
         }
     }
@@ -326,18 +442,19 @@ impl TextMode for SelectionMode {
     }
 
     fn start(&mut self, pane: &mut dyn TextPane) {
+        self.expansion_history.clear();
 
-        match self.selection_type {
-            SelectionType::Line => {
-                let (_, row) = pane.get_cursor();
-                pane.execute_command(&format!("select row {}", row));
-            }
-            SelectionType::Normal | SelectionType::Block => {
-                let (col, row) = pane.get_cursor();
-                pane.execute_command(&format!("select {},{} {},{}", col, row, col, row));
+        // The start is usually the cursor, but a motion from Normal mode can have selected text already
+        self.add_selection(pane);
+    }
+
+    fn run_key_actions(&mut self, actions: Vec<KeyAction>, pane: &mut dyn TextPane) {
+        for action in actions {
+            match action {
+                KeyAction::Command(command) => self.execute_command(&command, pane),
+                // Keys that aren't bound do nothing in Selection mode
+                KeyAction::Unbound(_) => {}
             }
         }
-
-
     }
 }
\ No newline at end of file
@@ -0,0 +1,234 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::models::file::symbol::Symbol;
+use crate::models::key::{Key, KeyEvent};
+use crate::models::mode::{Mode, TextMode};
+use crate::models::mode::pending_keys::{KeyAction, PendingKeys};
+use crate::models::pane::TextPane;
+use crate::models::settings::Settings;
+
+/// The entries that a picker shows and which of them is selected.
+pub struct PickerItems {
+    pub title: String,
+    pub items: Vec<String>,
+    pub selected: usize,
+}
+
+/// A fuzzy picker over the symbols of the current file that jumps to the chosen definition.
+pub struct SymbolsMode {
+    query: String,
+    edit_pos: usize,
+    symbols: Vec<Symbol>,
+    /// The indices of the symbols that match the query, best match first
+    matches: Vec<usize>,
+    picker: PickerItems,
+    settings: Option<Rc<RefCell<Settings>>>,
+    pending_keys: PendingKeys,
+}
+
+impl SymbolsMode {
+    pub fn new() -> Self {
+        Self {
+            query: String::new(),
+            edit_pos: 0,
+            symbols: Vec::new(),
+            matches: Vec::new(),
+            picker: PickerItems {
+                title: String::from("Symbols"),
+                items: Vec::new(),
+                selected: 0,
+            },
+            settings: None,
+            pending_keys: PendingKeys::new(),
+        }
+    }
+
+    fn execute_command(&mut self, command: &str, pane: &mut dyn TextPane) {
+        let mut command_args = command.split_whitespace();
+        let command_name = command_args.next().unwrap_or("");
+        match command_name {
+            "cancel" => {
+                self.leave(pane);
+            }
+            "left" => {
+                self.edit_pos -= self.query[..self.edit_pos].chars().next_back().map_or(0, char::len_utf8);
+            }
+            "right" => {
+                self.edit_pos += self.query[self.edit_pos..].chars().next().map_or(0, char::len_utf8);
+            }
+            "up" => {
+                self.picker.selected = self.picker.selected.saturating_sub(1);
+            }
+            "down" => {
+                self.picker.selected = (self.picker.selected + 1).min(self.matches.len().saturating_sub(1));
+            }
+            "start" => {
+                self.edit_pos = 0;
+            }
+            "end" => {
+                self.edit_pos = self.query.len();
+            }
+            "backspace" if self.edit_pos > 0 => {
+                self.edit_pos -= self.query[..self.edit_pos].chars().next_back().map_or(0, char::len_utf8);
+                self.query.remove(self.edit_pos);
+                self.filter();
+            }
+            "delete" if self.edit_pos < self.query.len() => {
+                self.query.remove(self.edit_pos);
+                self.filter();
+            }
+            "execute" => {
+                match self.matches.get(self.picker.selected) {
+                    Some(index) => {
+                        let byte = self.symbols[*index].name_start;
+                        pane.execute_command(&format!("move to_byte {}", byte));
+                    }
+                    None => pane.send_info_message("No matching symbol"),
+                }
+                self.leave(pane);
+            }
+            _ => {}
+        }
+    }
+
+    fn leave(&mut self, pane: &mut dyn TextPane) {
+        self.query.clear();
+        self.edit_pos = 0;
+        let settings = self.settings.clone().unwrap();
+        let settings = settings.borrow();
+        pane.execute_command(&format!("change_mode {}", settings.editor_settings.default_mode));
+    }
+
+    /// Ranks the symbols against the query and rebuilds the list that the picker shows.
+    fn filter(&mut self) {
+        let mut scored = self.symbols.iter().enumerate()
+            .filter_map(|(index, symbol)| fuzzy_score(&self.query, &symbol.name).map(|score| (index, score)))
+            .collect::<Vec<_>>();
+        // Keep the file order between symbols that match equally well
+        scored.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        self.matches = scored.into_iter().map(|(index, _)| index).collect();
+        self.picker.items = self.matches.iter().map(|index| {
+            let symbol = &self.symbols[*index];
+            format!("{:<6} {}", symbol.kind.to_string(), symbol.name)
+        }).collect();
+        self.picker.selected = 0;
+    }
+}
+
+impl Default for SymbolsMode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Scores how well a pattern matches a candidate when its characters appear in order, ignoring case.
+/// Matches at the start of words and runs of consecutive characters score higher.
+/// Returns `None` when the pattern doesn't match.
+pub fn fuzzy_score(pattern: &str, candidate: &str) -> Option<i64> {
+    let candidate = candidate.chars().collect::<Vec<_>>();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+
+    for pattern_char in pattern.chars().filter(|c| !c.is_whitespace()) {
+        let found = (position..candidate.len()).find(|i| candidate[*i].to_lowercase().eq(pattern_char.to_lowercase()))?;
+
+        score += 1;
+        let word_start = found == 0
+            || !candidate[found - 1].is_alphanumeric()
+            || (candidate[found - 1].is_lowercase() && candidate[found].is_uppercase());
+        if word_start {
+            score += 8;
+        }
+        match previous_match {
+            Some(previous) if previous + 1 == found => score += 5,
+            Some(previous) => score -= (found - previous - 1).min(5) as i64,
+            None => score -= found.min(5) as i64,
+        }
+
+        previous_match = Some(found);
+        position = found + 1;
+    }
+
+    // Prefer shorter names when everything else is equal
+    Some(score * 100 - candidate.len() as i64)
+}
+
+impl Mode for SymbolsMode {
+    fn get_name(&self) -> String {
+        "Symbols".to_string()
+    }
+
+    fn add_settings(&mut self, settings: Rc<RefCell<Settings>>) {
+        self.settings = Some(settings);
+    }
+
+    fn refresh(&mut self) {
+
+    }
+
+    fn add_special(&mut self, _something: &dyn Any) {
+
+    }
+
+    fn get_special(&self) -> Option<&dyn Any> {
+        Some(&self.picker)
+    }
+
+    fn influence_cursor(&self) -> Option<usize> {
+        let offset = self.get_name().chars().count() + 2 + self.query[..self.edit_pos].chars().count();
+        Some(offset)
+    }
+
+    fn get_pending_keys(&self) -> &PendingKeys {
+        &self.pending_keys
+    }
+
+    fn get_pending_keys_mut(&mut self) -> &mut PendingKeys {
+        &mut self.pending_keys
+    }
+
+    fn get_settings(&self) -> Rc<RefCell<Settings>> {
+        self.settings.clone().unwrap()
+    }
+}
+
+impl TextMode for SymbolsMode {
+    fn process_keypress(&mut self, key: KeyEvent, pane: &mut dyn TextPane) {
+        self.push_key(key, pane);
+    }
+
+    fn update_status(&self, _pane: &dyn TextPane) -> (String, String, String) {
+        let first = format!("@{}", self.query);
+        let second = format!("{}/{}", self.matches.len(), self.symbols.len());
+
+        (self.get_name(), first, second)
+    }
+
+    fn start(&mut self, pane: &mut dyn TextPane) {
+        self.query.clear();
+        self.edit_pos = 0;
+        self.pending_keys.clear();
+        self.symbols = pane.borrow_current_file().get_symbols();
+        self.filter();
+        if self.symbols.is_empty() {
+            pane.send_info_message("No symbols found in this file");
+        }
+    }
+
+    fn run_key_actions(&mut self, actions: Vec<KeyAction>, pane: &mut dyn TextPane) {
+        for action in actions {
+            match action {
+                KeyAction::Command(command) => self.execute_command(&command, pane),
+                KeyAction::Unbound(KeyEvent { key: Key::Char(c), .. }) => {
+                    self.query.insert(self.edit_pos, c);
+                    self.edit_pos += c.len_utf8();
+                    self.filter();
+                }
+                KeyAction::Unbound(_) => {}
+            }
+        }
+    }
+}
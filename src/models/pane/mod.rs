@@ -1,6 +1,7 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 use crate::models::file::File;
+use crate::models::file::search::Search;
 use crate::models::style::StyledText;
 use crate::models::key::KeyEvent;
 use crate::models::Rect;
@@ -13,6 +14,10 @@ pub mod text;
 pub trait Pane {
     fn execute_command(&mut self, command: &str);
 
+    /// Runs a command as it was typed on the command line, which can also be a command that takes a line range,
+    /// like `:%s/old/new/g`. Internal commands that modes send should use `execute_command` instead.
+    fn execute_command_line(&mut self, command: &str);
+
     fn get_cursor_position(&self) -> Option<(usize, usize)>;
     fn get_bottom_cursor_position(&self) -> Option<usize>;
 
@@ -62,6 +67,12 @@ pub trait TextPane: Pane {
     fn borrow_current_file(&self) -> &File;
 
     fn borrow_current_file_mut(&mut self) -> &mut File;
+
+    /// Remembers the search that `n` and `N` repeat.
+    fn set_last_search(&mut self, search: Search);
+
+    /// The paths of the other open buffers, which the command line completes.
+    fn get_buffer_names(&self) -> Vec<String>;
 }
 
 
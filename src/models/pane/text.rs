@@ -1,12 +1,15 @@
 use std::collections::HashMap;
+use std::ops::Range;
 use std::rc::Rc;
 use std::cell::RefCell;
 use std::path::PathBuf;
 use std::str::SplitWhitespace;
 use std::sync::mpsc::{Sender, Receiver};
+use std::time::{Duration, Instant};
+use regex::Regex;
 use crate::models::style::{StyledLine, StyledSpan, StyledText};
 use crate::models::cursor::Cursor;
-use crate::models::key::KeyEvent;
+use crate::models::key::{self, KeyEvent};
 use crate::models::pane::TextPane;
 use either::Either;
 
@@ -20,13 +23,21 @@ use crate::models::mode::command::CommandMode;
 use crate::models::mode::insert::InsertMode;
 use crate::models::mode::mirror::MirrorMode;
 use crate::models::settings::Settings;
+use crate::models::settings::mode_keybindings::ModeKeybindings;
 use crate::models::mode::TextMode;
 use crate::models::mode::normal::NormalMode;
 use crate::models::mode::Mode;
 use crate::models::mode::pair::PairMode;
 use crate::models::mode::search::{SearchMode, SearchType};
 use crate::models::mode::selection::{SelectionMode, SelectionType};
-use crate::models::settings::editor_settings::NumberLineStyle;
+use crate::models::mode::symbols::{PickerItems, SymbolsMode};
+use crate::models::mode::confirm::ConfirmMode;
+use crate::models::mode::history::HistoryMode;
+use crate::models::history::HistoryKind;
+use crate::models::completion;
+use crate::models::file::search::{self, Search, SearchOptions, Substitution};
+use crate::models::file::range::{self, Address, AddressBase, LineRange};
+use crate::models::settings::editor_settings::{EditorSettings, NumberLineStyle, LOCAL_OPTION_NAMES, OPTION_NAMES};
 use crate::threads::registers::RegisterMessage;
 
 
@@ -40,6 +51,23 @@ pub struct TextBuffer {
     sender: Sender<AppEvent>,
     //lsp_channels: (Sender<LspMessage>, Receiver<LspMessage>),
     register_channels: (Sender<RegisterMessage>, Rc<Receiver<RegisterMessage>>),
+    outline_open: bool,
+    /// The selected bytes when the command line was opened from Selection Mode
+    command_scope: Option<Range<usize>>,
+    /// The structural replacement that is previewed until `:ssr!` applies it, along with its scope
+    pending_replacement: Option<(String, Option<Range<usize>>)>,
+    /// The last search made in Search Mode
+    last_search: Option<Search>,
+    /// The paths of the buffers that are open but not shown
+    buffer_names: Vec<String>,
+    /// The rows remembered with `:mark`
+    marks: HashMap<char, usize>,
+    /// The first and last row of the last selection, which `'<` and `'>` refer to
+    last_selection: Option<(usize, usize)>,
+    /// When the last key was typed that the key hints have been shown for
+    key_hints_shown_for: Option<Instant>,
+    /// The aliases that are running, innermost last, so that an alias that ends up running itself can be stopped
+    running_aliases: Vec<String>,
 }
 
 
@@ -64,6 +92,12 @@ impl TextBuffer {
         mirror_mode.borrow_mut().add_settings(settings.clone());
         let pair_mode = Rc::new(RefCell::new(PairMode::new()));
         pair_mode.borrow_mut().add_settings(settings.clone());
+        let symbols_mode = Rc::new(RefCell::new(SymbolsMode::new()));
+        symbols_mode.borrow_mut().add_settings(settings.clone());
+        let confirm_mode = Rc::new(RefCell::new(ConfirmMode::new()));
+        confirm_mode.borrow_mut().add_settings(settings.clone());
+        let history_mode = Rc::new(RefCell::new(HistoryMode::new()));
+        history_mode.borrow_mut().add_settings(settings.clone());
 
         let normal_mode: Rc<RefCell<dyn TextMode>> = normal_mode.clone();
         let command_mode: Rc<RefCell<dyn TextMode>> = command_mode.clone();
@@ -72,6 +106,9 @@ impl TextBuffer {
         let search_mode: Rc<RefCell<dyn TextMode>> = search_mode.clone();
         let mirror_mode: Rc<RefCell<dyn TextMode>> = mirror_mode.clone();
         let pair_mode: Rc<RefCell<dyn TextMode>> = pair_mode.clone();
+        let symbols_mode: Rc<RefCell<dyn TextMode>> = symbols_mode.clone();
+        let confirm_mode: Rc<RefCell<dyn TextMode>> = confirm_mode.clone();
+        let history_mode: Rc<RefCell<dyn TextMode>> = history_mode.clone();
 
 
         let mut modes = HashMap::new();
@@ -82,6 +119,9 @@ impl TextBuffer {
         modes.insert("Search".to_string(), search_mode);
         modes.insert("Mirror".to_string(), mirror_mode);
         modes.insert("Pair".to_string(), pair_mode);
+        modes.insert("Symbols".to_string(), symbols_mode);
+        modes.insert("Confirm".to_string(), confirm_mode);
+        modes.insert("History".to_string(), history_mode);
 
 
         let mode = {
@@ -100,6 +140,120 @@ impl TextBuffer {
             settings,
             sender,
             register_channels,
+            outline_open: false,
+            command_scope: None,
+            pending_replacement: None,
+            last_search: None,
+            buffer_names: Vec::new(),
+            marks: HashMap::new(),
+            last_selection: None,
+            key_hints_shown_for: None,
+            running_aliases: Vec::new(),
+        }
+    }
+
+    /// Inserts indentation at the cursor and moves the cursor past it.
+    fn insert_indentation(&mut self, indentation: &str) {
+        if indentation.is_empty() {
+            return;
+        }
+        let index = self.get_current_byte_position();
+        self.file.insert_current(index, indentation);
+        self.cursor.move_cursor(CursorMovement::Right, indentation.chars().count(), &self.file);
+    }
+
+    pub fn is_outline_open(&self) -> bool {
+        self.outline_open
+    }
+
+    /// Returns the entries of the picker when the current mode is a picker.
+    /// Otherwise, once keys that start a longer binding have waited for `which_key_delay`,
+    /// returns the keys that can come next along with what they run, where nothing is selected.
+    pub fn get_picker(&self) -> Option<(String, Vec<String>, Option<usize>)> {
+        let mode = self.mode.borrow();
+        if let Some(picker) = mode.get_special().and_then(|special| special.downcast_ref::<PickerItems>()) {
+            return Some((picker.title.clone(), picker.items.clone(), Some(picker.selected)));
+        }
+
+        let pending_keys = mode.get_pending_keys();
+        let settings = self.settings.borrow();
+        if pending_keys.is_empty() || pending_keys.elapsed() < Duration::from_millis(settings.editor_settings.which_key_delay) {
+            return None;
+        }
+
+        let continuations = settings.mode_keybindings.continuations(&mode.get_name(), pending_keys.keys());
+        if continuations.is_empty() {
+            return None;
+        }
+        let items = continuations.into_iter()
+            .map(|(key, command, longer)| {
+                let key = key::key_sequence_to_string(&[key]);
+                let more = match longer {
+                    0 => String::new(),
+                    1 => String::from("+1 binding"),
+                    longer => format!("+{} bindings", longer),
+                };
+                match command {
+                    Some(command) if more.is_empty() => format!("{:<8} {}", key, command),
+                    Some(command) => format!("{:<8} {}  {}", key, command, more),
+                    None => format!("{:<8} {}", key, more),
+                }
+            })
+            .collect();
+        Some((key::key_sequence_to_string(pending_keys.keys()), items, None))
+    }
+
+    /// Runs the pending keys on their own once they have waited for `key_timeout`.
+    /// Returns whether the pane needs to be drawn again, which is also the case
+    /// when the key hints are due to be shown.
+    pub fn check_key_timeout(&mut self) -> bool {
+        let mode = self.mode.clone();
+        let Ok(mut mode) = mode.try_borrow_mut() else {
+            return false;
+        };
+        let pending_keys = mode.get_pending_keys();
+        if pending_keys.is_empty() {
+            return false;
+        }
+
+        let (key_timeout, which_key_delay) = {
+            let settings = self.settings.borrow();
+            (settings.editor_settings.key_timeout, settings.editor_settings.which_key_delay)
+        };
+        let waited = pending_keys.elapsed();
+        let last_key = pending_keys.last_key();
+        if waited >= Duration::from_millis(key_timeout) {
+            mode.flush_keys(self);
+            return true;
+        }
+        if waited >= Duration::from_millis(which_key_delay) && self.key_hints_shown_for != Some(last_key) {
+            self.key_hints_shown_for = Some(last_key);
+            return true;
+        }
+        false
+    }
+
+    /// Lets the mode carry on with work that it does in steps, like counting the matches of a search.
+    /// Returns whether the pane needs to be drawn again.
+    pub fn idle(&mut self) -> bool {
+        let mode = self.mode.clone();
+        let Ok(mut mode) = mode.try_borrow_mut() else {
+            return false;
+        };
+        mode.idle(self)
+    }
+
+    /// Updates the paths of the buffers that are open but not shown.
+    pub fn set_buffer_names(&mut self, buffer_names: Vec<String>) {
+        self.buffer_names = buffer_names;
+    }
+
+    /// Returns the width of the gutter, which is only shown for files with a syntax tree.
+    pub fn get_gutter_width(&self) -> usize {
+        if self.file.has_syntax_tree() {
+            1
+        } else {
+            0
         }
     }
 
@@ -145,13 +299,13 @@ impl TextBuffer {
             "e" => {
                 let path = command_args.next();
                 if let Some(path) = path {
-                    self.sender.send(AppEvent::OpenFile(path.to_owned().into()))
+                    self.sender.send(AppEvent::OpenFile(completion::expand_home(path).into()))
                         .expect("Failed to send open file event");
                 }
             }
             "w" => {
                 if let Some(path) = command_args.next() {
-                    let path = PathBuf::from(path);
+                    let path = PathBuf::from(completion::expand_home(path));
                     match self.file.save(Some(path), false) {
                         Ok(_) => {
                             self.send_info_message("File saved");
@@ -173,7 +327,7 @@ impl TextBuffer {
             }
             "w!" => {
                 if let Some(path) = command_args.next() {
-                    let path = PathBuf::from(path);
+                    let path = PathBuf::from(completion::expand_home(path));
                     match self.file.save(Some(path), true) {
                         Ok(_) => {
                             self.send_info_message("File saved");
@@ -195,7 +349,7 @@ impl TextBuffer {
             }
             "wq" => {
                 if let Some(path) = command_args.next() {
-                    let path = PathBuf::from(path);
+                    let path = PathBuf::from(completion::expand_home(path));
                     match self.file.save(Some(path), false) {
                         Ok(_) => {
                             self.send_info_message("File saved");
@@ -221,7 +375,7 @@ impl TextBuffer {
             }
             "w!q" => {
                 if let Some(path) = command_args.next() {
-                    let path = PathBuf::from(path);
+                    let path = PathBuf::from(completion::expand_home(path));
                     match self.file.save(Some(path), true) {
                         Ok(_) => {
                             self.send_info_message("File saved");
@@ -247,7 +401,7 @@ impl TextBuffer {
             }
             "w!q!" => {
                 if let Some(path) = command_args.next() {
-                    let path = PathBuf::from(path);
+                    let path = PathBuf::from(completion::expand_home(path));
                     match self.file.save(Some(path), true) {
                         Ok(_) => {
                             self.send_info_message("File saved");
@@ -271,7 +425,7 @@ impl TextBuffer {
             }
             "wq!" => {
                 if let Some(path) = command_args.next() {
-                    let path = PathBuf::from(path);
+                    let path = PathBuf::from(completion::expand_home(path));
                     match self.file.save(Some(path), false) {
                         Ok(_) => {
                             self.send_info_message("File saved");
@@ -384,7 +538,7 @@ impl TextBuffer {
                             }
 
                         }
-                        _ => panic!("Invalid paste direction"),
+                        direction => self.send_info_message(&format!("Unknown paste direction: {}", direction)),
                     }
                 }
             }
@@ -477,7 +631,7 @@ impl TextBuffer {
 
                             let line = self.file.get_line(row).expect("Invalid row in copy").to_string();
 
-                            let line = line.chars().skip(col - 1).collect::<String>();
+                            let line = line.chars().skip(col).collect::<String>().trim_end_matches(['\n', '\r']).to_string();
 
                             if let Some(Either::Left(reg)) = register {
                                 RegisterMessage::AddNumbered(reg, line)
@@ -525,7 +679,10 @@ impl TextBuffer {
                                 return;
                             }
                         }
-                        _ => panic!("Invalid copy verb"),
+                        verb => {
+                            self.send_info_message(&format!("Unknown text to copy: {}", verb));
+                            return;
+                        },
                     };
 
                     self.register_channels.0.send(message).expect("Failed to send register message");
@@ -569,12 +726,15 @@ impl TextBuffer {
                             self.set_cursor_to_byte_position(byte_offset);
                         }
                         "to_end_line" => {
-                            let (_, row) = self.get_cursor();
+                            let (col, row) = self.get_cursor();
 
                             let line = self.file.get_line(row).expect("Invalid row in copy").to_string();
+                            // The newline stays, so the next line isn't joined onto this one
+                            let rest = line.chars().skip(col).collect::<String>();
+                            let rest = rest.trim_end_matches(['\n', '\r']);
 
                             let byte_offset = self.get_current_byte_position();
-                            self.file.delete(byte_offset..byte_offset + line.len());
+                            self.file.delete(byte_offset..byte_offset + rest.len());
                         }
                         "to_start_line" => {
                             let (col, row) = self.get_cursor();
@@ -592,7 +752,7 @@ impl TextBuffer {
                             let byte_offset =self.file.delete_highlighted();
                             self.set_cursor_to_byte_position(byte_offset);
                         }
-                        _ => panic!("Invalid delete verb"),
+                        verb => self.send_info_message(&format!("Unknown text to delete: {}", verb)),
                     }
 
                 }
@@ -618,9 +778,21 @@ impl TextBuffer {
                 };
 
                 match mode.as_str() {
-                    "Normal" | "Insert" | "Command" => {
+                    "Normal" | "Insert" | "Symbols" => {
                         self.mode = self.modes.get(&mode).unwrap().clone();
                     },
+                    "Command" => {
+                        self.command_scope = None;
+                        self.mode = self.modes.get(&mode).unwrap().clone();
+                    },
+                    "command_selection" => {
+                        let highlights = self.file.get_highlights();
+                        self.command_scope = match (highlights.first(), highlights.last()) {
+                            (Some(first), Some(last)) => Some(*first..*last + 1),
+                            _ => None,
+                        };
+                        self.mode = self.modes.get("Command").unwrap().clone();
+                    },
                     "insert_before" => {
                         self.mode = self.modes.get("Insert").unwrap().clone();
                         self.cursor.move_cursor(CursorMovement::Left, 1, &self.file);
@@ -645,6 +817,9 @@ impl TextBuffer {
 
                         self.cursor.move_cursor(CursorMovement::Down, 1, &self.file);
                         self.cursor.move_cursor(CursorMovement::LineStart, 1, &self.file);
+
+                        let indentation = self.file.get_newline_indentation(row + 1);
+                        self.insert_indentation(&indentation);
                     },
                     "insert_above" => {
                         self.mode = self.modes.get("Insert").unwrap().clone();
@@ -655,29 +830,42 @@ impl TextBuffer {
 
                         self.cursor.move_cursor(CursorMovement::LineStart, 1, &self.file);
 
+                        let indentation = self.file.get_indentation(row + 1);
+                        self.insert_indentation(&indentation);
+
                         //self.cursor.move_cursor(CursorMovement::Up, 1, &self.file);
                     },
                     "selection_normal" => {
                         let mode= self.modes.get("Selection").unwrap().clone();
-                        let pos = self.get_cursor();
+                        // The selection can start somewhere other than the cursor, like where a motion started
+                        let pos = command_args.next().and_then(parse_position).unwrap_or_else(|| self.get_cursor());
                         mode.borrow_mut().add_special(&pos);
                         mode.borrow_mut().add_special(&SelectionType::Normal);
                         self.mode = mode;
                     }
                     "selection_line" => {
                         let mode= self.modes.get("Selection").unwrap().clone();
-                        let pos = self.get_cursor();
+                        let pos = command_args.next().and_then(parse_position).unwrap_or_else(|| self.get_cursor());
                         mode.borrow_mut().add_special(&pos);
                         mode.borrow_mut().add_special(&SelectionType::Line);
                         self.mode = mode;
                     }
                     "selection_block" => {
                         let mode= self.modes.get("Selection").unwrap().clone();
-                        let pos = self.get_cursor();
+                        let pos = command_args.next().and_then(parse_position).unwrap_or_else(|| self.get_cursor());
                         mode.borrow_mut().add_special(&pos);
                         mode.borrow_mut().add_special(&SelectionType::Block);
                         self.mode = mode;
                     }
+                    "History" | "history_search" => {
+                        let kind = match mode.as_str() {
+                            "history_search" => HistoryKind::Search,
+                            _ => HistoryKind::Command,
+                        };
+                        let mode = self.modes.get("History").unwrap().clone();
+                        mode.borrow_mut().add_special(&kind);
+                        self.mode = mode;
+                    }
                     "search_down" => {
                         let mode = self.modes.get("Search").unwrap().clone();
                         mode.borrow_mut().add_special(&SearchType::Forward);
@@ -718,13 +906,117 @@ impl TextBuffer {
                         mode.borrow_mut().add_special(&SelectionType::Block);
                         self.mode = mode;
                     }
-                    _ => panic!("Invalid mode"),
+                    mode => {
+                        self.send_info_message(&format!("Unknown mode: {}", mode));
+                        return;
+                    },
 
                 }
                 let mode = self.mode.clone();
-                let mut mode = mode.borrow_mut();
-                mode.start(self);
+                // The mode can still be busy with the key that led here, like a binding that runs `:normal`,
+                // and it carries on from where it is once that key is done
+                if let Ok(mut mode) = mode.try_borrow_mut() {
+                    mode.start(self);
+                };
             },
+            "fold" => {
+                let (col, row) = self.get_cursor();
+                let result = match command_args.next() {
+                    Some("toggle") => self.file.toggle_fold(row),
+                    Some("open") => self.file.open_fold(row),
+                    Some("close") => self.file.close_fold(row),
+                    Some("open_all") => {
+                        self.file.open_all_folds();
+                        Ok(())
+                    }
+                    Some("close_all") => {
+                        self.file.close_all_folds();
+                        Ok(())
+                    }
+                    _ => Err(String::from("Invalid fold command")),
+                };
+
+                match result {
+                    Ok(_) => {
+                        if let Some((start, _)) = self.file.get_closed_fold(row) {
+                            let col = col.min(self.file.get_row_len(start).unwrap_or(0));
+                            self.cursor.set_cursor(col, start);
+                        }
+                    }
+                    Err(msg) => self.send_info_message(&msg),
+                }
+            }
+            "reindent" => {
+                let (_, row) = self.get_cursor();
+                let (start_row, end_row) = match command_args.next() {
+                    Some("file") => (0, self.file.get_line_count().saturating_sub(1)),
+                    Some("line") | None => (row, row),
+                    Some(range) => {
+                        let mut rows = range.split(',').map(|x| x.parse::<usize>().unwrap_or(row));
+                        let start = rows.next().unwrap_or(row);
+                        let end = rows.next().unwrap_or(start);
+                        (start.min(end), start.max(end))
+                    }
+                };
+
+                if let Err(msg) = self.file.reindent(start_row, end_row) {
+                    self.send_info_message(&msg);
+                }
+                let (col, row) = self.get_cursor();
+                let col = col.min(self.file.get_row_len(row).unwrap_or(0));
+                self.cursor.set_cursor(col, row);
+            }
+            "comment" => {
+                let (_, row) = self.get_cursor();
+                let (start_row, end_row) = match command_args.next() {
+                    Some(range) if range.contains(',') => {
+                        let mut rows = range.split(',').map(|x| x.parse::<usize>().unwrap_or(row));
+                        let start = rows.next().unwrap_or(row);
+                        let end = rows.next().unwrap_or(start);
+                        (start.min(end), start.max(end))
+                    }
+                    Some(count) => {
+                        let count = count.parse::<usize>().unwrap_or(1).max(1);
+                        (row, row + count - 1)
+                    }
+                    None => (row, row),
+                };
+
+                if let Err(msg) = self.file.toggle_comment(start_row, end_row) {
+                    self.send_info_message(&msg);
+                }
+                let (col, row) = self.get_cursor();
+                let col = col.min(self.file.get_row_len(row).unwrap_or(0));
+                self.cursor.set_cursor(col, row);
+            }
+            "outline" => {
+                self.outline_open = match command_args.next() {
+                    Some("open") => true,
+                    Some("close") => false,
+                    _ => !self.outline_open,
+                };
+            }
+            "symbols" => {
+                self.execute_command("change_mode Symbols");
+            }
+            "history" => {
+                match command_args.next() {
+                    Some("search") => self.execute_command("change_mode history_search"),
+                    _ => self.execute_command("change_mode History"),
+                }
+            }
+            "syntax_error" => {
+                let byte_offset = self.get_current_byte_position();
+                let target = match command_args.next() {
+                    Some("prev") => self.file.prev_syntax_error(byte_offset),
+                    _ => self.file.next_syntax_error(byte_offset),
+                };
+
+                match target {
+                    Some(target) => self.set_cursor_to_byte_position(target),
+                    None => self.send_info_message("No syntax errors"),
+                }
+            }
             "clear_selection" => {
                 self.file.clear_highlights();
             }
@@ -737,13 +1029,13 @@ impl TextBuffer {
                 } else {
                     if let Some(start) = next {
                         if let Some(end) = command_args.next() {
-                            let start = start.split(',');
-                            let end = end.split(',');
-                            let start = start.map(|x| x.parse::<usize>().unwrap()).collect::<Vec<usize>>();
-                            let end = end.map(|x| x.parse::<usize>().unwrap()).collect::<Vec<usize>>();
+                            let (Some((column, row)), Some((end_column, end_row))) = (parse_position(start), parse_position(end)) else {
+                                self.send_info_message("select takes positions like column,row");
+                                return;
+                            };
 
-                            if let Some(start) = self.file.get_byte_offset(start[1], start[0]) {
-                                if let Some(end) = self.file.get_byte_offset(end[1], end[0]) {
+                            if let Some(start) = self.file.get_byte_offset(row, column) {
+                                if let Some(end) = self.file.get_byte_offset(end_row, end_column) {
                                     self.file.add_highlight(start, end);
                                 }
                             }
@@ -752,18 +1044,842 @@ impl TextBuffer {
                         }
                     }
                 }
+
+                let highlights = self.file.get_highlights();
+                if let (Some(first), Some(last)) = (highlights.first(), highlights.last()) {
+                    let (_, first) = self.file.get_cursor(*first).unwrap_or((0, 0));
+                    let (_, last) = self.file.get_cursor(*last).unwrap_or((0, first));
+                    self.last_selection = Some((first, last));
+                }
+            }
+            _ => {}
+        }
+    }
+    fn text_commands(&mut self, command_name: &str, text: &str) {
+        match command_name {
+            "ssr" => {
+                self.structural_replace(text, false);
+            }
+            "ssr!" => {
+                self.structural_replace(text, true);
+            }
+            "set" => {
+                self.set_options(text, false);
+            }
+            "setlocal" => {
+                self.set_options(text, true);
+            }
+            "bind" | "bind!" => {
+                if let Err(message) = self.bind(text, command_name == "bind!") {
+                    self.send_info_message(&message);
+                }
+            }
+            "unbind" | "unbind!" => {
+                if let Err(message) = self.unbind(text, command_name == "unbind!") {
+                    self.send_info_message(&message);
+                }
+            }
+            "bindings" => {
+                let mode = match text {
+                    "" => self.settings.borrow().editor_settings.default_mode.clone(),
+                    mode => mode.to_string(),
+                };
+                match self.settings.borrow().mode_keybindings.find_mode(&mode) {
+                    Some(mode) => self.sender.send(AppEvent::OpenBindings(mode.into()))
+                        .expect("Failed to send open bindings event"),
+                    None => self.send_info_message(&format!("Unknown mode: {}", mode)),
+                }
             }
             _ => {}
         }
     }
+
+    /// Binds keys in a mode for `:bind Normal <C-s> w`, where the command can also be in quotes.
+    /// With `save` the binding is also written to `keybindings.toml`.
+    fn bind(&mut self, args: &str, save: bool) -> Result<(), String> {
+        let usage = || String::from("Usage: :bind <mode> <keys> <command>");
+        let (mode, rest) = args.split_once(char::is_whitespace).ok_or_else(usage)?;
+        let (keys, command) = rest.trim_start().split_once(char::is_whitespace).ok_or_else(usage)?;
+        let command = command.trim();
+        let command = match (command.chars().next(), command.chars().last()) {
+            (Some(open @ ('"' | '\'')), Some(close)) if open == close && command.len() > 1 => &command[1..command.len() - 1],
+            _ => command,
+        };
+        if command.is_empty() {
+            return Err(String::from("No command given"));
+        }
+
+        let mode = self.find_binding_mode(mode)?;
+        let keys = self.parse_keys(keys);
+        let mut settings = self.settings.borrow_mut();
+        match mode.as_str() {
+            "Universal" => settings.mode_keybindings.set_universal(keys.clone(), command),
+            mode => settings.mode_keybindings.set(mode, keys.clone(), command),
+        }
+        drop(settings);
+
+        if save {
+            let settings = self.settings.borrow();
+            settings.mode_keybindings.save_binding(&mode, &keys, Some(command), &settings.editor_settings.leader)?;
+        }
+        self.send_info_message(&format!("{} runs \"{}\" in {}", key::key_sequence_to_string(&keys), command, mode));
+        Ok(())
+    }
+
+    /// Parses keys written like `<C-s>` or `<leader>w`, where `<leader>` is the leader key from the settings.
+    fn parse_keys(&self, keys: &str) -> Vec<KeyEvent> {
+        let leader = self.settings.borrow().editor_settings.leader.clone();
+        key::parse_key_sequence(&key::expand_leader(keys, &leader))
+    }
+
+    /// Runs the commands of an alias from the `[Aliases]` table of keybindings.toml.
+    /// An alias that ends up running itself, like through `:normal` and a binding, is stopped there.
+    fn run_alias(&mut self, name: &str, arguments: &str, commands: Vec<String>) {
+        if !arguments.is_empty() {
+            self.send_info_message(&format!("The alias {} doesn't take arguments", name));
+            return;
+        }
+        if self.running_aliases.iter().any(|alias| alias == name) {
+            self.send_info_message(&format!("The alias {} runs itself, so it was stopped", name));
+            return;
+        }
+
+        self.running_aliases.push(name.to_string());
+        for command in commands {
+            self.execute_command_line(&command);
+        }
+        self.running_aliases.pop();
+    }
+
+    /// Removes a binding for `:unbind Insert jj`.
+    /// With `save` it is also removed from `keybindings.toml`, which keeps a default binding from coming back.
+    fn unbind(&mut self, args: &str, save: bool) -> Result<(), String> {
+        let (mode, keys) = args.split_once(char::is_whitespace).ok_or(String::from("Usage: :unbind <mode> <keys>"))?;
+        let mode = self.find_binding_mode(mode)?;
+        let keys = self.parse_keys(keys.trim());
+        let keys_text = key::key_sequence_to_string(&keys);
+
+        if self.settings.borrow_mut().mode_keybindings.remove(&mode, &keys).is_none() {
+            let universal = self.settings.borrow().mode_keybindings.list(&mode).iter()
+                .any(|entry| entry.universal && entry.keys == keys);
+            return match universal {
+                true => Err(format!("{} is a Universal binding, use :unbind Universal {}", keys_text, keys_text)),
+                false => Err(format!("{} is not bound in {}", keys_text, mode)),
+            };
+        }
+
+        if save {
+            let settings = self.settings.borrow();
+            settings.mode_keybindings.save_binding(&mode, &keys, None, &settings.editor_settings.leader)?;
+        }
+        self.send_info_message(&format!("Removed {} from {}", keys_text, mode));
+        Ok(())
+    }
+
+    fn find_binding_mode(&self, mode: &str) -> Result<String, String> {
+        self.settings.borrow().mode_keybindings.find_mode(mode).ok_or(format!("Unknown mode: {}", mode))
+    }
+
+    /// Changes or shows settings for `:set` and `:setlocal`.
+    /// Each argument is `name=value`, `name?` to show it, `noname` to turn it off, `name!` to toggle it,
+    /// `name&` to reset it, or a bare name, which turns a true or false setting on and shows any other setting.
+    /// Without arguments it lists the settings that differ from the defaults.
+    fn set_options(&mut self, args: &str, local: bool) {
+        if args.is_empty() {
+            let changed = self.changed_options(local);
+            match (changed.is_empty(), local) {
+                (true, false) => self.send_info_message("No settings differ from the defaults"),
+                (true, true) => self.send_info_message("No settings were changed for this buffer"),
+                (false, _) => self.send_info_message(&changed.join(" ")),
+            }
+            return;
+        }
+
+        let mut shown = Vec::new();
+        for arg in args.split_whitespace() {
+            match self.set_option(arg, local) {
+                Ok(Some(value)) => shown.push(value),
+                Ok(None) => {}
+                Err(message) => {
+                    self.send_info_message(&message);
+                    return;
+                }
+            }
+        }
+        if !shown.is_empty() {
+            self.send_info_message(&shown.join(" "));
+        }
+    }
+
+    /// Applies one argument of `:set`, returning the text to show when it asks for a value.
+    /// `:set` changes a setting for every buffer, while `:setlocal` changes it for the current buffer alone.
+    fn set_option(&mut self, arg: &str, local: bool) -> Result<Option<String>, String> {
+        let check_bool = |name: &str| match (EditorSettings::is_bool_option(name), OPTION_NAMES.contains(&name)) {
+            (true, _) => Ok(()),
+            (false, true) => Err(format!("{} is not a true or false setting", name)),
+            (false, false) => Err(format!("Unknown setting: {}", name)),
+        };
+
+        let (name, value) = if let Some((name, value)) = arg.split_once('=') {
+            (name, Some(value.to_string()))
+        } else if let Some(name) = arg.strip_suffix('?') {
+            (name, None)
+        } else if let Some(name) = arg.strip_suffix('!') {
+            check_bool(name)?;
+            (name, Some((self.get_option(name)? != "true").to_string()))
+        } else if let Some(name) = arg.strip_suffix('&') {
+            if local {
+                let mut local_settings = self.file.get_local_settings();
+                local_settings.set_option(name, None)?;
+                self.file.set_local_settings(local_settings);
+                return Ok(None);
+            }
+            (name, Some(EditorSettings::default().get_option(name)?))
+        } else if let Some(name) = arg.strip_prefix("no").filter(|name| OPTION_NAMES.contains(name)) {
+            check_bool(name)?;
+            (name, Some(String::from("false")))
+        } else if EditorSettings::is_bool_option(arg) {
+            (arg, Some(String::from("true")))
+        } else {
+            (arg, None)
+        };
+
+        let Some(value) = value else {
+            return self.get_option(name).map(|value| Some(format!("{}={}", name, value)));
+        };
+        let mut local_settings = self.file.get_local_settings();
+        if local {
+            local_settings.set_option(name, Some(&value))?;
+        } else {
+            self.settings.borrow_mut().editor_settings.set_option(name, &value)?;
+            // Like in Vim, `:set` also replaces the value that the current buffer had of its own
+            if LOCAL_OPTION_NAMES.contains(&name) {
+                local_settings.set_option(name, None)?;
+            }
+        }
+        self.file.set_local_settings(local_settings);
+        if name == "keymap_preset" {
+            self.load_keymap_preset();
+        }
+        Ok(None)
+    }
+
+    /// Swaps in the bindings of the preset that `:set keymap_preset` picked, with keybindings.toml on top of them again.
+    /// Bindings from `:bind` that weren't saved are lost, and the buffer goes to the mode that the preset starts in.
+    fn load_keymap_preset(&mut self) {
+        let mut settings = self.settings.borrow_mut();
+        let preset = settings.editor_settings.keymap_preset;
+        settings.editor_settings.default_mode = String::from(preset.default_mode());
+        let (mode_keybindings, problems) = ModeKeybindings::load(&settings.editor_settings.leader, preset);
+        settings.mode_keybindings = mode_keybindings;
+        drop(settings);
+
+        self.execute_command("change_mode");
+        if !problems.is_empty() {
+            self.send_info_message(&format!("{} problems in keybindings.toml, run `sevi --check-config` to list them", problems.len()));
+        }
+    }
+
+    /// Returns the value of a setting in the current buffer.
+    fn get_option(&self, name: &str) -> Result<String, String> {
+        match self.file.get_local_settings().get_option(name) {
+            Some(value) => Ok(value),
+            None => self.settings.borrow().editor_settings.get_option(name),
+        }
+    }
+
+    /// Lists the settings of the current buffer that differ from the defaults as `name=value`,
+    /// or with `local` the settings that were changed for the current buffer alone.
+    fn changed_options(&self, local: bool) -> Vec<String> {
+        let local_settings = self.file.get_local_settings();
+        let defaults = EditorSettings::default();
+        OPTION_NAMES.iter()
+            .filter_map(|name| {
+                let value = match local {
+                    true => local_settings.get_option(name)?,
+                    false => self.get_option(name).ok()?,
+                };
+                (local || defaults.get_option(name).ok()? != value).then(|| format!("{}={}", name, value))
+            })
+            .collect()
+    }
+
+    /// Runs a structural replacement written as `pattern ==>> replacement`.
+    /// Without `!` the matches are highlighted as a preview, and a bare `:ssr!` applies the previewed replacement.
+    fn structural_replace(&mut self, text: &str, apply: bool) {
+        let (text, scope) = if text.is_empty() && apply {
+            match self.pending_replacement.take() {
+                Some(pending) => pending,
+                None => {
+                    self.send_info_message("No structural replacement to apply");
+                    return;
+                }
+            }
+        } else {
+            (text.to_string(), self.command_scope.clone())
+        };
+
+        let (pattern, replacement) = match text.split_once("==>>") {
+            Some((pattern, replacement)) => (pattern.trim(), Some(replacement.trim())),
+            None => (text.trim(), None),
+        };
+        let range = scope.clone().unwrap_or(0..usize::MAX);
+
+        self.file.clear_highlights();
+        match (apply, replacement) {
+            (true, Some(replacement)) => match self.file.replace_structural(pattern, replacement, range) {
+                Ok(0) => self.send_info_message("No matches"),
+                Ok(count) => self.send_info_message(&format!("Replaced {} matches", count)),
+                Err(message) => self.send_info_message(&message),
+            },
+            (_, replacement) => match self.file.find_structural(pattern, range) {
+                Ok(matches) if matches.is_empty() => self.send_info_message("No matches"),
+                Ok(matches) => {
+                    for found in matches.iter().filter(|found| !found.range.is_empty()) {
+                        self.file.add_highlight(found.range.start, found.range.end - 1);
+                    }
+                    if replacement.is_some() {
+                        self.send_info_message(&format!("{} matches, run :ssr! to replace them", matches.len()));
+                        self.pending_replacement = Some((text, scope));
+                    } else {
+                        self.send_info_message(&format!("{} matches", matches.len()));
+                    }
+                }
+                Err(message) => self.send_info_message(&message),
+            },
+        }
+    }
+
+    /// Searches for text from the buffer, like the word under the cursor, and makes it the last search.
+    /// The search starts from `start` so that a backward search skips the text itself.
+    fn search_text(&mut self, text: &str, start: usize, forward: bool, whole_word: bool) {
+        let case = self.settings.borrow().editor_settings.search_case;
+        self.last_search = Some(Search {
+            pattern: regex::escape(text),
+            options: SearchOptions {
+                case,
+                whole_word,
+            },
+            forward,
+        });
+        self.set_cursor_to_byte_position(start);
+        self.repeat_search(false, 1);
+    }
+
+    /// Moves to the next match of the last search, highlighting all of its matches again.
+    /// With `reverse` it moves the opposite way from the direction that the search was made in.
+    fn repeat_search(&mut self, reverse: bool, amount: usize) {
+        let Some(last_search) = self.last_search.clone() else {
+            self.send_info_message("No previous search");
+            return;
+        };
+        let regex = match search::compile_pattern(&last_search.pattern, last_search.options) {
+            Ok(regex) => regex,
+            Err(message) => {
+                self.send_info_message(&message);
+                return;
+            }
+        };
+
+        self.file.clear_highlights();
+        self.file.set_search_highlight(regex.clone());
+
+        let forward = last_search.forward != reverse;
+        let mut wrapped = false;
+        for _ in 0..amount.max(1) {
+            let byte = self.get_current_byte_position();
+            let Some((found, wrap)) = self.file.find_next_regex(&regex, byte, forward) else {
+                self.send_info_message(&format!("Pattern not found: {}", last_search.pattern));
+                return;
+            };
+            wrapped |= wrap;
+            self.set_cursor_to_byte_position(found.start);
+        }
+        if wrapped {
+            self.send_info_message("search wrapped");
+        }
+    }
+
+    /// Runs the commands that can start with a line range, like `:%s/old/new/g` or `:'<,'>sort`.
+    /// Returns whether the command was one of them, so that other commands can be handed to `execute_command`.
+    fn range_commands(&mut self, command: &str) -> bool {
+        let (range, rest) = match range::parse_range(command) {
+            Ok(parsed) => parsed,
+            Err(message) => {
+                self.send_info_message(&message);
+                return true;
+            }
+        };
+
+        let name_end = match rest.chars().next() {
+            Some(shift @ ('>' | '<')) => rest.find(|c: char| c != shift).unwrap_or(rest.len()),
+            _ => rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len()),
+        };
+        let (name, args) = rest.split_at(name_end);
+
+        let result = match name {
+            "s" => match args.chars().next() {
+                None => Err(String::from("Usage: :s/pattern/replacement/flags")),
+                Some(delimiter) if delimiter.is_alphanumeric() || delimiter.is_whitespace() || "\\\"|".contains(delimiter) => {
+                    Err(format!("Invalid delimiter: {}", delimiter))
+                }
+                Some(_) => {
+                    self.substitute(&range, args);
+                    Ok(())
+                }
+            },
+            // A range on its own moves to its last line, like `:42`
+            "" if range != LineRange::Default && args.trim().is_empty() => {
+                self.resolve_range(&range).map(|(_, last_row)| {
+                    self.cursor.set_cursor(0, last_row);
+                })
+            }
+            "d" => self.delete_rows(&range, args.trim()),
+            "y" => self.yank_rows(&range, args.trim()),
+            "m" => self.move_rows(&range, args.trim()),
+            "t" | "co" => self.copy_rows(&range, args.trim()),
+            // Each extra `>` or `<` shifts by another level, like `:>>>`
+            shift if shift.starts_with(['>', '<']) && args.trim().is_empty() => {
+                let levels = if shift.starts_with('>') { shift.len() as isize } else { -(shift.len() as isize) };
+                self.resolve_range(&range).map(|(first_row, last_row)| {
+                    self.file.shift_rows(first_row, last_row, levels);
+                })
+            }
+            "sort" => self.sort_rows(&range, args.trim()),
+            // Without a range, `:w` saves the whole file
+            "w" if range != LineRange::Default => self.write_rows(&range, args.trim()),
+            "mark" | "k" => self.set_mark(&range, args.trim()),
+            "g" => self.global(&range, args, false),
+            "v" => self.global(&range, args, true),
+            "normal" | "norm" => self.normal(&range, args.trim_start()),
+            _ => return false,
+        };
+
+        if let Err(message) = result {
+            self.send_info_message(&message);
+        }
+        true
+    }
+
+    /// Turns a line range into its first and last row.
+    /// Without a range it is the current line, or the lines of the selection that the command line was opened from.
+    fn resolve_range(&self, range: &LineRange) -> Result<(usize, usize), String> {
+        let (_, row) = self.get_cursor();
+        let to_row = |line: usize| line.saturating_sub(1);
+
+        match range {
+            LineRange::Default => {
+                let scope_rows = self.command_scope.as_ref().map(|scope| {
+                    let (_, first) = self.file.get_cursor(scope.start).unwrap_or((0, 0));
+                    let (_, last) = self.file.get_cursor(scope.end.saturating_sub(1)).unwrap_or((0, first));
+                    (first, last)
+                });
+                Ok(scope_rows.unwrap_or((row, row)))
+            }
+            LineRange::Whole => Ok((0, self.file.get_last_row())),
+            LineRange::Single(address) => {
+                let line = to_row(self.resolve_address(address, row)?);
+                Ok((line, line))
+            }
+            LineRange::Pair(first, second, from_first) => {
+                let first = to_row(self.resolve_address(first, row)?);
+                let second = to_row(self.resolve_address(second, if *from_first { first } else { row })?);
+                Ok((first.min(second), first.max(second)))
+            }
+        }
+    }
+
+    /// Turns an address into a line number that starts at 1, where 0 is before the first line.
+    /// Relative addresses count from `row`.
+    fn resolve_address(&self, address: &Address, row: usize) -> Result<usize, String> {
+        let last_line = self.file.get_last_row() + 1;
+        let line = match &address.base {
+            AddressBase::Line(line) => *line,
+            AddressBase::Current => row + 1,
+            AddressBase::Last => last_line,
+            AddressBase::Mark(mark @ ('<' | '>')) => {
+                let (first, last) = self.last_selection.ok_or(String::from("No selection"))?;
+                if *mark == '<' { first + 1 } else { last + 1 }
+            }
+            AddressBase::Mark(mark) => {
+                let row = self.marks.get(mark).ok_or(format!("Mark not set: {}", mark))?;
+                row.min(&self.file.get_last_row()) + 1
+            }
+            AddressBase::Pattern { pattern, forward } => {
+                let (regex, pattern) = self.compile_line_pattern(pattern)?;
+                // The line of the cursor is skipped, and the search wraps around the ends of the file
+                let byte = match forward {
+                    true => self.file.get_next_row_start(self.file.get_byte_offset(row, 0).unwrap_or(0)).saturating_sub(1),
+                    false => self.file.get_byte_offset(row, 0).unwrap_or(0),
+                };
+                let found = self.file.find_next_regex(&regex, byte, *forward)
+                    .and_then(|(found, _)| self.file.get_cursor(found.start))
+                    .map(|(_, row)| row);
+                found.ok_or(format!("Pattern not found: {}", pattern))? + 1
+            }
+        };
+
+        let line = line as isize + address.offset;
+        if line < 0 {
+            return Err(String::from("Invalid range"));
+        }
+        Ok((line as usize).min(last_line))
+    }
+
+    /// Compiles a pattern from a command, where an empty pattern repeats the last search.
+    /// Returns the pattern that was used along with it.
+    fn compile_line_pattern(&self, pattern: &str) -> Result<(Regex, String), String> {
+        let (pattern, options) = match (pattern.is_empty(), &self.last_search) {
+            (false, _) => (pattern.to_string(), SearchOptions {
+                case: self.settings.borrow().editor_settings.search_case,
+                whole_word: false,
+            }),
+            (true, Some(last_search)) => (last_search.pattern.clone(), last_search.options),
+            (true, None) => return Err(String::from("No previous search")),
+        };
+        Ok((search::compile_pattern(&pattern, options)?, pattern))
+    }
+
+    /// Parses the address that `:m` and `:t` put the lines after.
+    fn resolve_destination(&self, text: &str) -> Result<usize, String> {
+        let (_, row) = self.get_cursor();
+        match range::parse_address(text)? {
+            Some((address, rest)) if rest.trim().is_empty() => self.resolve_address(&address, row),
+            _ => Err(String::from("Missing destination")),
+        }
+    }
+
+    /// Deletes the lines, keeping them in a register when one is given.
+    fn delete_rows(&mut self, range: &LineRange, register: &str) -> Result<(), String> {
+        let (first_row, last_row) = self.resolve_range(range)?;
+        if !register.is_empty() {
+            self.yank_rows(range, register)?;
+        }
+
+        self.file.replace_rows(first_row, last_row, Vec::new());
+        self.cursor.set_cursor(0, first_row.min(self.file.get_last_row()));
+        self.send_info_message(&format!("{} lines deleted", last_row - first_row + 1));
+        Ok(())
+    }
+
+    /// Copies the lines to the clipboard, or to a register when one is given.
+    fn yank_rows(&mut self, range: &LineRange, register: &str) -> Result<(), String> {
+        let (first_row, last_row) = self.resolve_range(range)?;
+        let text = self.file.get_rows(first_row, last_row).iter()
+            .map(|row| format!("{}\n", row))
+            .collect::<String>();
+
+        let message = match register {
+            "" => RegisterMessage::SetClipboard(text),
+            // Like in Vim, an uppercase register appends to the lowercase one, as in `:g/todo/y A`
+            register if register.len() == 1 && register.chars().all(|c| c.is_ascii_uppercase()) => {
+                let register = register.to_ascii_lowercase();
+                self.register_channels.0.send(RegisterMessage::GetNamed(register.clone())).expect("Failed to send register message");
+                let message = self.register_channels.1.recv().expect("Failed to receive register message");
+                let existing = match message {
+                    RegisterMessage::RegisterResult(Some(existing), _) => existing,
+                    _ => String::new(),
+                };
+                RegisterMessage::AddNamed(register, existing + &text)
+            }
+            register => match register.parse::<usize>() {
+                Ok(register) => RegisterMessage::AddNumbered(register, text),
+                Err(_) => RegisterMessage::AddNamed(register.to_string(), text),
+            },
+        };
+        self.register_channels.0.send(message).expect("Failed to send register message");
+        self.send_info_message(&format!("{} lines yanked", last_row - first_row + 1));
+        Ok(())
+    }
+
+    /// Moves the lines below the destination line, as a single undo step.
+    fn move_rows(&mut self, range: &LineRange, destination: &str) -> Result<(), String> {
+        let (first_row, last_row) = self.resolve_range(range)?;
+        let destination = self.resolve_destination(destination)?;
+        if destination > first_row && destination <= last_row {
+            return Err(String::from("Cannot move lines into themselves"));
+        }
+
+        let count = last_row - first_row + 1;
+        self.file.move_rows(first_row, last_row, destination);
+        let new_last = if destination <= first_row { destination + count - 1 } else { destination - 1 };
+        self.cursor.set_cursor(0, new_last);
+        Ok(())
+    }
+
+    /// Copies the lines below the destination line.
+    fn copy_rows(&mut self, range: &LineRange, destination: &str) -> Result<(), String> {
+        let (first_row, last_row) = self.resolve_range(range)?;
+        let destination = self.resolve_destination(destination)?;
+
+        let copied = self.file.get_rows(first_row, last_row);
+        let count = copied.len();
+        self.file.insert_rows(destination, copied);
+        self.cursor.set_cursor(0, destination + count - 1);
+        Ok(())
+    }
+
+    /// Sorts the lines as a single undo step.
+    /// `!` reverses the order, and the flags are `i` to ignore case, `n` to sort by the first number
+    /// in each line and `u` to keep only the first of equal lines.
+    fn sort_rows(&mut self, range: &LineRange, args: &str) -> Result<(), String> {
+        // Sorting a single line does nothing, so `:sort` sorts the whole file like in Vim
+        let range = match range {
+            LineRange::Default if self.command_scope.is_none() => &LineRange::Whole,
+            range => range,
+        };
+        let (first_row, last_row) = self.resolve_range(range)?;
+        let (reverse, flags) = match args.strip_prefix('!') {
+            Some(flags) => (true, flags.trim()),
+            None => (false, args),
+        };
+        if let Some(flag) = flags.chars().find(|flag| !"inu ".contains(*flag)) {
+            return Err(format!("Unknown flag: {}", flag));
+        }
+        let ignore_case = flags.contains('i');
+        let numeric = flags.contains('n');
+
+        let number_pattern = Regex::new(r"-?\d+").unwrap();
+        let key = |row: &String| -> (Option<i64>, String) {
+            let number = match numeric {
+                true => number_pattern.find(row).and_then(|found| found.as_str().parse().ok()),
+                false => None,
+            };
+            let text = if ignore_case { row.to_lowercase() } else { row.clone() };
+            (number, text)
+        };
+
+        let mut rows = self.file.get_rows(first_row, last_row);
+        rows.sort_by_cached_key(key);
+        if flags.contains('u') {
+            rows.dedup_by(|a, b| key(a) == key(b));
+        }
+        if reverse {
+            rows.reverse();
+        }
+        self.file.replace_rows(first_row, last_row, rows);
+        Ok(())
+    }
+
+    /// Writes the lines to a file, which has to be forced with `:w!` when the file already exists.
+    fn write_rows(&mut self, range: &LineRange, args: &str) -> Result<(), String> {
+        let (first_row, last_row) = self.resolve_range(range)?;
+        let (force, path) = match args.strip_prefix('!') {
+            Some(path) => (true, path.trim()),
+            None => (false, args),
+        };
+        if path.is_empty() {
+            return Err(String::from("No file name"));
+        }
+        let path = PathBuf::from(completion::expand_home(path));
+        if path.exists() && !force {
+            return Err(String::from("File exists. Use `w!` to overwrite it"));
+        }
+
+        let text = self.file.get_rows(first_row, last_row).iter()
+            .map(|row| format!("{}\n", row))
+            .collect::<String>();
+        std::fs::write(&path, text).map_err(|error| error.to_string())?;
+        self.send_info_message(&format!("{} lines written to {}", last_row - first_row + 1, path.display()));
+        Ok(())
+    }
+
+    /// Runs a command on every line in the range that matches a pattern, like `:g/TODO/d`,
+    /// or on every line that doesn't match for `:v` and `:g!`. Without a range it covers the whole file.
+    /// The lines are found before the command runs, and everything it changes is one undo step.
+    fn global(&mut self, range: &LineRange, args: &str, invert: bool) -> Result<(), String> {
+        let (invert, args) = match args.strip_prefix('!') {
+            Some(args) => (!invert, args),
+            None => (invert, args),
+        };
+        let delimiter = args.chars().next().ok_or(String::from("No pattern given"))?;
+        if delimiter.is_alphanumeric() || delimiter.is_whitespace() || "\\\"|".contains(delimiter) {
+            return Err(format!("Invalid delimiter: {}", delimiter));
+        }
+        let (pattern, end) = range::parse_pattern(&args[delimiter.len_utf8()..], delimiter);
+        let command = args[delimiter.len_utf8() + end..].trim();
+        if command.is_empty() {
+            return Err(String::from("No command given"));
+        }
+        if let Ok((_, rest)) = range::parse_range(command) {
+            let name = rest.split(|c: char| !c.is_ascii_alphabetic()).next().unwrap_or("");
+            if name == "g" || name == "v" {
+                return Err(String::from("Cannot run :g inside of :g"));
+            }
+        }
+
+        let (regex, pattern) = self.compile_line_pattern(&pattern)?;
+        let range = match range {
+            LineRange::Default if self.command_scope.is_none() => &LineRange::Whole,
+            range => range,
+        };
+        let (first_row, last_row) = self.resolve_range(range)?;
+        let rows = self.file.get_rows(first_row, last_row).iter()
+            .enumerate()
+            .filter(|(_, line)| regex.is_match(line) != invert)
+            .map(|(index, _)| first_row + index)
+            .collect::<Vec<_>>();
+        if rows.is_empty() {
+            return Err(format!("Pattern not found: {}", pattern));
+        }
+
+        self.run_on_rows(rows, command);
+        Ok(())
+    }
+
+    /// Types keys in Normal Mode on each line of the range, like `:%normal A;`.
+    /// Keys that can't be typed into the command line are written like `<Esc>` or `<C-r>`.
+    fn normal(&mut self, range: &LineRange, keys: &str) -> Result<(), String> {
+        if keys.is_empty() {
+            return Err(String::from("No keys given"));
+        }
+
+        match range {
+            LineRange::Default if self.command_scope.is_none() => self.type_keys(&self.parse_keys(keys)),
+            range => {
+                let (first_row, last_row) = self.resolve_range(range)?;
+                self.run_on_rows((first_row..=last_row).collect(), &format!("normal {}", keys));
+                Ok(())
+            }
+        }
+    }
+
+    /// Feeds keys to Normal Mode as if they were typed, then goes back to the default mode.
+    fn type_keys(&mut self, keys: &[KeyEvent]) -> Result<(), String> {
+        let normal_mode = self.modes.get("Normal").unwrap().clone();
+        self.mode = normal_mode;
+
+        let mut result = Ok(());
+        for key in keys {
+            let mode = self.mode.clone();
+            // The mode that ran this command is still busy with it, so the keys can't switch back to it
+            let Ok(mut mode) = mode.try_borrow_mut() else {
+                result = Err(String::from("The keys switched to a mode that can't be used from :normal"));
+                break;
+            };
+            mode.process_keypress(*key, self);
+        }
+        // Keys that could still start a longer binding run on their own, as they would once the key timeout ran out
+        let mode = self.mode.clone();
+        if let (true, Ok(mut mode)) = (result.is_ok(), mode.try_borrow_mut()) {
+            mode.flush_keys(self);
+        }
+
+        let default_mode = self.settings.borrow().editor_settings.default_mode.clone();
+        self.execute_command(&format!("change_mode {}", default_mode));
+        result
+    }
+
+    /// Runs a command with the cursor on each of the rows in turn, as a single undo step.
+    /// The rows are followed as the command adds, removes and moves lines,
+    /// and rows whose lines get deleted are skipped.
+    fn run_on_rows(&mut self, rows: Vec<usize>, command: &str) {
+        let scope = self.command_scope.take();
+        let undo_group = self.file.start_undo_group();
+        let line_count = self.file.get_line_count();
+
+        let mut version = self.file.get_version();
+        let mut rows = rows;
+        while !rows.is_empty() {
+            let row = rows.remove(0);
+            self.cursor.set_cursor(0, row);
+            self.execute_command_line(command);
+
+            // Rows can't be followed past an undo, so the rest of them are skipped
+            rows = match self.file.get_row_edits(version) {
+                Some(edits) => range::follow_rows(&edits, &rows),
+                None => Vec::new(),
+            };
+            version = self.file.get_version();
+        }
+
+        self.file.end_undo_group(undo_group);
+        self.command_scope = scope;
+
+        let difference = self.file.get_line_count() as isize - line_count as isize;
+        // Only the total is worth showing rather than the message from the last row
+        self.sender.send(AppEvent::RemoveInfoDisplay).expect("Failed to send remove info display event");
+        if difference < 0 {
+            self.send_info_message(&format!("{} fewer lines", -difference));
+        } else if difference > 0 {
+            self.send_info_message(&format!("{} more lines", difference));
+        }
+    }
+
+    /// Remembers the last line of the range under a letter, for use as `'a` in later ranges.
+    fn set_mark(&mut self, range: &LineRange, mark: &str) -> Result<(), String> {
+        let (_, last_row) = self.resolve_range(range)?;
+        let mut chars = mark.chars();
+        match (chars.next(), chars.next()) {
+            (Some(mark), None) if mark.is_ascii_lowercase() => {
+                self.marks.insert(mark, last_row);
+                Ok(())
+            }
+            (None, _) => Err(String::from("Missing mark name")),
+            _ => Err(format!("Invalid mark: {}", mark)),
+        }
+    }
+
+    /// Runs `:s/pattern/replacement/flags` over a line range as a single undo step.
+    /// With the `c` flag each match is confirmed in Confirm Mode before anything is replaced.
+    fn substitute(&mut self, range: &LineRange, text: &str) {
+        let (first_row, last_row) = match self.resolve_range(range) {
+            Ok(rows) => rows,
+            Err(message) => {
+                self.send_info_message(&message);
+                return;
+            }
+        };
+        let case = self.settings.borrow().editor_settings.search_case;
+        let substitution = match Substitution::parse(text, case) {
+            Ok(substitution) => substitution,
+            Err(message) => {
+                self.send_info_message(&message);
+                return;
+            }
+        };
+
+        self.file.clear_highlights();
+        let edits = self.file.find_substitutions(&substitution, first_row, last_row);
+        if edits.is_empty() {
+            self.send_info_message("Pattern not found");
+            return;
+        }
+
+        if substitution.confirm {
+            let mode = self.modes.get("Confirm").unwrap().clone();
+            mode.borrow_mut().add_special(&edits);
+            self.mode = mode.clone();
+            mode.borrow_mut().start(self);
+            return;
+        }
+
+        // Put the cursor on the last replacement, after the earlier ones have moved it
+        let shift = edits[..edits.len() - 1].iter()
+            .map(|(range, replacement)| replacement.len() as isize - range.len() as isize)
+            .sum::<isize>();
+        let last = (edits[edits.len() - 1].0.start as isize + shift) as usize;
+
+        let count = edits.len();
+        self.file.replace_ranges(edits);
+        // The last replacement can end the buffer, which leaves its start past the last byte
+        match self.file.get_byte_count() {
+            0 => self.cursor.set_cursor(0, 0),
+            len => self.set_cursor_to_byte_position(last.min(len - 1)),
+        }
+        self.send_info_message(&format!("{} substitutions", count));
+    }
+
     fn movement_commands(&mut self, command_name: &str, command_args: &mut SplitWhitespace) {
         match command_name {
             "move" => {
                 let direction = command_args.next();
                 if let Some("to") = direction {
                     if let Some(set) = command_args.next() {
-                        let set = set.split(',').map(|x| x.parse::<usize>().unwrap()).collect::<Vec<usize>>();
-                        self.cursor.set_cursor(set[0], set[1]);
+                        match parse_position(set) {
+                            Some((column, row)) => self.cursor.set_cursor(column, row),
+                            None => self.send_info_message("move to takes a position like column,row"),
+                        }
                     }
                     return;
                 } else if let Some("to_byte") = direction {
@@ -773,6 +1889,13 @@ impl TextBuffer {
                         }
                     }
                     return;
+                } else if let Some("to_pair") = direction {
+                    let byte = self.get_current_byte_position();
+                    match self.file.get_matching_pair(byte) {
+                        Some((_, pair)) => self.set_cursor_to_byte_position(pair.start),
+                        None => self.send_info_message("No matching pair"),
+                    }
+                    return;
                 }
 
 
@@ -801,7 +1924,10 @@ impl TextBuffer {
                     Some("next_word_back") => CursorMovement::WordBackRight,
                     Some("prev_word_front") => CursorMovement::WordFrontLeft,
                     Some("prev_word_back") =>  CursorMovement::WordBackLeft,
-                    _ => panic!("Invalid direction"),
+                    direction => {
+                        self.send_info_message(&format!("Unknown direction: {}", direction.unwrap_or("")));
+                        return;
+                    },
                 };
 
                 let arg = command_args.next();
@@ -813,6 +1939,32 @@ impl TextBuffer {
                 self.cursor.move_cursor(direction, amount, &self.file);
 
             }
+            "search" => {
+                let reverse = command_args.next() == Some("previous");
+                let amount = command_args.next().and_then(|amount| amount.parse::<usize>().ok()).unwrap_or(1);
+                self.repeat_search(reverse, amount);
+            }
+            "search_word" => {
+                let forward = command_args.next() != Some("backward");
+                let whole_word = command_args.next() != Some("partial");
+                let byte = self.get_current_byte_position();
+                match (self.file.get_word_range(byte), self.file.get_word(byte)) {
+                    (Some(range), Some(word)) => self.search_text(&word, range.start, forward, whole_word),
+                    _ => self.send_info_message("No word found at cursor position"),
+                }
+            }
+            "search_selection" => {
+                let forward = command_args.next() != Some("backward");
+                let highlights = self.file.get_highlights();
+                let text = match (highlights.first(), highlights.last()) {
+                    (Some(first), Some(last)) => self.file.get_slice(*first, *last + 1).map(|text| (*first, text)),
+                    _ => None,
+                };
+                match text {
+                    Some((start, text)) if !text.is_empty() => self.search_text(&text, start, forward, false),
+                    _ => self.send_info_message("Nothing is selected"),
+                }
+            }
             "goto_line" => {
                 if let Some(line) = command_args.next() {
                     if let Ok(line) = line.parse::<usize>() {
@@ -828,16 +1980,38 @@ impl TextBuffer {
 }
 
 
+/// Parses a position written as `column,row`, like the ones that `move to` and `select` take.
+fn parse_position(text: &str) -> Option<(usize, usize)> {
+    let (column, row) = text.split_once(',')?;
+    Some((column.parse().ok()?, row.parse().ok()?))
+}
+
+
 impl Pane for TextBuffer {
     fn execute_command(&mut self, command: &str) {
         self.sender.send(AppEvent::RemoveInfoDisplay).expect("Failed to send remove info display event");
         let mut command_args = command.split_whitespace();
-        let command = command_args.next().unwrap_or("");
+        let command_name = command_args.next().unwrap_or("");
+        // Everything after the name, for commands that take free-form text like patterns
+        let text = command.trim_start()[command_name.len()..].trim();
+
+        let alias = self.settings.borrow().mode_keybindings.alias(command_name).cloned();
+        if let Some(commands) = alias {
+            self.run_alias(command_name, text, commands);
+            return;
+        }
 
-        self.editor_commands(command, &mut command_args);
-        self.edit_commands(command, &mut command_args);
-        self.pane_commands(command, &mut command_args);
-        self.movement_commands(command, &mut command_args);
+        self.editor_commands(command_name, &mut command_args);
+        self.edit_commands(command_name, &mut command_args);
+        self.pane_commands(command_name, &mut command_args);
+        self.movement_commands(command_name, &mut command_args);
+        self.text_commands(command_name, text);
+    }
+
+    fn execute_command_line(&mut self, command: &str) {
+        if !self.range_commands(command.trim()) {
+            self.execute_command(command);
+        }
     }
 
     fn get_cursor_position(&self) -> Option<(usize, usize)> {
@@ -878,6 +2052,8 @@ impl Pane for TextBuffer {
     fn refresh(&mut self) {
         let number_line_width = self.get_number_line_width();
         self.cursor.set_number_line_width(number_line_width);
+        let gutter_width = self.get_gutter_width();
+        self.cursor.set_gutter_width(gutter_width);
     }
 
     fn get_settings(&self) -> Rc<RefCell<Settings>> {
@@ -953,15 +2129,17 @@ impl TextPane for TextBuffer {
         self.insert_char(index, '\n');
         self.cursor.move_cursor(CursorMovement::Down, 1, &self.file);
         self.cursor.move_cursor(CursorMovement::LineStart, 1, &self.file);
+
+        let (_, row) = self.get_cursor();
+        let indentation = self.file.get_newline_indentation(row);
+        self.insert_indentation(&indentation);
     }
 
     fn tab(&mut self) {
         let index = self.get_current_byte_position();
 
-        let settings = self.settings.clone();
-        let settings = settings.borrow();
-        let tab_size = settings.editor_settings.tab_size;
-        let use_spaces = settings.editor_settings.use_spaces;
+        let tab_size = self.file.get_tab_size();
+        let use_spaces = self.file.get_use_spaces();
 
         if use_spaces {
             self.insert_str_after(index, " ".repeat(tab_size as usize).as_str());
@@ -974,7 +2152,21 @@ impl TextPane for TextBuffer {
 
     fn insert_char(&mut self, index: usize, c: char) {
         self.file.insert_char(index, c);
-        self.cursor.move_cursor(CursorMovement::Right, 1, &self.file)
+        self.cursor.move_cursor(CursorMovement::Right, 1, &self.file);
+
+        // Typing a closing delimiter at the start of a line dedents it
+        if !c.is_whitespace() {
+            let (col, row) = self.get_cursor();
+            if let Some(indentation) = self.file.get_outdent_indentation(row, index + c.len_utf8()) {
+                let current = self.file.get_indentation(row);
+                if current != indentation {
+                    let start = self.file.get_byte_offset(row, 0).unwrap_or(0);
+                    let col = (col + indentation.chars().count()).saturating_sub(current.chars().count());
+                    self.file.replace_ranges_current(vec![(start..start + current.len(), indentation)]);
+                    self.cursor.set_cursor(col, row);
+                }
+            }
+        }
     }
     fn insert_str_after(&mut self, index: usize, string: &str) {
         self.file.insert_after_current(index, string);
@@ -1006,6 +2198,14 @@ impl TextPane for TextBuffer {
     fn borrow_current_file_mut(&mut self) -> &mut File {
         &mut self.file
     }
+
+    fn set_last_search(&mut self, search: Search) {
+        self.last_search = Some(search);
+    }
+
+    fn get_buffer_names(&self) -> Vec<String> {
+        self.buffer_names.clone()
+    }
 }
 
 
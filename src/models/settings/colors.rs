@@ -1,9 +1,11 @@
 use std::collections::HashMap;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io;
-use std::io::{Read, Write};
+use std::io::Write;
+use crate::models::settings::ConfigProblem;
 use crate::models::style::color::Color;
 use crate::models::style::Style;
+use crate::models::style::text_modifier::Modifier;
 
 
 impl Default for EditorColors {
@@ -11,6 +13,11 @@ impl Default for EditorColors {
         EditorColors {
             buffer_color: Style::new(),
             selected: Style::new().fg(Color::Black).bg(Color::LightBlue),
+            folded: Style::new().fg(Color::DarkGray),
+            syntax_error: Style::new().add_modifier(Modifier::UNDERLINE),
+            syntax_error_mark: Style::new().fg(Color::LightRed),
+            sticky_scroll: Style::new().add_modifier(Modifier::BOLD),
+            matching_pair: Style::new().bg(Color::DarkGray),
             number_bar: NumberBarColor::default(),
             status_bar: StatusBarColor::default(),
             rainbow_delimiters: vec![
@@ -27,6 +34,11 @@ impl Default for EditorColors {
 pub struct EditorColors {
     pub buffer_color: Style,
     pub selected: Style,
+    pub folded: Style,
+    pub syntax_error: Style,
+    pub syntax_error_mark: Style,
+    pub sticky_scroll: Style,
+    pub matching_pair: Style,
     pub number_bar: NumberBarColor,
     pub status_bar: StatusBarColor,
     pub rainbow_delimiters: Vec<Style>,
@@ -48,6 +60,16 @@ impl EditorColors {
 
         output.push_str(&format!("[EditorColors.selected]\n{}\n", self.selected.config_file()));
 
+        output.push_str(&format!("[EditorColors.folded]\n{}\n", self.folded.config_file()));
+
+        output.push_str(&format!("[EditorColors.syntax_error]\n{}\n", self.syntax_error.config_file()));
+
+        output.push_str(&format!("[EditorColors.syntax_error_mark]\n{}\n", self.syntax_error_mark.config_file()));
+
+        output.push_str(&format!("[EditorColors.sticky_scroll]\n{}\n", self.sticky_scroll.config_file()));
+
+        output.push_str(&format!("[EditorColors.matching_pair]\n{}\n", self.matching_pair.config_file()));
+
 
         output.push_str(&format!("{}\n", self.number_bar.config_file()));
 
@@ -69,43 +91,51 @@ impl EditorColors {
         file.write_all(settings.config_file().as_bytes())
     }
 
-    fn load_user_settings(config: &str) -> Result<EditorColors, String> {
-        let table = config.parse::<toml::Value>().expect("Could not parse config file");
+    fn load_user_settings(config: &str, path: &str) -> Result<EditorColors, ConfigProblem> {
+        let table = config.parse::<toml::Value>()
+            .map_err(|error| ConfigProblem::from_toml_error(path, config, &error))?;
 
         match table.get("EditorColors") {
             None => Ok(EditorColors::default()),
-            Some(editor_colors) => parse_editor_colors(editor_colors),
+            Some(editor_colors) => parse_editor_colors(editor_colors).map_err(|message| ConfigProblem::new(path, None, message)),
         }
     }
 
-    pub fn new() -> EditorColors {
+    /// Loads the colors along with the problems found in the user's colors.toml.
+    /// The colors stay at their defaults when the file has a problem.
+    pub fn load() -> (EditorColors, Vec<ConfigProblem>) {
         let mut settings = EditorColors::default();
 
         if crate::arg_parser::IGNORE_USER_SETTINGS.load(std::sync::atomic::Ordering::Relaxed) {
-            return settings;
+            return (settings, Vec::new());
         }
 
         let xdg_dirs = xdg::BaseDirectories::with_prefix("sevi").unwrap();
-        let config_path = xdg_dirs.place_config_file("colors.toml").expect("Could not create config file");
-
-        match File::open(config_path) {
-            Err(_) => {},
-            Ok(mut user_config) => {
-                let mut string = String::new();
-                user_config.read_to_string(&mut string).expect("Could not read config file");
-
-                let user_settings = EditorColors::load_user_settings(&string).expect("Could not parse config file");
-
-                settings.merge(user_settings);
-            }
+        let Some(config_path) = xdg_dirs.find_config_file("colors.toml") else {
+            return (settings, Vec::new());
+        };
+        let path = config_path.display().to_string();
+
+        let string = match fs::read_to_string(&config_path) {
+            Ok(string) => string,
+            Err(error) => return (settings, vec![ConfigProblem::new(&path, None, format!("Could not read the file: {}", error))]),
+        };
+
+        match EditorColors::load_user_settings(&string, &path) {
+            Ok(user_settings) => settings.merge(user_settings),
+            Err(problem) => return (settings, vec![problem]),
         }
 
-
-        settings
+        (settings, Vec::new())
     }
 
     fn merge(&mut self, other: Self) {
         self.buffer_color.patch(other.buffer_color);
+        self.folded = self.folded.patch(other.folded);
+        self.syntax_error = self.syntax_error.patch(other.syntax_error);
+        self.syntax_error_mark = self.syntax_error_mark.patch(other.syntax_error_mark);
+        self.sticky_scroll = self.sticky_scroll.patch(other.sticky_scroll);
+        self.matching_pair = self.matching_pair.patch(other.matching_pair);
         self.number_bar.merge(other.number_bar);
         self.status_bar.merge(other.status_bar);
         self.rainbow_delimiters = other.rainbow_delimiters;
@@ -123,6 +153,26 @@ fn parse_editor_colors(table: &toml::Value) -> Result<EditorColors, String> {
         editor_colors.selected = crate::models::style::parse_style(selected)?;
     }
 
+    if let Some(folded) = table.get("folded") {
+        editor_colors.folded = crate::models::style::parse_style(folded)?;
+    }
+
+    if let Some(syntax_error) = table.get("syntax_error") {
+        editor_colors.syntax_error = crate::models::style::parse_style(syntax_error)?;
+    }
+
+    if let Some(syntax_error_mark) = table.get("syntax_error_mark") {
+        editor_colors.syntax_error_mark = crate::models::style::parse_style(syntax_error_mark)?;
+    }
+
+    if let Some(sticky_scroll) = table.get("sticky_scroll") {
+        editor_colors.sticky_scroll = crate::models::style::parse_style(sticky_scroll)?;
+    }
+
+    if let Some(matching_pair) = table.get("matching_pair") {
+        editor_colors.matching_pair = crate::models::style::parse_style(matching_pair)?;
+    }
+
     if let Some(number_bar) = table.get("number_bar") {
         editor_colors.number_bar = parse_number_bar_color(number_bar)?;
     }
@@ -214,6 +264,9 @@ impl Default for StatusBarColor {
         mode.insert("Replace".to_string(), Style::new().fg(Color::Black).bg(Color::LightRed));
         mode.insert("Mirror".to_string(), Style::new().fg(Color::Black).bg(Color::Gray));
         mode.insert("Pair".to_string(), Style::new().fg(Color::Black).bg(Color::White));
+        mode.insert("Symbols".to_string(), Style::new().fg(Color::Black).bg(Color::LightGreen));
+        mode.insert("Confirm".to_string(), Style::new().fg(Color::Black).bg(Color::LightRed));
+        mode.insert("History".to_string(), Style::new().fg(Color::Black).bg(Color::LightMagenta));
 
         StatusBarColor {
             message: Style::new().bg(Color::DarkGray),
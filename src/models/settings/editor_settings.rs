@@ -1,8 +1,9 @@
-use std::fs::File;
+use std::fs::{self, File};
 use std::{fmt, io};
 use std::collections::HashMap;
 use std::fmt::Formatter;
-use std::io::{Read, Write};
+use std::io::Write;
+use crate::models::settings::{key_lines, ConfigProblem};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum NumberLineStyle {
@@ -24,6 +25,56 @@ impl fmt::Display for NumberLineStyle {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SearchCase {
+    /// Uppercase and lowercase letters are different
+    Sensitive,
+    /// Uppercase and lowercase letters are the same
+    Insensitive,
+    /// Ignore case unless the pattern has an uppercase letter
+    Smart,
+}
+
+impl fmt::Display for SearchCase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SearchCase::Sensitive => write!(f, "Sensitive"),
+            SearchCase::Insensitive => write!(f, "Insensitive"),
+            SearchCase::Smart => write!(f, "Smart"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeymapPreset {
+    /// Operators come before the motion they act on, like in Vim
+    Vim,
+    /// Motions select text first and operators act on the selection, like in Helix and Kakoune
+    Helix,
+    /// Chords with Ctrl and Alt from Insert mode, like in Emacs
+    Emacs,
+}
+
+impl KeymapPreset {
+    /// The mode that a buffer starts in with this preset, unless `default_mode` says otherwise.
+    pub fn default_mode(&self) -> &'static str {
+        match self {
+            KeymapPreset::Emacs => "Insert",
+            KeymapPreset::Vim | KeymapPreset::Helix => "Normal",
+        }
+    }
+}
+
+impl fmt::Display for KeymapPreset {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            KeymapPreset::Vim => write!(f, "Vim"),
+            KeymapPreset::Helix => write!(f, "Helix"),
+            KeymapPreset::Emacs => write!(f, "Emacs"),
+        }
+    }
+}
+
 
 impl fmt::Display for EditorSettings {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
@@ -33,7 +84,13 @@ impl fmt::Display for EditorSettings {
         write!(f, "\ntab_size = {}", self.tab_size)?;
         write!(f, "\nuse_spaces = {}", self.use_spaces)?;
         write!(f, "\nrainbow_delimiters = {}", self.rainbow_delimiters)?;
+        write!(f, "\nsticky_scroll = {}", self.sticky_scroll)?;
+        write!(f, "\nsearch_case = \"{}\"", self.search_case)?;
         write!(f, "\ndefault_mode = \"{}\"", self.default_mode)?;
+        write!(f, "\nkey_timeout = {}", self.key_timeout)?;
+        write!(f, "\nwhich_key_delay = {}", self.which_key_delay)?;
+        write!(f, "\nleader = {}", toml::Value::String(self.leader.clone()))?;
+        write!(f, "\nkeymap_preset = \"{}\"", self.keymap_preset)?;
         write!(f, "\npairs = [{}]", self.pairs_to_string())
 
     }
@@ -50,6 +107,11 @@ pub struct EditorSettings {
     pub use_spaces: bool,
     /// Whether to highlight matching brackets
     pub rainbow_delimiters: bool,
+    /// The most header lines of enclosing scopes to pin at the top of the buffer
+    /// A value of 0 turns sticky scroll off.
+    pub sticky_scroll: usize,
+    /// How searches treat uppercase and lowercase letters
+    pub search_case: SearchCase,
     /// The font settings
     /// This is only used in the GUI not the TUI.
     pub font_settings: Option<FontSettings>,
@@ -57,6 +119,16 @@ pub struct EditorSettings {
     /// This is the mode that the editor will start in.
     /// The value should be either "Normal" or "Insert".
     pub default_mode: String,
+    /// How many milliseconds to wait for the next key when the keys typed so far start a longer binding,
+    /// before they are run on their own
+    pub key_timeout: u64,
+    /// How many milliseconds to wait after a key that starts a longer binding before listing how it can go on
+    pub which_key_delay: u64,
+    /// The key that `<leader>` stands for in bindings, written like the keys of `:bind`, such as `\` or `<Space>`
+    /// Bindings read it when they are made, so changing it doesn't move the bindings that already use it.
+    pub leader: String,
+    /// The built-in key bindings that the ones in keybindings.toml are added on top of
+    pub keymap_preset: KeymapPreset,
     /// Pairs to use within Pair mode
     pub pairs: HashMap<String, String>,
 }
@@ -86,8 +158,14 @@ impl Default for EditorSettings {
             tab_size: 4,
             use_spaces: true,
             rainbow_delimiters: true,
+            sticky_scroll: 3,
+            search_case: SearchCase::Sensitive,
             font_settings: None,
             default_mode: String::from("Normal"),
+            key_timeout: 1000,
+            which_key_delay: 500,
+            leader: String::from("\\"),
+            keymap_preset: KeymapPreset::Vim,
             pairs,
         }
     }
@@ -95,28 +173,29 @@ impl Default for EditorSettings {
 
 
 impl EditorSettings {
-    pub fn new() -> Self {
+    /// Loads the settings along with the problems found in the user's config.toml.
+    /// Settings that have a problem keep their default.
+    pub fn load() -> (Self, Vec<ConfigProblem>) {
         let mut settings = EditorSettings::default();
 
         if crate::arg_parser::IGNORE_USER_SETTINGS.load(std::sync::atomic::Ordering::Relaxed) {
-            return settings;
+            return (settings, Vec::new());
         }
 
         let xdg_dirs = xdg::BaseDirectories::with_prefix("sevi").unwrap();
-        let config_path = xdg_dirs.place_config_file("config.toml").expect("Could not create config file");
-        match File::open(config_path) {
-            Err(_) => {},
-            Ok(mut user_config) => {
-                let mut string = String::new();
-                user_config.read_to_string(&mut string).expect("Could not read config file");
-
-                let user_settings = EditorSettings::load_user_settings(&string);
+        let Some(config_path) = xdg_dirs.find_config_file("config.toml") else {
+            return (settings, Vec::new());
+        };
+        let path = config_path.display().to_string();
+        let string = match fs::read_to_string(&config_path) {
+            Ok(string) => string,
+            Err(error) => return (settings, vec![ConfigProblem::new(&path, None, format!("Could not read the file: {}", error))]),
+        };
 
-                settings.merge_settings(user_settings);
-            }
-        }
+        let (user_settings, problems) = EditorSettings::load_user_settings(&string, &path);
+        settings.merge_settings(user_settings);
 
-        settings
+        (settings, problems)
     }
     pub fn create_default_config_file() -> io::Result<()> {
         let settings = EditorSettings::default();
@@ -128,8 +207,11 @@ impl EditorSettings {
         file.write_all(settings.to_string().as_bytes())
     }
 
-    fn load_user_settings(config: &str) -> EditorSettings {
-        let table = config.parse::<toml::Value>().expect("Could not parse config file");
+    fn load_user_settings(config: &str, path: &str) -> (EditorSettings, Vec<ConfigProblem>) {
+        let table = match config.parse::<toml::Value>() {
+            Ok(table) => table,
+            Err(error) => return (EditorSettings::default(), vec![ConfigProblem::from_toml_error(path, config, &error)]),
+        };
 
         let values = [
             "number_line",
@@ -139,12 +221,30 @@ impl EditorSettings {
             "font_settings",
             "default_mode",
             "pairs",
+            "sticky_scroll",
+            "search_case",
+            "key_timeout",
+            "which_key_delay",
+            "leader",
+            "keymap_preset",
         ];
 
-        match table.get("EditorSettings") {
-            None => EditorSettings::default(),
-            Some(editor_settings) => parse_settings(editor_settings, &values),
-        }
+        let Some(editor_settings) = table.get("EditorSettings") else {
+            return (EditorSettings::default(), Vec::new());
+        };
+
+        let mut problems = Vec::new();
+        let settings = parse_settings(editor_settings, &values, &mut problems);
+
+        let lines = key_lines(config);
+        let mut problems = problems.into_iter()
+            .map(|(name, message)| {
+                let line = lines.get("EditorSettings").and_then(|keys| keys.get(&name)).copied();
+                ConfigProblem::new(path, line, message)
+            })
+            .collect::<Vec<_>>();
+        problems.sort_by_key(|problem| problem.line);
+        (settings, problems)
     }
 
     fn merge_settings(&mut self, user_settings: EditorSettings) {
@@ -160,12 +260,30 @@ impl EditorSettings {
         if user_settings.rainbow_delimiters != self.rainbow_delimiters {
             self.rainbow_delimiters = user_settings.rainbow_delimiters;
         }
+        if user_settings.sticky_scroll != self.sticky_scroll {
+            self.sticky_scroll = user_settings.sticky_scroll;
+        }
+        if user_settings.search_case != SearchCase::Sensitive {
+            self.search_case = user_settings.search_case;
+        }
         if user_settings.font_settings.is_some() {
             self.font_settings = user_settings.font_settings;
         }
         if user_settings.default_mode != "Normal" {
             self.default_mode = user_settings.default_mode;
         }
+        if user_settings.key_timeout != self.key_timeout {
+            self.key_timeout = user_settings.key_timeout;
+        }
+        if user_settings.which_key_delay != self.which_key_delay {
+            self.which_key_delay = user_settings.which_key_delay;
+        }
+        if user_settings.leader != self.leader {
+            self.leader = user_settings.leader;
+        }
+        if user_settings.keymap_preset != KeymapPreset::Vim {
+            self.keymap_preset = user_settings.keymap_preset;
+        }
         self.pairs.extend(user_settings.pairs)
     }
 
@@ -184,108 +302,264 @@ impl EditorSettings {
 }
 
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct FontSettings {
-    pub size: u8,
-    pub family: String,
+/// The settings that `:set` can change while the editor is running.
+pub const OPTION_NAMES: [&str; 11] = [
+    "default_mode",
+    "key_timeout",
+    "keymap_preset",
+    "leader",
+    "number_line",
+    "rainbow_delimiters",
+    "search_case",
+    "sticky_scroll",
+    "tab_size",
+    "use_spaces",
+    "which_key_delay",
+];
+
+/// The settings that can also be changed for a single buffer with `:setlocal`.
+pub const LOCAL_OPTION_NAMES: [&str; 2] = ["tab_size", "use_spaces"];
+
+/// The settings that a single buffer has changed with `:setlocal`.
+/// A setting that is `None` follows the value in `EditorSettings`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LocalSettings {
+    pub tab_size: Option<u8>,
+    pub use_spaces: Option<bool>,
 }
 
+impl LocalSettings {
+    /// Returns the value of a setting if this buffer has changed it.
+    pub fn get_option(&self, name: &str) -> Option<String> {
+        match name {
+            "tab_size" => self.tab_size.map(|tab_size| tab_size.to_string()),
+            "use_spaces" => self.use_spaces.map(|use_spaces| use_spaces.to_string()),
+            _ => None,
+        }
+    }
 
+    /// Changes a setting for this buffer, or makes it follow the global value again when `value` is `None`.
+    pub fn set_option(&mut self, name: &str, value: Option<&str>) -> Result<(), String> {
+        match name {
+            "tab_size" => self.tab_size = value.map(parse_tab_size).transpose()?,
+            "use_spaces" => self.use_spaces = value.map(|value| parse_bool(name, value)).transpose()?,
+            _ if OPTION_NAMES.contains(&name) => return Err(format!("{} can't be set for a single buffer", name)),
+            _ => return Err(format!("Unknown setting: {}", name)),
+        }
+        Ok(())
+    }
+}
 
+impl EditorSettings {
+    /// Whether a setting is true or false, which lets `:set` turn it off with `no` and toggle it with `!`.
+    pub fn is_bool_option(name: &str) -> bool {
+        matches!(name, "rainbow_delimiters" | "use_spaces")
+    }
 
+    /// The values that `:set` accepts for a setting, written the way that `:set` shows them.
+    /// Settings that take a number or a key have none.
+    pub fn option_values(name: &str) -> &'static [&'static str] {
+        match name {
+            "default_mode" => &["Normal", "Insert"],
+            "keymap_preset" => &["Vim", "Helix", "Emacs"],
+            "number_line" => &["None", "Relative", "Absolute"],
+            "search_case" => &["Sensitive", "Insensitive", "Smart"],
+            name if Self::is_bool_option(name) => &["true", "false"],
+            _ => &[],
+        }
+    }
 
-fn parse_settings(table: &toml::Value, values: &[&str]) -> EditorSettings {
-    let number_line: NumberLineStyle;
-    let tab_size: u8;
-    let use_spaces: bool;
-    let rainbow_delimiters: bool;
-    let font_settings: Option<FontSettings>;
-    let default_mode: String;
-
-    if let Some(number_line_str) = table.get(values[0]) {
-        number_line = match number_line_str.as_str().unwrap() {
-            "None" => NumberLineStyle::None,
-            "Relative" => NumberLineStyle::Relative,
-            "Absolute" => NumberLineStyle::Absolute,
-            _ => panic!("Invalid number line style"),
-        };
-    } else {
-        number_line = NumberLineStyle::None;
+    /// Returns the value of a setting the way that `:set` shows it.
+    pub fn get_option(&self, name: &str) -> Result<String, String> {
+        match name {
+            "default_mode" => Ok(self.default_mode.clone()),
+            "key_timeout" => Ok(self.key_timeout.to_string()),
+            "keymap_preset" => Ok(self.keymap_preset.to_string()),
+            "leader" => Ok(self.leader.clone()),
+            "number_line" => Ok(self.number_line.to_string()),
+            "rainbow_delimiters" => Ok(self.rainbow_delimiters.to_string()),
+            "search_case" => Ok(self.search_case.to_string()),
+            "sticky_scroll" => Ok(self.sticky_scroll.to_string()),
+            "tab_size" => Ok(self.tab_size.to_string()),
+            "use_spaces" => Ok(self.use_spaces.to_string()),
+            "which_key_delay" => Ok(self.which_key_delay.to_string()),
+            _ => Err(format!("Unknown setting: {}", name)),
+        }
     }
 
-    if let Some(tab_size_str) = table.get(values[1]) {
-        tab_size = tab_size_str.as_integer().unwrap() as u8;
-    } else {
-        tab_size = 4;
+    /// Changes a setting from the text that was given to `:set`, leaving it unchanged if the value isn't valid.
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "default_mode" => {
+                self.default_mode = match value.to_lowercase().as_str() {
+                    "normal" => String::from("Normal"),
+                    "insert" => String::from("Insert"),
+                    _ => return Err(invalid_value(name, value)),
+                };
+            }
+            "keymap_preset" => {
+                self.keymap_preset = match value.to_lowercase().as_str() {
+                    "vim" => KeymapPreset::Vim,
+                    "helix" => KeymapPreset::Helix,
+                    "emacs" => KeymapPreset::Emacs,
+                    _ => return Err(invalid_value(name, value)),
+                };
+            }
+            "number_line" => {
+                self.number_line = match value.to_lowercase().as_str() {
+                    "none" => NumberLineStyle::None,
+                    "relative" => NumberLineStyle::Relative,
+                    "absolute" => NumberLineStyle::Absolute,
+                    _ => return Err(invalid_value(name, value)),
+                };
+            }
+            "rainbow_delimiters" => self.rainbow_delimiters = parse_bool(name, value)?,
+            "search_case" => {
+                self.search_case = match value.to_lowercase().as_str() {
+                    "sensitive" => SearchCase::Sensitive,
+                    "insensitive" => SearchCase::Insensitive,
+                    "smart" => SearchCase::Smart,
+                    _ => return Err(invalid_value(name, value)),
+                };
+            }
+            "sticky_scroll" => {
+                self.sticky_scroll = value.parse::<usize>()
+                    .map_err(|_| format!("sticky_scroll must be a number of lines, not {}", value))?;
+            }
+            "tab_size" => self.tab_size = parse_tab_size(value)?,
+            "use_spaces" => self.use_spaces = parse_bool(name, value)?,
+            "key_timeout" | "which_key_delay" => {
+                let milliseconds = value.parse::<u64>()
+                    .map_err(|_| format!("{} must be a number of milliseconds, not {}", name, value))?;
+                match name {
+                    "key_timeout" => self.key_timeout = milliseconds,
+                    _ => self.which_key_delay = milliseconds,
+                }
+            }
+            "leader" => {
+                if crate::models::key::parse_key_sequence(value).len() != 1 {
+                    return Err(format!("leader must be a single key, like \\ or <Space>, not {}", value));
+                }
+                self.leader = value.to_string();
+            }
+            _ => return Err(format!("Unknown setting: {}", name)),
+        }
+        Ok(())
     }
+}
 
-    if let Some(use_spaces_str) = table.get(values[2]) {
-        use_spaces = use_spaces_str.as_bool().unwrap();
-    } else {
-        use_spaces = true;
+fn parse_bool(name: &str, value: &str) -> Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "true" | "on" | "yes" => Ok(true),
+        "false" | "off" | "no" => Ok(false),
+        _ => Err(invalid_value(name, value)),
     }
+}
+
+/// The message for a value that isn't one of the values of `EditorSettings::option_values`.
+fn invalid_value(name: &str, value: &str) -> String {
+    let values = EditorSettings::option_values(name);
+    let values = match values.split_last() {
+        Some((last, [])) => last.to_string(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+        None => String::from("a valid value"),
+    };
+    format!("{} must be {}, not {}", name, values, value)
+}
 
-    if let Some(rainbow_delimiters_str) = table.get(values[3]) {
-        rainbow_delimiters = rainbow_delimiters_str.as_bool().unwrap();
-    } else {
-        rainbow_delimiters = false;
+/// A tab is drawn as this many columns, so it has to be at least one wide.
+fn parse_tab_size(value: &str) -> Result<u8, String> {
+    match value.parse::<u8>() {
+        Ok(tab_size @ 1..=16) => Ok(tab_size),
+        _ => Err(format!("tab_size must be a number from 1 to 16, not {}", value)),
     }
+}
 
-    if let Some(font_settings_table) = table.get(values[4]) {
-        let size: u8;
-        let family: String;
 
-        if let Some(size_str) = font_settings_table.get("size") {
-            size = size_str.as_integer().unwrap() as u8;
-        } else {
-            size = 12;
-        }
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontSettings {
+    pub size: u8,
+    pub family: String,
+}
 
-        if let Some(family_str) = font_settings_table.get("family") {
-            family = family_str.as_str().unwrap().to_string();
-        } else {
-            family = "monospace".to_string();
-        }
 
-        font_settings = Some(FontSettings {
-            size,
-            family,
-        });
-    } else {
-        font_settings = None;
-    }
 
-    if let Some(default_mode_str) = table.get(values[5]) {
-        default_mode = default_mode_str.as_str().unwrap().to_string();
-    } else {
-        default_mode = "Normal".to_string();
-    }
 
-    let pairs = if let Some(pairs_table) = table.get(values[6]) {
-        let mut pairs = HashMap::new();
 
-        for pair in pairs_table.as_array().unwrap() {
-            let pair = pair.as_array().unwrap();
-            let key = pair[0].as_str().unwrap().to_string();
-            let value = pair[1].as_str().unwrap().to_string();
+/// Reads the settings of the `[EditorSettings]` table, starting from the defaults.
+/// The settings that can't be read are left at their default and noted in `problems` by their name.
+fn parse_settings(table: &toml::Value, values: &[&str], problems: &mut Vec<(String, String)>) -> EditorSettings {
+    let mut settings = EditorSettings::default();
+    // The pairs are added to the default ones when the settings are merged
+    settings.pairs.clear();
 
-            pairs.insert(key, value);
+    let Some(table) = table.as_table() else {
+        problems.push((String::from("EditorSettings"), String::from("EditorSettings must be a table")));
+        return settings;
+    };
+
+    for (name, value) in table {
+        let result = match name.as_str() {
+            "font_settings" => parse_font_settings(value).map(|font_settings| settings.font_settings = Some(font_settings)),
+            "pairs" => parse_pairs(value).map(|pairs| settings.pairs = pairs),
+            // The rest are checked the same way that `:set` checks them
+            name if values.contains(&name) => match value {
+                toml::Value::String(value) => settings.set_option(name, value),
+                toml::Value::Integer(value) => settings.set_option(name, &value.to_string()),
+                toml::Value::Boolean(value) => settings.set_option(name, &value.to_string()),
+                _ => Err(format!("{} must be a string, a number or a boolean", name)),
+            },
+            name => Err(format!("Unknown setting: {}", name)),
+        };
+
+        if let Err(message) = result {
+            problems.push((name.clone(), message));
         }
+    }
 
-        pairs
-    } else {
-        HashMap::new()
+    // A preset can start in another mode, but an explicit default_mode still wins
+    if !table.contains_key("default_mode") {
+        settings.default_mode = String::from(settings.keymap_preset.default_mode());
+    }
+
+    settings
+}
+
+fn parse_font_settings(table: &toml::Value) -> Result<FontSettings, String> {
+    let size = match table.get("size") {
+        Some(size) => size.as_integer()
+            .filter(|size| (1..=255).contains(size))
+            .ok_or(String::from("font_settings.size must be a number from 1 to 255"))? as u8,
+        None => 12,
+    };
+
+    let family = match table.get("family") {
+        Some(family) => family.as_str().ok_or(String::from("font_settings.family must be a string"))?.to_string(),
+        None => "monospace".to_string(),
     };
 
-    EditorSettings {
-        number_line,
-        tab_size,
-        use_spaces,
-        rainbow_delimiters,
-        font_settings,
-        default_mode,
-        pairs,
+    Ok(FontSettings {
+        size,
+        family,
+    })
+}
+
+fn parse_pairs(array: &toml::Value) -> Result<HashMap<String, String>, String> {
+    let error = || String::from("pairs must be an array of pairs of strings, like [[\"(\", \")\"]]");
+    let mut pairs = HashMap::new();
+
+    for pair in array.as_array().ok_or_else(error)? {
+        match pair.as_array().map(Vec::as_slice) {
+            Some([open, close]) => {
+                let open = open.as_str().ok_or_else(error)?.to_string();
+                let close = close.as_str().ok_or_else(error)?.to_string();
+                pairs.insert(open, close);
+            }
+            _ => return Err(error()),
+        }
     }
+
+    Ok(pairs)
 }
 
 
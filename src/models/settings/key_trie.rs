@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use crate::models::key::KeyEvent;
+
+/// The bindings of a mode stored key by key, so that the keys typed so far can be told apart
+/// as a whole binding, the start of a longer binding, or neither.
+#[derive(Debug, Clone, Default)]
+pub struct KeyTrie {
+    /// The command of the binding that ends at this node
+    command: Option<String>,
+    children: HashMap<KeyEvent, KeyTrie>,
+}
+
+impl KeyTrie {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a binding, returning the command that the keys ran before.
+    pub fn insert(&mut self, keys: Vec<KeyEvent>, command: String) -> Option<String> {
+        let mut node = self;
+        for key in keys {
+            node = node.children.entry(key).or_default();
+        }
+        node.command.replace(command)
+    }
+
+    pub fn get(&self, keys: &[KeyEvent]) -> Option<&String> {
+        self.node(keys)?.command.as_ref()
+    }
+
+    pub fn contains_key(&self, keys: &[KeyEvent]) -> bool {
+        self.get(keys).is_some()
+    }
+
+    /// Removes a binding along with the nodes that no other binding goes through.
+    pub fn remove(&mut self, keys: &[KeyEvent]) -> Option<String> {
+        match keys.split_first() {
+            None => self.command.take(),
+            Some((first, rest)) => {
+                let child = self.children.get_mut(first)?;
+                let command = child.remove(rest);
+                if child.command.is_none() && child.children.is_empty() {
+                    self.children.remove(first);
+                }
+                command
+            }
+        }
+    }
+
+    /// Finds the node that the keys lead to.
+    pub fn node(&self, keys: &[KeyEvent]) -> Option<&KeyTrie> {
+        keys.iter().try_fold(self, |node, key| node.children.get(key))
+    }
+
+    pub fn command(&self) -> Option<&String> {
+        self.command.as_ref()
+    }
+
+    /// The keys that can come next, along with the node that each one leads to.
+    pub fn children(&self) -> impl Iterator<Item = (&KeyEvent, &KeyTrie)> {
+        self.children.iter()
+    }
+
+    /// Whether a longer binding starts with the keys that lead to this node.
+    pub fn has_children(&self) -> bool {
+        !self.children.is_empty()
+    }
+
+    /// Counts the bindings at this node and below it.
+    pub fn len(&self) -> usize {
+        self.command.is_some() as usize + self.children.values().map(KeyTrie::len).sum::<usize>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Lists every binding along with its keys.
+    pub fn bindings(&self) -> Vec<(Vec<KeyEvent>, &String)> {
+        let mut bindings = Vec::new();
+        self.collect_bindings(&mut Vec::new(), &mut bindings);
+        bindings
+    }
+
+    fn collect_bindings<'a>(&'a self, keys: &mut Vec<KeyEvent>, bindings: &mut Vec<(Vec<KeyEvent>, &'a String)>) {
+        if let Some(command) = &self.command {
+            bindings.push((keys.clone(), command));
+        }
+        for (key, child) in &self.children {
+            keys.push(*key);
+            child.collect_bindings(keys, bindings);
+            keys.pop();
+        }
+    }
+}
+
+impl FromIterator<(Vec<KeyEvent>, String)> for KeyTrie {
+    fn from_iter<T: IntoIterator<Item = (Vec<KeyEvent>, String)>>(bindings: T) -> Self {
+        let mut trie = KeyTrie::new();
+        for (keys, command) in bindings {
+            trie.insert(keys, command);
+        }
+        trie
+    }
+}
@@ -0,0 +1,255 @@
+//! The built-in bindings that `keymap_preset` picks between, other than the Vim-like ones that
+//! `ModeKeybindings::default` makes. Keys are written the way that `:bind` takes them.
+
+use std::collections::HashMap;
+
+use crate::models::key::{parse_key_sequence, KeyEvent};
+use crate::models::settings::editor_settings::KeymapPreset;
+
+
+/// Bindings written as the keys and the command that they run.
+type Table = &'static [(&'static str, &'static str)];
+
+/// Normal mode selects first, so the motions go to Selection mode where the operators act on what they moved over.
+const HELIX_NORMAL: Table = &[
+    ("h", "left"),
+    ("<Left>", "left"),
+    ("j", "down"),
+    ("<Down>", "down"),
+    ("k", "up"),
+    ("<Up>", "up"),
+    ("l", "right"),
+    ("<Right>", "right"),
+    ("w", "select_next_word_front"),
+    ("e", "select_next_word_back"),
+    ("b", "select_previous_word_front"),
+    ("W", "next_word_front"),
+    ("E", "next_word_back"),
+    ("B", "previous_word_front"),
+    ("x", "selection_mode_line"),
+    ("v", "selection_mode"),
+    ("<C-v>", "selection_mode_block"),
+    ("gg", "start_of_file"),
+    ("ge", "end_of_file"),
+    ("gh", "start_of_line"),
+    ("gl", "end_of_line"),
+    ("G", "goto_line"),
+    ("mm", "goto_pair"),
+    ("i", "insert_before"),
+    ("a", "insert_after"),
+    ("I", "insert_start_of_line"),
+    ("A", "insert_end_of_line"),
+    ("o", "insert_below"),
+    ("O", "insert_above"),
+    ("d", "delete_char"),
+    ("c", "change_char"),
+    ("y", "copy_char"),
+    ("p", "paste_after"),
+    ("P", "paste_before"),
+    ("u", "undo"),
+    ("U", "redo"),
+    ("R", "replace_mode"),
+    ("/", "search_mode_down"),
+    ("?", "search_mode_up"),
+    ("n", "search_next"),
+    ("N", "search_previous"),
+    ("*", "search_word_forward"),
+    (":", "command_mode"),
+    ("=", "reindent_line"),
+    ("<C-c>", "toggle_comment"),
+    ("]d", "next_syntax_error"),
+    ("[d", "prev_syntax_error"),
+    ("za", "toggle_fold"),
+    ("zo", "open_fold"),
+    ("zc", "close_fold"),
+    ("zR", "open_all_folds"),
+    ("zM", "close_all_folds"),
+    ("<Space>s", "open_symbols"),
+    ("<Space>o", "toggle_outline"),
+];
+
+/// Every motion extends the selection, and the operators end it.
+const HELIX_SELECTION: Table = &[
+    ("h", "left"),
+    ("<Left>", "left"),
+    ("j", "down"),
+    ("<Down>", "down"),
+    ("k", "up"),
+    ("<Up>", "up"),
+    ("l", "right"),
+    ("<Right>", "right"),
+    ("w", "next_word_front"),
+    ("e", "next_word_back"),
+    ("b", "previous_word_front"),
+    ("x", "down"),
+    ("gg", "start_of_file"),
+    ("ge", "end_of_file"),
+    ("gh", "start_of_line"),
+    ("gl", "end_of_line"),
+    ("mm", "goto_pair"),
+    ("d", "delete"),
+    ("c", "change_selection"),
+    ("y", "copy"),
+    ("p", "paste"),
+    ("=", "reindent"),
+    ("<C-c>", "toggle_comment"),
+    ("*", "search_selection_forward"),
+    (":", "command_mode"),
+    ("<M-o>", "expand_selection"),
+    ("<M-i>", "shrink_selection"),
+    ("ms", "pair_mode"),
+    ("C", "mirror_mode"),
+    (";", "cancel"),
+    ("v", "cancel"),
+];
+
+const HELIX_ALIASES: &[(&str, &[&str])] = &[
+    ("change_char", &["delete char", "change_mode Insert"]),
+    ("change_selection", &["copy selection", "delete selection", "clear_selection", "change_mode Insert"]),
+];
+
+/// Typing goes before the cursor, so moving forward a word ends just past its last letter.
+const EMACS_ALIASES: &[(&str, &[&str])] = &[
+    ("forward_word", &["move next_word_back", "move right 1"]),
+];
+
+/// Text is typed straight away, so everything else is a chord.
+const EMACS_INSERT: Table = &[
+    ("<Backspace>", "backspace"),
+    ("<Delete>", "delete"),
+    ("<Enter>", "newline"),
+    ("<Tab>", "tab"),
+    ("<C-g>", "cancel"),
+    ("<C-f>", "right"),
+    ("<C-b>", "left"),
+    ("<C-n>", "down"),
+    ("<C-p>", "up"),
+    ("<C-a>", "start_of_line"),
+    ("<C-e>", "end_of_line"),
+    ("<M-f>", "forward_word"),
+    ("<M-b>", "previous_word_front"),
+    ("<M-lt>", "start_of_file"),
+    ("<M-gt>", "end_of_file"),
+    ("<C-v>", "page_down"),
+    ("<M-v>", "page_up"),
+    ("<C-d>", "delete"),
+    ("<C-k>", "cut_to_end_line"),
+    ("<C-y>", "paste_before"),
+    ("<C-/>", "undo"),
+    ("<C-_>", "undo"),
+    ("<C-x>u", "undo"),
+    ("<C-Space>", "selection_mode"),
+    ("<C-@>", "selection_mode"),
+    ("<C-s>", "search_mode_down"),
+    ("<C-r>", "search_mode_up"),
+    ("<M-x>", "command_mode"),
+    ("<C-x><C-s>", "w"),
+    ("<C-x><C-c>", "q"),
+];
+
+/// The mark is set where the selection starts, and the motions move the point.
+const EMACS_SELECTION: Table = &[
+    ("<C-f>", "right"),
+    ("<Right>", "right"),
+    ("<C-b>", "left"),
+    ("<Left>", "left"),
+    ("<C-n>", "down"),
+    ("<Down>", "down"),
+    ("<C-p>", "up"),
+    ("<Up>", "up"),
+    ("<C-a>", "start_of_line"),
+    ("<C-e>", "end_of_line"),
+    ("<M-f>", "next_word_back"),
+    ("<M-b>", "previous_word_front"),
+    ("<M-lt>", "start_of_file"),
+    ("<M-gt>", "end_of_file"),
+    ("<C-v>", "page_down"),
+    ("<M-v>", "page_up"),
+    ("<M-w>", "copy"),
+    ("<C-w>", "cut"),
+    ("<C-y>", "paste"),
+    ("<Backspace>", "delete"),
+    ("<Delete>", "delete"),
+    ("<C-s>", "search_selection_forward"),
+    ("<C-r>", "search_selection_backward"),
+    ("<M-x>", "command_mode"),
+    ("<M-o>", "expand_selection"),
+    ("<M-i>", "shrink_selection"),
+    ("<C-Space>", "cancel"),
+    ("<C-@>", "cancel"),
+    ("<C-g>", "cancel"),
+];
+
+const EMACS_COMMAND: Table = &[
+    ("<C-g>", "cancel"),
+    ("<C-f>", "right"),
+    ("<C-b>", "left"),
+    ("<C-p>", "up"),
+    ("<C-n>", "down"),
+    ("<C-d>", "delete"),
+    ("<C-h>", "backspace"),
+];
+
+const EMACS_SEARCH: Table = &[
+    ("<C-g>", "cancel"),
+    ("<C-s>", "next_match"),
+    ("<C-r>", "previous_match"),
+];
+
+const EMACS_PICKER: Table = &[
+    ("<C-g>", "cancel"),
+    ("<C-p>", "up"),
+    ("<C-n>", "down"),
+];
+
+const EMACS_CONFIRM: Table = &[
+    ("<C-g>", "cancel"),
+];
+
+
+/// The tables that a preset uses in place of the default ones of a mode.
+pub fn replaced_tables(preset: KeymapPreset) -> Vec<(&'static str, HashMap<Vec<KeyEvent>, String>)> {
+    match preset {
+        KeymapPreset::Vim => Vec::new(),
+        KeymapPreset::Helix => vec![
+            ("Normal", parse_table(HELIX_NORMAL)),
+            ("Selection", parse_table(HELIX_SELECTION)),
+        ],
+        KeymapPreset::Emacs => vec![
+            ("Insert", parse_table(EMACS_INSERT)),
+            ("Selection", parse_table(EMACS_SELECTION)),
+        ],
+    }
+}
+
+/// The bindings that a preset adds to the default tables of a mode.
+pub fn added_bindings(preset: KeymapPreset) -> Vec<(&'static str, HashMap<Vec<KeyEvent>, String>)> {
+    match preset {
+        KeymapPreset::Vim | KeymapPreset::Helix => Vec::new(),
+        KeymapPreset::Emacs => vec![
+            ("Command", parse_table(EMACS_COMMAND)),
+            ("Search", parse_table(EMACS_SEARCH)),
+            ("Symbols", parse_table(EMACS_PICKER)),
+            ("History", parse_table(EMACS_PICKER)),
+            ("Confirm", parse_table(EMACS_CONFIRM)),
+        ],
+    }
+}
+
+/// The aliases that the bindings of a preset run, which keybindings.toml can replace.
+pub fn aliases(preset: KeymapPreset) -> HashMap<String, Vec<String>> {
+    let aliases = match preset {
+        KeymapPreset::Vim => &[],
+        KeymapPreset::Helix => HELIX_ALIASES,
+        KeymapPreset::Emacs => EMACS_ALIASES,
+    };
+    aliases.iter()
+        .map(|(name, commands)| (name.to_string(), commands.iter().map(|command| command.to_string()).collect()))
+        .collect()
+}
+
+fn parse_table(table: Table) -> HashMap<Vec<KeyEvent>, String> {
+    table.iter()
+        .map(|(keys, command)| (parse_key_sequence(keys), command.to_string()))
+        .collect()
+}
@@ -1,9 +1,13 @@
+use std::collections::HashMap;
+use std::fmt;
 use crate::models::settings::colors::EditorColors;
 use self::editor_settings::EditorSettings;
 use self::mode_keybindings::ModeKeybindings;
 
 pub mod editor_settings;
 pub mod mode_keybindings;
+pub mod key_trie;
+pub mod keymap_presets;
 pub mod colors;
 //pub mod language_formats;
 //pub mod colors;
@@ -21,19 +25,103 @@ pub struct Settings {
     /// (e.g. Kernel, Google, Microsoft, etc.)
     pub language_formats: LanguageFormats,*/
     /// The colors for the editor
-    pub colors: EditorColors
+    pub colors: EditorColors,
+    /// What was wrong with the user's configuration files, which were skipped over while loading them
+    pub problems: Vec<ConfigProblem>,
 }
 
 
+/// A problem found in one of the user's configuration files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigProblem {
+    /// The path of the file
+    pub file: String,
+    /// The line that the problem is on, when it is known
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl ConfigProblem {
+    pub fn new(file: &str, line: Option<usize>, message: impl Into<String>) -> Self {
+        ConfigProblem {
+            file: file.to_string(),
+            line,
+            message: message.into(),
+        }
+    }
+
+    /// Turns an error from the toml parser into a problem, which says where it happened.
+    pub fn from_toml_error(file: &str, text: &str, error: &toml::de::Error) -> Self {
+        let line = error.span().map(|span| line_at(text, span.start));
+        // The parser explains some errors over several lines, but a problem is shown on one line
+        ConfigProblem::new(file, line, error.message().trim().replace('\n', ", "))
+    }
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.file, line, self.message),
+            None => write!(f, "{}: {}", self.file, self.message),
+        }
+    }
+}
+
+/// The line number, starting at 1, of a byte in a file.
+pub fn line_at(text: &str, byte: usize) -> usize {
+    text[..byte.min(text.len())].matches('\n').count() + 1
+}
+
+/// Finds the line of each key in the tables of a toml file, by table and then by key.
+/// A file that doesn't parse, or that has values outside of tables, has no lines.
+pub fn key_lines(text: &str) -> HashMap<String, HashMap<String, usize>> {
+    let tables: HashMap<String, HashMap<String, toml::Spanned<toml::Value>>> = toml::from_str(text).unwrap_or_default();
+    tables.into_iter()
+        .map(|(table, keys)| {
+            let keys = keys.into_iter()
+                .map(|(key, value)| (key, line_at(text, value.span().start)))
+                .collect();
+            (table, keys)
+        })
+        .collect()
+}
+
+/// Checks every configuration file of the user, including bindings that conflict with or shadow each other.
+pub fn check_config() -> Vec<ConfigProblem> {
+    let (editor_settings, mut problems) = EditorSettings::load();
+    problems.extend(ModeKeybindings::check_config(&editor_settings.leader, editor_settings.keymap_preset));
+    problems.extend(EditorColors::load().1);
+    problems
+}
+
 
 
+
+impl Settings {
+    /// What to tell the user at startup about the problems in their configuration, if there are any.
+    pub fn problems_message(&self) -> Option<String> {
+        match self.problems.as_slice() {
+            [] => None,
+            [problem] => Some(problem.to_string()),
+            problems => Some(format!("{} problems in the configuration, run `sevi --check-config` to list them", problems.len())),
+        }
+    }
+}
+
 impl Default for Settings {
     fn default() -> Self {
+        let (editor_settings, mut problems) = EditorSettings::load();
+        let (mode_keybindings, keybinding_problems) = ModeKeybindings::load(&editor_settings.leader, editor_settings.keymap_preset);
+        let (colors, color_problems) = EditorColors::load();
+        problems.extend(keybinding_problems);
+        problems.extend(color_problems);
+
         Settings {
-            editor_settings: EditorSettings::new(),
-            mode_keybindings: ModeKeybindings::new(),
+            editor_settings,
+            mode_keybindings,
             /*language_formats: LanguageFormats::default(),*/
-            colors: EditorColors::new(),
+            colors,
+            problems,
         }
     }
 }
@@ -1,11 +1,16 @@
 
 use std::collections::HashMap;
-use std::fs::File;
+use std::fs::{self, File};
 use std::{fmt, io};
 use std::fmt::Formatter;
-use std::io::{Read, Write};
+use std::io::Write;
+use serde::Deserialize;
 
-use crate::models::key::{Key, key_event_to_string, KeyEvent, KeyModifiers};
+use crate::models::key::{expand_leader, Key, key_event_to_string, key_sequence_to_string, KeyEvent, KeyModifiers, parse_key_sequence};
+use crate::models::command_registry;
+use crate::models::settings::{key_lines, keymap_presets, line_at, ConfigProblem};
+use crate::models::settings::editor_settings::KeymapPreset;
+use crate::models::settings::key_trie::KeyTrie;
 
 
 impl fmt::Display for ModeKeybindings {
@@ -13,8 +18,8 @@ impl fmt::Display for ModeKeybindings {
         write!(f, "[Universal]\n")?;
 
         {
-            let mut grouped_bindings: HashMap<&String, Vec<&Vec<KeyEvent>>> = HashMap::new();
-            for (keys, command) in &self.universal_bindings {
+            let mut grouped_bindings: HashMap<&String, Vec<Vec<KeyEvent>>> = HashMap::new();
+            for (keys, command) in self.universal_bindings.bindings() {
                 match grouped_bindings.get_mut(command) {
                     Some(keys_vec) => {
                         keys_vec.push(keys);
@@ -33,7 +38,7 @@ impl fmt::Display for ModeKeybindings {
                 } else if keys_vec.len() > 1 {
                     write!(f, "[")?;
 
-                    let output = keys_vec.iter().map(|keys| keys_to_string(keys)).collect::<Vec<String>>().join(", ");
+                    let output = keys_vec.iter().map(keys_to_string).collect::<Vec<String>>().join(", ");
 
                     write!(f, "{}]\n", output)?;
                 }
@@ -46,9 +51,9 @@ impl fmt::Display for ModeKeybindings {
         for (mode, bindings) in &self.bindings {
             write!(f, "\n[{}]\n", mode)?;
 
-            let mut grouped_bindings: HashMap<&String, Vec<&Vec<KeyEvent>>> = HashMap::new();
+            let mut grouped_bindings: HashMap<&String, Vec<Vec<KeyEvent>>> = HashMap::new();
 
-            for (keys, command) in bindings {
+            for (keys, command) in bindings.bindings() {
                 match grouped_bindings.get_mut(command) {
                     Some(keys_vec) => {
                         keys_vec.push(keys);
@@ -69,13 +74,21 @@ impl fmt::Display for ModeKeybindings {
                 } else if keys_vec.len() > 1 {
                     write!(f, "[")?;
 
-                    let output = keys_vec.iter().map(|keys| keys_to_string(keys)).collect::<Vec<String>>().join(", ");
+                    let output = keys_vec.iter().map(keys_to_string).collect::<Vec<String>>().join(", ");
 
 
                     write!(f, "{}]\n", output)?;
                 }
             }
         }
+
+        if !self.aliases.is_empty() {
+            write!(f, "\n[Aliases]\n")?;
+            for name in self.alias_names() {
+                let commands = self.aliases[&name].iter().cloned().map(toml::Value::String).collect();
+                writeln!(f, "{} = {}", name, toml::Value::Array(commands))?;
+            }
+        }
         Ok(())
 
     }
@@ -84,10 +97,33 @@ impl fmt::Display for ModeKeybindings {
 
 pub type Keys= Vec<KeyEvent>;
 
-#[derive(Debug)]
+/// A binding as `:bindings` lists it.
+pub struct BindingEntry {
+    pub keys: Keys,
+    pub command: String,
+    /// Whether the binding is the same as the default one
+    pub default: bool,
+    /// Whether the binding comes from the bindings that every mode shares
+    pub universal: bool,
+}
+
+/// What the keys typed so far in a mode lead to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyMatch {
+    /// The command of the binding with exactly these keys
+    pub command: Option<String>,
+    /// Whether a longer binding starts with these keys
+    pub is_prefix: bool,
+}
+
+#[derive(Debug, Clone)]
 pub struct ModeKeybindings {
-    universal_bindings: HashMap<Vec<KeyEvent>, String>,
-    bindings: HashMap<String, HashMap<Vec<KeyEvent>, String>>,
+    universal_bindings: KeyTrie,
+    bindings: HashMap<String, KeyTrie>,
+    /// The commands that each alias from the `[Aliases]` table runs in order
+    aliases: HashMap<String, Vec<String>>,
+    /// The built-in bindings that these bindings started from
+    preset: KeymapPreset,
 }
 
 
@@ -95,17 +131,23 @@ impl Default for ModeKeybindings {
     fn default() -> ModeKeybindings {
         let mut bindings = HashMap::new();
 
-        bindings.insert("Normal".to_string(), ModeKeybindings::generate_normal_keybindings());
-        bindings.insert("Insert".to_string(), ModeKeybindings::generate_insert_keybindings());
-        bindings.insert("Command".to_string(), ModeKeybindings::generate_command_keybindings());
-        bindings.insert("Selection".to_string(), ModeKeybindings::generate_selection_keybindings());
-        bindings.insert("Search".to_string(), ModeKeybindings::generate_search_keybindings());
-        bindings.insert("Mirror".to_string(), ModeKeybindings::generate_mirror_keybindings());
-        bindings.insert("Pair".to_string(), ModeKeybindings::generate_pair_keybindings());
+        bindings.insert("Normal".to_string(), ModeKeybindings::generate_normal_keybindings().into_iter().collect());
+        bindings.insert("Insert".to_string(), ModeKeybindings::generate_insert_keybindings().into_iter().collect());
+        bindings.insert("Command".to_string(), ModeKeybindings::generate_command_keybindings().into_iter().collect());
+        bindings.insert("Selection".to_string(), ModeKeybindings::generate_selection_keybindings().into_iter().collect());
+        bindings.insert("Search".to_string(), ModeKeybindings::generate_search_keybindings().into_iter().collect());
+        bindings.insert("Mirror".to_string(), ModeKeybindings::generate_mirror_keybindings().into_iter().collect());
+        bindings.insert("Pair".to_string(), ModeKeybindings::generate_pair_keybindings().into_iter().collect());
+        bindings.insert("Symbols".to_string(), ModeKeybindings::generate_symbols_keybindings().into_iter().collect());
+        bindings.insert("Confirm".to_string(), ModeKeybindings::generate_confirm_keybindings().into_iter().collect());
+        // The history is a picker just like the symbols
+        bindings.insert("History".to_string(), ModeKeybindings::generate_symbols_keybindings().into_iter().collect());
 
         ModeKeybindings {
-            universal_bindings: ModeKeybindings::generate_universal_keybindings(),
+            universal_bindings: ModeKeybindings::generate_universal_keybindings().into_iter().collect(),
             bindings,
+            aliases: HashMap::new(),
+            preset: KeymapPreset::Vim,
         }
     }
 }
@@ -114,27 +156,128 @@ impl Default for ModeKeybindings {
 
 impl ModeKeybindings {
 
-    pub fn new() -> ModeKeybindings {
-        let mut bindings = ModeKeybindings::default();
+    /// The built-in bindings of a preset, which start from the default ones and replace or add to some of their tables.
+    pub fn preset(preset: KeymapPreset) -> ModeKeybindings {
+        let mut bindings = ModeKeybindings {
+            preset,
+            ..ModeKeybindings::default()
+        };
+
+        if preset == KeymapPreset::Emacs {
+            // Ctrl chords belong to the modes in Emacs, so the shared table only keeps the special keys
+            bindings.universal_bindings = bindings.universal_bindings.bindings().into_iter()
+                .filter(|(keys, _)| !keys.iter().any(|key| key.modifiers.contains(KeyModifiers::CTRL)))
+                .map(|(keys, command)| (keys, command.clone()))
+                .collect();
+        }
+        for (mode, table) in keymap_presets::replaced_tables(preset) {
+            bindings.bindings.insert(mode.to_string(), table.into_iter().collect());
+        }
+        for (mode, table) in keymap_presets::added_bindings(preset) {
+            for (keys, command) in table {
+                bindings.set(mode, keys, &command);
+            }
+        }
+        bindings.aliases = keymap_presets::aliases(preset);
+
+        bindings
+    }
+
+    /// Loads the bindings of a preset merged with the user's keybindings.toml, along with the problems found in it.
+    /// Bindings that have a problem are left out, and `<leader>` in the keys of a binding is `leader`.
+    pub fn load(leader: &str, preset: KeymapPreset) -> (ModeKeybindings, Vec<ConfigProblem>) {
+        let mut bindings = ModeKeybindings::preset(preset);
 
         if crate::arg_parser::IGNORE_USER_SETTINGS.load(std::sync::atomic::Ordering::Relaxed) {
-            return bindings;
+            return (bindings, Vec::new());
         }
 
-        let xdg_dirs = xdg::BaseDirectories::with_prefix("sevi").unwrap();
-        let user_bindings_path = xdg_dirs.place_config_file("keybindings.toml").expect("Failed to create user keybindings file");
-        match File::open(user_bindings_path) {
-            Err(_) => {},
-            Ok(mut user_bindings) => {
-                let mut string = String::new();
-                user_bindings.read_to_string(&mut string).expect("Failed to read user keybindings file");
+        match ModeKeybindings::read_user_bindings(leader, preset) {
+            Some(user_bindings) => {
+                bindings.merge_bindings(user_bindings.bindings);
+                (bindings, user_bindings.problems)
+            },
+            None => (bindings, Vec::new()),
+        }
+    }
+
+    /// Checks keybindings.toml, including the bindings that hide other bindings once they are merged with the preset.
+    /// Bindings that are the same as a binding of the preset aren't checked for that, since nothing changes about them.
+    pub fn check_config(leader: &str, preset: KeymapPreset) -> Vec<ConfigProblem> {
+        let Some(user_bindings) = ModeKeybindings::read_user_bindings(leader, preset) else {
+            return Vec::new();
+        };
+        let defaults = &user_bindings.defaults;
+        let mut merged = defaults.clone();
+        merged.merge_bindings(user_bindings.bindings.clone());
+
+        let mut modes = merged.bindings.keys().cloned().collect::<Vec<_>>();
+        modes.sort();
+
+        let mut problems = user_bindings.problems;
+        for (mode, keys, line) in &user_bindings.lines {
+            let Some(command) = user_bindings.bindings.mode_trie(mode).and_then(|trie| trie.get(keys)) else {
+                continue;
+            };
+            if command.is_empty() || defaults.mode_trie(mode).and_then(|trie| trie.get(keys)) == Some(command) {
+                continue;
+            }
+            let keys_string = key_sequence_to_string(keys);
+            let problem = |message: String| ConfigProblem::new(&user_bindings.path, Some(*line), message);
+
+            let checked_modes = if mode == "Universal" {
+                let hiding_modes = modes.iter()
+                    .filter(|other| merged.bindings[*other].get(keys).is_some_and(|other| !other.is_empty() && other != command))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if !hiding_modes.is_empty() {
+                    problems.push(problem(format!("{} in Universal is hidden by the bindings of {}", keys_string, hiding_modes.join(", "))));
+                }
+                modes.clone()
+            } else {
+                if let Some(universal) = merged.universal_bindings.get(keys).filter(|universal| !universal.is_empty() && *universal != command) {
+                    problems.push(problem(format!("{} in {} hides the Universal binding to {}", keys_string, mode, universal)));
+                }
+                vec![mode.clone()]
+            };
+
+            // A binding that another one starts with only runs once the key timeout runs out
+            let longer_modes = checked_modes.iter()
+                .filter(|other| merged.lookup(other, keys).is_prefix)
+                .cloned()
+                .collect::<Vec<_>>();
+            if !longer_modes.is_empty() {
+                problems.push(problem(format!("{} is the start of a longer binding in {}, so it waits for key_timeout", keys_string, longer_modes.join(", "))));
+            }
+            for length in 1..keys.len() {
+                let shorter_modes = checked_modes.iter()
+                    .filter(|other| merged.lookup(other, &keys[..length]).command.is_some())
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if !shorter_modes.is_empty() {
+                    problems.push(problem(format!("{} starts with {}, which is bound in {} and so waits for key_timeout", keys_string, key_sequence_to_string(&keys[..length]), shorter_modes.join(", "))));
+                }
+            }
+        }
 
-                let user_bindings = ModeKeybindings::load_user_bindings(&string);
+        problems.sort_by_key(|problem| problem.line);
+        problems
+    }
 
-                bindings.merge_bindings(user_bindings);
+    /// Reads the user's keybindings.toml, when there is one.
+    fn read_user_bindings(leader: &str, preset: KeymapPreset) -> Option<UserBindings> {
+        let xdg_dirs = xdg::BaseDirectories::with_prefix("sevi").unwrap();
+        let config_path = xdg_dirs.find_config_file("keybindings.toml")?;
+        let path = config_path.display().to_string();
+
+        match fs::read_to_string(&config_path) {
+            Ok(string) => Some(ModeKeybindings::load_user_bindings(&string, &path, leader, preset)),
+            Err(error) => {
+                let mut user_bindings = UserBindings::new(&path, preset);
+                user_bindings.problems.push(ConfigProblem::new(&path, None, format!("Could not read the file: {}", error)));
+                Some(user_bindings)
             },
         }
-        bindings
     }
 
     pub fn create_default_config_file() -> io::Result<()> {
@@ -148,31 +291,14 @@ impl ModeKeybindings {
     }
 
 
-    pub fn get(&mut self, mode: &String, keys: &Vec<KeyEvent>) -> Option<&String> {
-
-
-        match self.bindings.get(mode) {
-            Some(mode_bindings) => {
-                match mode_bindings.get(keys) {
-                    Some(command) => Some(command),
-                    None => {
-                        match self.universal_bindings.get(keys) {
-                            Some(command) => Some(command),
-                            None => None,
-                        }
-                    }
-                }
-            },
-            None => {
-                match self.universal_bindings.get(keys) {
-                    Some(command) => Some(command),
-                    None => None,
-                }
-            },
+    pub fn get(&mut self, mode: &String, keys: &[KeyEvent]) -> Option<&String> {
+        match self.bindings.get(mode).and_then(|mode_bindings| mode_bindings.get(keys)) {
+            Some(command) => Some(command),
+            None => self.universal_bindings.get(keys),
         }
     }
 
-    pub fn get_ignore_universal(&mut self, mode: &String, keys: &Vec<KeyEvent>) -> Option<&String> {
+    pub fn get_ignore_universal(&mut self, mode: &String, keys: &[KeyEvent]) -> Option<&String> {
         match self.bindings.get(mode) {
             Some(mode_bindings) => {
                 mode_bindings.get(keys)
@@ -181,6 +307,70 @@ impl ModeKeybindings {
         }
     }
 
+    /// The commands that an alias runs, or `None` when there is no alias with the name.
+    pub fn alias(&self, name: &str) -> Option<&Vec<String>> {
+        self.aliases.get(name)
+    }
+
+    /// The names of the aliases, sorted.
+    pub fn alias_names(&self) -> Vec<String> {
+        let mut names = self.aliases.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// The bindings of a mode, where `Universal` is the universal bindings.
+    fn mode_trie(&self, mode: &str) -> Option<&KeyTrie> {
+        match mode {
+            "Universal" => Some(&self.universal_bindings),
+            mode => self.bindings.get(mode),
+        }
+    }
+
+    /// Looks up the keys typed so far in a mode, where the mode's bindings shadow the universal ones.
+    /// An empty command is a binding that was removed with `:unbind!`, so it doesn't count as a binding.
+    pub fn lookup(&self, mode: &str, keys: &[KeyEvent]) -> KeyMatch {
+        let mode_node = self.bindings.get(mode).and_then(|mode_bindings| mode_bindings.node(keys));
+        let universal_node = self.universal_bindings.node(keys);
+        let command = mode_node.and_then(KeyTrie::command)
+            .or_else(|| universal_node.and_then(KeyTrie::command))
+            .filter(|command| !command.is_empty())
+            .cloned();
+        KeyMatch {
+            command,
+            is_prefix: mode_node.is_some_and(KeyTrie::has_children) || universal_node.is_some_and(KeyTrie::has_children),
+        }
+    }
+
+    /// Lists the keys that can follow `keys` in a mode, sorted by key.
+    /// Each one has the command that it runs and the number of longer bindings that start with it.
+    pub fn continuations(&self, mode: &str, keys: &[KeyEvent]) -> Vec<(KeyEvent, Option<String>, usize)> {
+        let mut next_keys = Vec::new();
+        let nodes = [self.bindings.get(mode).and_then(|mode_bindings| mode_bindings.node(keys)), self.universal_bindings.node(keys)];
+        for (key, _) in nodes.iter().flatten().flat_map(|node| node.children()) {
+            if next_keys.contains(key) {
+                continue;
+            }
+            next_keys.push(*key);
+        }
+
+        let mut continuations = next_keys.into_iter()
+            .filter_map(|key| {
+                let mut keys = keys.to_vec();
+                keys.push(key);
+                let found = self.lookup(mode, &keys);
+                let longer = nodes.iter().flatten()
+                    .filter_map(|node| node.node(&[key]))
+                    .map(|node| node.len() - node.command().is_some() as usize)
+                    .max()
+                    .unwrap_or(0);
+                (found.command.is_some() || longer > 0).then_some((key, found.command, longer))
+            })
+            .collect::<Vec<_>>();
+        continuations.sort_by_key(|(key, _, _)| key_sequence_to_string(&[*key]));
+        continuations
+    }
+
     pub fn set_universal(&mut self, keys: Vec<KeyEvent>, command: &str) {
         self.universal_bindings.insert(keys, command.to_string());
     }
@@ -191,23 +381,143 @@ impl ModeKeybindings {
                 mode_bindings.insert(keys, command.to_string());
             },
             None => {
-                let mut mode_bindings = HashMap::new();
+                let mut mode_bindings = KeyTrie::new();
                 mode_bindings.insert(keys, command.to_string());
                 self.bindings.insert(mode.to_string(), mode_bindings);
             },
         }
     }
 
+    /// Finds the name of a mode that has bindings, ignoring case.
+    /// `Universal` is the name of the bindings that every mode falls back to.
+    pub fn find_mode(&self, mode: &str) -> Option<String> {
+        if mode.eq_ignore_ascii_case("Universal") {
+            return Some(String::from("Universal"));
+        }
+        self.bindings.keys().find(|name| name.eq_ignore_ascii_case(mode)).cloned()
+    }
+
+    /// Removes a binding from a mode, returning the command that it ran.
+    pub fn remove(&mut self, mode: &str, keys: &Keys) -> Option<String> {
+        match mode {
+            "Universal" => self.universal_bindings.remove(keys),
+            mode => self.bindings.get_mut(mode)?.remove(keys),
+        }
+    }
+
+    /// Lists the bindings that are in effect in a mode, sorted by their keys.
+    /// Each binding says whether it is one of the defaults and whether it comes from the universal bindings.
+    pub fn list(&self, mode: &str) -> Vec<BindingEntry> {
+        let defaults = ModeKeybindings::preset(self.preset);
+        let empty = KeyTrie::new();
+        let (mode_bindings, default_bindings) = match mode {
+            "Universal" => (&empty, &empty),
+            mode => (self.bindings.get(mode).unwrap_or(&empty), defaults.bindings.get(mode).unwrap_or(&empty)),
+        };
+
+        let mut entries = mode_bindings.bindings().into_iter()
+            .map(|(keys, command)| {
+                let default = default_bindings.get(&keys) == Some(command);
+                (keys, command, default, false)
+            })
+            .chain(self.universal_bindings.bindings().into_iter()
+                .filter(|(keys, _)| !mode_bindings.contains_key(keys))
+                .map(|(keys, command)| {
+                    let default = defaults.universal_bindings.get(&keys) == Some(command);
+                    (keys, command, default, true)
+                }))
+            // An empty command is a binding that was removed with `:unbind!`
+            .filter(|(_, command, _, _)| !command.is_empty())
+            .map(|(keys, command, default, universal)| BindingEntry {
+                keys,
+                command: command.clone(),
+                default,
+                universal,
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|entry| key_sequence_to_string(&entry.keys));
+        entries
+    }
+
+    /// Adds a binding to `keybindings.toml`, or with no command removes it from there.
+    /// Removing a binding of the preset saves it with an empty command so that it stays removed.
+    pub fn save_binding(&self, mode: &str, keys: &Keys, command: Option<&str>, leader: &str) -> Result<(), String> {
+        if crate::arg_parser::IGNORE_USER_SETTINGS.load(std::sync::atomic::Ordering::Relaxed) {
+            return Err(String::from("User settings are being ignored"));
+        }
+
+        let xdg_dirs = xdg::BaseDirectories::with_prefix("sevi").map_err(|error| error.to_string())?;
+        let path = xdg_dirs.place_config_file("keybindings.toml").map_err(|error| error.to_string())?;
+        let text = std::fs::read_to_string(&path).unwrap_or_default();
+        // The file is edited in place so that the user's comments and layout are kept
+        let mut document = text.parse::<toml_edit::DocumentMut>()
+            .map_err(|error| format!("Could not read {}: {}", path.display(), error))?;
+
+        let mode_item = document.entry(mode).or_insert_with(|| {
+            let mut table = toml_edit::Table::new();
+            table.set_implicit(true);
+            toml_edit::Item::Table(table)
+        });
+        let is_table = mode_item.is_table();
+        let mode_table = mode_item.as_table_like_mut()
+            .ok_or(format!("{} in keybindings.toml is not a table", mode))?;
+        let custom = mode_table.entry("custom").or_insert(match is_table {
+            true => toml_edit::Item::ArrayOfTables(toml_edit::ArrayOfTables::new()),
+            false => toml_edit::Item::Value(toml_edit::Value::Array(toml_edit::Array::new())),
+        });
+
+        let binds_keys = |binding: Option<&toml_edit::Value>| binding
+            .and_then(|binding| {
+                let mut binding = binding.clone();
+                binding.decor_mut().clear();
+                toml::Value::deserialize(toml::de::ValueDeserializer::new(&binding.to_string())).ok()
+            })
+            .is_some_and(|binding| parse_keys(&binding, leader).is_ok_and(|bindings| bindings.contains(keys)));
+
+        let defaults = ModeKeybindings::preset(self.preset);
+        let is_default = match mode {
+            "Universal" => defaults.universal_bindings.contains_key(keys),
+            mode => defaults.bindings.get(mode).is_some_and(|bindings| bindings.contains_key(keys)),
+        };
+        let binding = (command.is_some() || is_default).then(|| {
+            let mut binding = toml_edit::InlineTable::new();
+            binding.insert("binding", keys_to_value(keys));
+            binding.insert("command", command.unwrap_or("").into());
+            binding
+        });
+
+        match custom {
+            toml_edit::Item::ArrayOfTables(custom) => {
+                custom.retain(|table| !binds_keys(table.get("binding").and_then(toml_edit::Item::as_value)));
+                if let Some(binding) = binding {
+                    custom.push(binding.into_table());
+                }
+            },
+            toml_edit::Item::Value(toml_edit::Value::Array(custom)) => {
+                custom.retain(|value| !binds_keys(value.as_inline_table().and_then(|table| table.get("binding"))));
+                if let Some(binding) = binding {
+                    custom.push(binding);
+                }
+            },
+            _ => return Err(format!("custom in the {} table of keybindings.toml is not an array", mode)),
+        }
+
+        let text = document.to_string();
+        std::fs::write(&path, text).map_err(|error| format!("Could not write {}: {}", path.display(), error))
+    }
+
     fn merge_bindings(&mut self, other: Self) {
-        for (keys, command) in other.universal_bindings {
-            self.universal_bindings.insert(keys, command);
+        self.aliases.extend(other.aliases);
+
+        for (keys, command) in other.universal_bindings.bindings() {
+            self.universal_bindings.insert(keys, command.clone());
         }
 
         for (mode, bindings) in other.bindings {
             match self.bindings.get_mut(&mode) {
                 Some(mode_bindings) => {
-                    for (keys, command) in bindings {
-                        mode_bindings.insert(keys, command);
+                    for (keys, command) in bindings.bindings() {
+                        mode_bindings.insert(keys, command.clone());
                     }
                 },
                 None => {
@@ -1105,6 +1415,163 @@ impl ModeKeybindings {
             }], "replace".to_string());
         }
 
+        // Indentation
+        {
+            // Re-indent Line
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('='),
+                modifiers: KeyModifiers::NONE,
+            }, KeyEvent {
+                key: Key::Char('='),
+                modifiers: KeyModifiers::NONE,
+            }], "reindent_line".to_string());
+            // Re-indent File
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('='),
+                modifiers: KeyModifiers::NONE,
+            }, KeyEvent {
+                key: Key::Char('a'),
+                modifiers: KeyModifiers::NONE,
+            }], "reindent_file".to_string());
+        }
+
+        // Comments
+        {
+            // Toggle Comment
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('g'),
+                modifiers: KeyModifiers::NONE,
+            }, KeyEvent {
+                key: Key::Char('c'),
+                modifiers: KeyModifiers::NONE,
+            }, KeyEvent {
+                key: Key::Char('c'),
+                modifiers: KeyModifiers::NONE,
+            }], "toggle_comment".to_string());
+        }
+
+        // Symbols
+        {
+            // Symbol Picker
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('g'),
+                modifiers: KeyModifiers::NONE,
+            }, KeyEvent {
+                key: Key::Char('s'),
+                modifiers: KeyModifiers::NONE,
+            }], "open_symbols".to_string());
+            // Outline
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('g'),
+                modifiers: KeyModifiers::NONE,
+            }, KeyEvent {
+                key: Key::Char('O'),
+                modifiers: KeyModifiers::NONE,
+            }], "toggle_outline".to_string());
+        }
+
+        // Syntax Errors
+        {
+            // Next Syntax Error
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char(']'),
+                modifiers: KeyModifiers::NONE,
+            }, KeyEvent {
+                key: Key::Char('e'),
+                modifiers: KeyModifiers::NONE,
+            }], "next_syntax_error".to_string());
+            // Previous Syntax Error
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('['),
+                modifiers: KeyModifiers::NONE,
+            }, KeyEvent {
+                key: Key::Char('e'),
+                modifiers: KeyModifiers::NONE,
+            }], "prev_syntax_error".to_string());
+        }
+
+        // Folding
+        {
+            // Toggle Fold
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('z'),
+                modifiers: KeyModifiers::NONE,
+            }, KeyEvent {
+                key: Key::Char('a'),
+                modifiers: KeyModifiers::NONE,
+            }], "toggle_fold".to_string());
+            // Open Fold
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('z'),
+                modifiers: KeyModifiers::NONE,
+            }, KeyEvent {
+                key: Key::Char('o'),
+                modifiers: KeyModifiers::NONE,
+            }], "open_fold".to_string());
+            // Close Fold
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('z'),
+                modifiers: KeyModifiers::NONE,
+            }, KeyEvent {
+                key: Key::Char('c'),
+                modifiers: KeyModifiers::NONE,
+            }], "close_fold".to_string());
+            // Open All Folds
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('z'),
+                modifiers: KeyModifiers::NONE,
+            }, KeyEvent {
+                key: Key::Char('R'),
+                modifiers: KeyModifiers::NONE,
+            }], "open_all_folds".to_string());
+            // Close All Folds
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('z'),
+                modifiers: KeyModifiers::NONE,
+            }, KeyEvent {
+                key: Key::Char('M'),
+                modifiers: KeyModifiers::NONE,
+            }], "close_all_folds".to_string());
+        }
+
+        // Repeat Search
+        {
+            // Next Match
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('n'),
+                modifiers: KeyModifiers::NONE,
+            }], "search_next".to_string());
+            // Previous Match
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('N'),
+                modifiers: KeyModifiers::NONE,
+            }], "search_previous".to_string());
+            // Search for the Word under the Cursor
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('*'),
+                modifiers: KeyModifiers::NONE,
+            }], "search_word_forward".to_string());
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('#'),
+                modifiers: KeyModifiers::NONE,
+            }], "search_word_backward".to_string());
+            // Search for Text Containing the Word under the Cursor
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('g'),
+                modifiers: KeyModifiers::NONE,
+            }, KeyEvent {
+                key: Key::Char('*'),
+                modifiers: KeyModifiers::NONE,
+            }], "search_partial_word_forward".to_string());
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('g'),
+                modifiers: KeyModifiers::NONE,
+            }, KeyEvent {
+                key: Key::Char('#'),
+                modifiers: KeyModifiers::NONE,
+            }], "search_partial_word_backward".to_string());
+        }
+
         
 
         bindings
@@ -1196,6 +1663,18 @@ impl ModeKeybindings {
                 modifiers: KeyModifiers::NONE,
             }], "delete".to_string());
         }
+        // Complete the word before the cursor
+        {
+            bindings.insert(vec![KeyEvent {
+                key: Key::Tab,
+                modifiers: KeyModifiers::NONE,
+            }], "complete".to_string());
+
+            bindings.insert(vec![KeyEvent {
+                key: Key::BackTab,
+                modifiers: KeyModifiers::SHIFT,
+            }], "complete_previous".to_string());
+        }
     
         bindings
     }
@@ -1418,6 +1897,55 @@ impl ModeKeybindings {
 
             }
         }
+        // Re-indent Selection
+        {
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('='),
+                modifiers: KeyModifiers::NONE,
+            }], "reindent".to_string());
+        }
+        // Toggle Comment on Selection
+        {
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('g'),
+                modifiers: KeyModifiers::NONE,
+            }, KeyEvent {
+                key: Key::Char('c'),
+                modifiers: KeyModifiers::NONE,
+            }], "toggle_comment".to_string());
+        }
+        // Command Mode on the Selection
+        {
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char(':'),
+                modifiers: KeyModifiers::NONE,
+            }], "command_mode".to_string());
+        }
+        // Search for the Selected Text
+        {
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('*'),
+                modifiers: KeyModifiers::NONE,
+            }], "search_selection_forward".to_string());
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('#'),
+                modifiers: KeyModifiers::NONE,
+            }], "search_selection_backward".to_string());
+        }
+        // Syntax Tree Selection
+        {
+            // Expand Selection
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('o'),
+                modifiers: KeyModifiers::ALT,
+            }], "expand_selection".to_string());
+
+            // Shrink Selection
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('i'),
+                modifiers: KeyModifiers::ALT,
+            }], "shrink_selection".to_string());
+        }
         // Mode change
         {
             // Enter Mirror Mode
@@ -1452,10 +1980,6 @@ impl ModeKeybindings {
                     modifiers: KeyModifiers::CTRL,
                 }], "next_match".to_string());
 
-                bindings.insert(vec![KeyEvent {
-                    key: Key::Enter,
-                    modifiers: KeyModifiers::NONE,
-                }], "next_match".to_string());
                 bindings.insert(vec![KeyEvent {
                     key: Key::Char('l'),
                     modifiers: KeyModifiers::CTRL,
@@ -1499,6 +2023,13 @@ impl ModeKeybindings {
                 modifiers: KeyModifiers::NONE,
             }], "cancel".to_string());
         }
+        // Accept the match and leave the highlighting
+        {
+            bindings.insert(vec![KeyEvent {
+                key: Key::Enter,
+                modifiers: KeyModifiers::NONE,
+            }], "accept".to_string());
+        }
         // Backspace and Delete
         {
             bindings.insert(vec![KeyEvent {
@@ -1544,6 +2075,20 @@ impl ModeKeybindings {
                 }], "delete_search".to_string());
             }
         }
+        // Search options
+        {
+            // Cycle between case sensitive, case insensitive and smart case
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('c'),
+                modifiers: KeyModifiers::ALT,
+            }], "toggle_case".to_string());
+
+            // Only match whole words
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('w'),
+                modifiers: KeyModifiers::ALT,
+            }], "toggle_whole_word".to_string());
+        }
         // Mode change
         {
             // Enter Mirror Mode
@@ -1650,178 +2195,298 @@ impl ModeKeybindings {
         bindings
     }
 
-    fn load_user_bindings(file_string: &str) -> ModeKeybindings {
-        let table: toml::Value = toml::from_str(file_string).expect("failed to parse user keybindings");
-
-        let possible_bindings = [
-            "right",
-            "left",
-            "up",
-            "down",
-            "start_of_file",
-            "end_of_file",
-            "page_up",
-            "page_down",
-            "half_page_up",
-            "half_page_down",
-            "split_horizontal",
-            "split_vertical",
-            "pane_left",
-            "pane_right",
-            "pane_up",
-            "pane_down",
-            "new_tab",
-            "new_tab_current_pane",
-            "tab_left",
-            "tab_right",
-            "jump_forwards",
-            "jump_backwards",
-            "cancel",
-            "start_of_line",
-            "end_of_line",
-            "up_line_start",
-            "down_line_start",
-            "goto_line",
-            "next_word_front",
-            "next_word_back",
-            "previous_word_front",
-            "previous_word_back",
-            "goto_pair",
-            "jump_paragraph",
-            "jump_paragraph_back",
-            "insert_before",
-            "insert_after",
-            "insert_start",
-            "insert_end",
-            "insert_below",
-            "insert_above",
-            "command_mode",
-            "selection_mode",
-            "selection_mode_line",
-            "selection_mode_block",
-            "search_mode_down",
-            "search_mode_up",
-            "replace_mode",
-            "paste_before",
-            "paste_after",
-            "copy_char",
-            "copy_line",
-            "copy_word",
-            "copy_to_next_word",
-            "copy_to_prev_word",
-            "copy_to_end_line",
-            "copy_to_start_line",
-            "cut_char",
-            "cut_line",
-            "cut_word",
-            "cut_to_next_word",
-            "cut_to_prev_word",
-            "cut_to_end_line",
-            "cut_to_start_line",
-            "delete_char",
-            "delete_line",
-            "delete_word",
-            "delete_to_next_word",
-            "delete_to_prev_word",
-            "delete_to_end_line",
-            "delete_to_start_line",
-            "open_completion",
-            "undo",
-            "redo",
-            "replace",
-            "backspace",
-            "delete",
-            "newline",
-            "tab",
-            "start",
-            "end",
-            "execute",
-            "paste",
-            "copy",
-            "cut",
-            "next_match",
-            "previous_match",
-            "delete_search",
-        ];
-
-        let universal_bindings = match table.get("Universal") {
-            Some(value) => {
-                parse_keybindings(value, &possible_bindings)
-            },
-            None => {
-                HashMap::new()
-            },
-        };
+    fn generate_symbols_keybindings() -> HashMap<Vec<KeyEvent>, String> {
+        let mut bindings = HashMap::new();
 
-        let normal_bindings= match table.get("Normal") {
-            Some(value) => {
-                parse_keybindings(value, &possible_bindings)
-            },
-            None => {
-                HashMap::new()
-            },
-        };
+        // Move through the symbols
+        {
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('n'),
+                modifiers: KeyModifiers::CTRL,
+            }], "down".to_string());
 
-        let insert_bindings = match table.get("Insert") {
-            Some(value) => {
-                parse_keybindings(value, &possible_bindings)
-            },
-            None => {
-                HashMap::new()
-            },
-        };
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('p'),
+                modifiers: KeyModifiers::CTRL,
+            }], "up".to_string());
 
-        let command_bindings = match table.get("Command") {
-            Some(value) => {
-                parse_keybindings(value, &possible_bindings)
-            },
-            None => {
-                HashMap::new()
-            },
-        };
+            bindings.insert(vec![KeyEvent {
+                key: Key::Tab,
+                modifiers: KeyModifiers::NONE,
+            }], "down".to_string());
+        }
+        // Jump to Symbol
+        {
+            bindings.insert(vec![KeyEvent {
+                key: Key::Enter,
+                modifiers: KeyModifiers::NONE,
+            }], "execute".to_string());
+        }
+        // Backspace and delete
+        {
+            bindings.insert(vec![KeyEvent {
+                key: Key::Backspace,
+                modifiers: KeyModifiers::NONE,
+            }], "backspace".to_string());
 
-        let selection_bindings = match table.get("Selection") {
-            Some(value) => {
-                parse_keybindings(value, &possible_bindings)
-            },
-            None => {
-                HashMap::new()
+            bindings.insert(vec![KeyEvent {
+                key: Key::Delete,
+                modifiers: KeyModifiers::NONE,
+            }], "delete".to_string());
+        }
+
+        bindings
+    }
+
+    fn generate_confirm_keybindings() -> HashMap<Vec<KeyEvent>, String> {
+        let mut bindings = HashMap::new();
+
+        // Answers
+        {
+            // Replace this match
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('y'),
+                modifiers: KeyModifiers::NONE,
+            }], "yes".to_string());
+
+            // Skip this match
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('n'),
+                modifiers: KeyModifiers::NONE,
+            }], "no".to_string());
+
+            // Replace this match and all of the rest
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('a'),
+                modifiers: KeyModifiers::NONE,
+            }], "all".to_string());
+
+            // Stop and keep the replacements made so far
+            bindings.insert(vec![KeyEvent {
+                key: Key::Char('q'),
+                modifiers: KeyModifiers::NONE,
+            }], "quit".to_string());
+        }
+        // Escape
+        {
+            bindings.insert(vec![KeyEvent {
+                key: Key::Esc,
+                modifiers: KeyModifiers::NONE,
+            }], "cancel".to_string());
+        }
+
+        bindings
+    }
+
+    fn load_user_bindings(file_string: &str, path: &str, leader: &str, preset: KeymapPreset) -> UserBindings {
+        let mut user_bindings = UserBindings::new(path, preset);
+
+        let table = match toml::from_str::<toml::value::Table>(file_string) {
+            Ok(table) => table,
+            Err(error) => {
+                user_bindings.problems.push(ConfigProblem::from_toml_error(path, file_string, &error));
+                return user_bindings;
             },
         };
+        let lines = key_lines(file_string);
+        let custom_lines: HashMap<String, CustomLines> = toml::from_str(file_string).unwrap_or_default();
 
-        let search_bindings = match table.get("Search") {
-            Some(value) => {
-                parse_keybindings(value, &possible_bindings)
-            },
-            None => {
-                HashMap::new()
+        // The aliases come first so that the bindings can run them
+        if let Some(aliases) = table.get("Aliases") {
+            user_bindings.add_aliases(aliases, lines.get("Aliases"));
+        }
+
+        let mut modes = table.keys().filter(|mode| *mode != "Aliases").collect::<Vec<_>>();
+        modes.sort_by_key(|mode| lines.values().find_map(|keys| keys.get(*mode)).copied());
+        for mode in modes {
+            let value = &table[mode];
+            let line = lines.values().find_map(|keys| keys.get(mode)).copied()
+                .or_else(|| file_string.lines().position(|text| text.trim() == format!("[{}]", mode)).map(|index| index + 1));
+            if mode != "Universal" && command_registry::mode_commands(mode).is_none() {
+                user_bindings.problems.push(ConfigProblem::new(path, line, format!("Unknown mode: {}", mode)));
+                continue;
+            }
+            let Some(mode_table) = value.as_table() else {
+                user_bindings.problems.push(ConfigProblem::new(path, line, format!("{} must be a table of bindings", mode)));
+                continue;
+            };
+            let key_line = |name: &str| lines.get(mode).and_then(|keys| keys.get(name)).copied();
+
+            let mut names = mode_table.keys().filter(|name| *name != "custom").collect::<Vec<_>>();
+            names.sort_by_key(|name| key_line(name));
+            for name in names {
+                let line = key_line(name);
+                let is_default_command = user_bindings.defaults.mode_trie(mode)
+                    .is_some_and(|defaults| defaults.bindings().iter().any(|(_, command)| *command == name));
+                if !command_registry::binding_names().contains(&name.as_str()) && !is_default_command {
+                    user_bindings.problems.push(ConfigProblem::new(path, line, format!("Unknown binding: {}", name)));
+                    continue;
+                }
+                match parse_keys(&mode_table[name], leader) {
+                    Ok(keys) => for keys in keys {
+                        user_bindings.add(mode, keys, name, line);
+                    },
+                    Err(message) => user_bindings.problems.push(ConfigProblem::new(path, line, format!("{}: {}", name, message))),
+                }
+            }
+
+            if let Some(custom) = mode_table.get("custom") {
+                let Some(custom) = custom.as_array() else {
+                    user_bindings.problems.push(ConfigProblem::new(path, key_line("custom"), "custom must be an array of bindings"));
+                    continue;
+                };
+                let element_lines = custom_lines.get(mode).map(|custom| custom.custom.as_slice()).unwrap_or_default();
+                for (index, value) in custom.iter().enumerate() {
+                    let line = element_lines.get(index).map(|value| line_at(file_string, value.span().start)).or(key_line("custom"));
+                    match parse_custom_binding(value, leader) {
+                        Ok((keys, command)) => user_bindings.add(mode, keys, &command, line),
+                        Err(message) => user_bindings.problems.push(ConfigProblem::new(path, line, message)),
+                    }
+                }
+            }
+        }
+
+        user_bindings.problems.sort_by_key(|problem| problem.line);
+        user_bindings
+    }
+
+}
+
+
+/// Bindings read from a user's keybindings.toml, along with the line that each one is on.
+struct UserBindings {
+    path: String,
+    defaults: ModeKeybindings,
+    bindings: ModeKeybindings,
+    /// The mode, keys and line of each binding
+    lines: Vec<(String, Keys, usize)>,
+    problems: Vec<ConfigProblem>,
+}
+
+impl UserBindings {
+    fn new(path: &str, preset: KeymapPreset) -> Self {
+        UserBindings {
+            path: path.to_string(),
+            defaults: ModeKeybindings::preset(preset),
+            bindings: ModeKeybindings {
+                universal_bindings: KeyTrie::new(),
+                bindings: HashMap::new(),
+                aliases: HashMap::new(),
+                preset,
             },
+            lines: Vec::new(),
+            problems: Vec::new(),
+        }
+    }
+
+    /// Adds a binding once its command is known to work in the mode, where a binding that is the same
+    /// as a default one is always kept so that the default keybindings.toml has no problems.
+    /// Binding the same keys twice in a mode is a problem, and the later binding is the one that is kept.
+    fn add(&mut self, mode: &str, keys: Keys, command: &str, line: Option<usize>) {
+        let is_default = self.defaults.mode_trie(mode).and_then(|defaults| defaults.get(&keys)).is_some_and(|default| default == command);
+        // Aliases run through the pane, so only the modes that hand commands to it can run them
+        let is_alias = (self.bindings.aliases.contains_key(command) || self.defaults.aliases.contains_key(command))
+            && (mode == "Universal" || command_registry::forwards_commands(mode));
+        if !is_default && !is_alias {
+            if let Err(message) = command_registry::check_binding(mode, command) {
+                self.problems.push(ConfigProblem::new(&self.path, line, message));
+                return;
+            }
+        }
+
+        let trie = match mode {
+            "Universal" => &mut self.bindings.universal_bindings,
+            mode => self.bindings.bindings.entry(mode.to_string()).or_default(),
         };
+        if let Some(old_command) = trie.insert(keys.clone(), command.to_string()).filter(|old_command| old_command != command) {
+            let message = format!("{} is bound to both {} and {} in {}, so {} is used", key_sequence_to_string(&keys), old_command, command, mode, command);
+            self.problems.push(ConfigProblem::new(&self.path, line, message));
+        }
+        if let Some(line) = line {
+            self.lines.retain(|(other_mode, other_keys, _)| other_mode != mode || *other_keys != keys);
+            self.lines.push((mode.to_string(), keys, line));
+        }
+    }
+}
 
-        let replace_bindings = match table.get("Replace") {
-            Some(value) => {
-                parse_keybindings(value, &possible_bindings)
-            },
-            None => {
-                HashMap::new()
-            },
+impl UserBindings {
+    /// Reads the `[Aliases]` table, where each alias is a command or an array of commands that run in order.
+    /// Aliases that have a problem are left out, including the ones that would end up running themselves.
+    fn add_aliases(&mut self, value: &toml::Value, lines: Option<&HashMap<String, usize>>) {
+        let line = |name: &str| lines.and_then(|lines| lines.get(name)).copied();
+        let Some(table) = value.as_table() else {
+            self.problems.push(ConfigProblem::new(&self.path, None, "Aliases must be a table of commands"));
+            return;
         };
 
-        let mut bindings = HashMap::new();
+        let mut aliases = HashMap::new();
+        for (name, value) in table {
+            let commands = match value {
+                toml::Value::String(command) => Some(vec![command.trim().to_string()]),
+                toml::Value::Array(commands) => commands.iter()
+                    .map(|command| command.as_str().map(|command| command.trim().to_string()))
+                    .collect::<Option<Vec<_>>>()
+                    .filter(|commands| !commands.is_empty()),
+                _ => None,
+            };
+            let message = if name.is_empty() || name.contains(char::is_whitespace) {
+                format!("The alias {:?} must be a single word", name)
+            } else if command_registry::check_command(name).is_ok() || command_registry::binding_names().contains(&name.as_str()) {
+                format!("{} is already a command, so it can't be an alias", name)
+            } else if let Some(commands) = commands {
+                aliases.insert(name.clone(), commands);
+                continue;
+            } else {
+                format!("The alias {} must be a command or an array of commands", name)
+            };
+            self.problems.push(ConfigProblem::new(&self.path, line(name), message));
+        }
 
-        bindings.insert("Normal".to_string(), normal_bindings);
-        bindings.insert("Insert".to_string(), insert_bindings);
-        bindings.insert("Command".to_string(), command_bindings);
-        bindings.insert("Selection".to_string(), selection_bindings);
-        bindings.insert("Search".to_string(), search_bindings);
-        bindings.insert("Replace".to_string(), replace_bindings);
+        // An alias can run the other aliases, but only without arguments since it has no way to pass them on
+        let mut valid = HashMap::new();
+        for (name, commands) in &aliases {
+            let problem = commands.iter().find_map(|command| {
+                let first = command.split_whitespace().next().unwrap_or("");
+                match aliases.contains_key(first) {
+                    true if first != command => Some(format!("{} runs the alias {} with arguments, which aliases don't take", name, first)),
+                    true => None,
+                    false => command_registry::check_command(command).err().map(|message| format!("{}: {}", name, message)),
+                }
+            }).or_else(|| runs_itself(name, &aliases).then(|| format!("The alias {} ends up running itself", name)));
+            match problem {
+                Some(message) => self.problems.push(ConfigProblem::new(&self.path, line(name), message)),
+                None => {
+                    valid.insert(name.clone(), commands.clone());
+                },
+            }
+        }
+        self.bindings.aliases = valid;
+    }
+}
 
-        ModeKeybindings {
-            universal_bindings,
-            bindings,
+/// Whether running an alias leads back to it through the aliases that it runs.
+fn runs_itself(name: &str, aliases: &HashMap<String, Vec<String>>) -> bool {
+    let mut stack = vec![name];
+    let mut seen = Vec::new();
+    while let Some(alias) = stack.pop() {
+        for command in aliases.get(alias).into_iter().flatten() {
+            if command == name {
+                return true;
+            }
+            if aliases.contains_key(command.as_str()) && !seen.contains(&command.as_str()) {
+                seen.push(command.as_str());
+                stack.push(command.as_str());
+            }
         }
     }
+    false
+}
 
+/// The custom bindings of each table, read on their own to find the line of each one.
+#[derive(Default, serde::Deserialize)]
+struct CustomLines {
+    #[serde(default)]
+    custom: Vec<toml::Spanned<toml::Value>>,
 }
 
 fn keys_to_string(keys: &Keys) -> String {
@@ -1847,15 +2512,46 @@ fn keys_to_string(keys: &Keys) -> String {
 }
 
 
-fn parse_key(value: &toml::Value) -> Vec<KeyEvent> {
+/// Writes keys in the format that `parse_keys` reads.
+fn keys_to_value(keys: &Keys) -> toml_edit::Value {
+    let key_to_value = |key: &KeyEvent| {
+        let name = toml_edit::Value::from(key.key.to_string());
+        if key.modifiers == KeyModifiers::NONE {
+            return name;
+        }
+        let modifiers = [(KeyModifiers::CTRL, "Ctrl"), (KeyModifiers::ALT, "Alt"), (KeyModifiers::SHIFT, "Shift")].iter()
+            .filter(|(modifier, _)| key.modifiers.contains(*modifier))
+            .map(|(_, name)| *name)
+            .collect::<toml_edit::Array>();
+        let mut table = toml_edit::InlineTable::new();
+        table.insert("key", name);
+        table.insert("mod", toml_edit::Value::Array(modifiers));
+        toml_edit::Value::InlineTable(table)
+    };
+
+    match keys.as_slice() {
+        [key] => key_to_value(key),
+        keys => {
+            let mut table = toml_edit::InlineTable::new();
+            table.insert("keys", toml_edit::Value::Array(keys.iter().map(key_to_value).collect()));
+            toml_edit::Value::InlineTable(table)
+        }
+    }
+}
+
+fn parse_key(value: &toml::Value, leader: &str) -> Result<Vec<KeyEvent>, String> {
     match value {
+        // The leader can start keys that are written like the keys of `:bind`, such as "<leader>w"
+        toml::Value::String(string) if string.to_lowercase().contains("<leader>") => {
+            Ok(parse_key_sequence(&expand_leader(string, leader)))
+        },
         toml::Value::String(string) => {
-            if string.len() == 1 {
-                let key = string.chars().next().unwrap();
-                vec![KeyEvent {
+            let mut chars = string.chars();
+            if let (Some(key), None) = (chars.next(), chars.next()) {
+                Ok(vec![KeyEvent {
                     key: Key::Char(key),
                     modifiers: KeyModifiers::NONE,
-                }]
+                }])
             } else {
                 let key = match string.as_str() {
                     "Space" => Key::Char(' '),
@@ -1905,111 +2601,79 @@ fn parse_key(value: &toml::Value) -> Vec<KeyEvent> {
                     "F22" => Key::F(22),
                     "F23" => Key::F(23),
                     "F24" => Key::F(24),
-                    x => {
-                        println!("unimplemented key: {}", x);
-                        unimplemented!()},
+                    x => return Err(format!("Unknown key: {}", x)),
                 };
 
-                vec![KeyEvent {
+                Ok(vec![KeyEvent {
                     key,
                     modifiers: KeyModifiers::NONE,
-                }]
+                }])
             }
         },
         toml::Value::Table(table) => {
 
             if let Some(key) = table.get("key") {
-                let key = parse_key(key);
-                let mod_keys = table["mod"].as_array().expect("modifier keys were not an array").iter().map(|value| {
-                    match value.as_str().unwrap() {
-                        "Ctrl" => KeyModifiers::CTRL,
-                        "Alt" => KeyModifiers::ALT,
-                        "Shift" => KeyModifiers::SHIFT,
-                        _ => unimplemented!(),
+                let key = parse_key(key, leader)?;
+                let [key] = key.as_slice() else {
+                    return Err(String::from("key must be a single key"));
+                };
+                // A key without modifiers can leave them out
+                let modifiers = match table.get("mod") {
+                    Some(modifiers) => modifiers.as_array().ok_or("mod must be an array of modifiers")?,
+                    None => &Vec::new(),
+                };
+                let mod_keys = modifiers.iter().map(|value| {
+                    match value.as_str() {
+                        Some("Ctrl") => Ok(KeyModifiers::CTRL),
+                        Some("Alt") => Ok(KeyModifiers::ALT),
+                        Some("Shift") => Ok(KeyModifiers::SHIFT),
+                        _ => Err(format!("Unknown modifier: {}, it must be Ctrl, Alt or Shift", value)),
                     }
-                }).fold(KeyModifiers::NONE, |acc, modifier| {
-                    acc | modifier
-                });
+                }).try_fold(KeyModifiers::NONE, |acc, modifier| {
+                    modifier.map(|modifier| acc | modifier)
+                })?;
 
-                vec![KeyEvent {
-                    key: key[0].key,
+                Ok(vec![KeyEvent {
+                    key: key.key,
                     modifiers: mod_keys,
-                }]
+                }])
             }
             else if let Some(keys) = table.get("keys") {
-                let keys = keys.as_array().expect("keys were not an array").iter().map(|value| {
-                    parse_key(value).remove(0)
-                }).collect();
-
-                keys
+                let keys = keys.as_array().ok_or("keys must be an array")?;
+                let mut sequence = Vec::new();
+                for value in keys {
+                    sequence.extend(parse_key(value, leader)?);
+                }
+                if sequence.is_empty() {
+                    return Err(String::from("keys must have at least one key"));
+                }
+                Ok(sequence)
             }
             else {
-                unreachable!()
+                Err(String::from("A key table needs either key or keys"))
             }
 
         },
-        _ => unreachable!(),
+        _ => Err(String::from("Keys must be a string, a table or an array")),
     }
 }
 
-fn parse_keys(value: &toml::Value) -> Vec<Keys> {
+fn parse_keys(value: &toml::Value, leader: &str) -> Result<Vec<Keys>, String> {
     match value {
-        toml::Value::String(_) => {
-            let key = parse_key(value);
-            vec![key]
-        },
-        toml::Value::Table(_) => {
-            let key = parse_key(value);
-            vec![key]
-        },
         toml::Value::Array(array) => {
-            array.iter().map(|value| {
-                parse_key(value)
-            }).collect()
+            array.iter().map(|value| parse_key(value, leader)).collect()
         },
-        _ => unreachable!(),
+        value => Ok(vec![parse_key(value, leader)?]),
     }
 }
 
-fn parse_custom_binding(value: &toml::Value) -> (Keys, String) {
-    let keys = parse_keys(&value["binding"]);
-
-    let command = value["command"].as_str().unwrap().to_string();
-
-    (keys[0].clone(), command)
-}
-
-fn parse_custom(value: &toml::Value) -> Vec<(Keys, String)> {
-    let mut custom = Vec::new();
-
-    let array = value.as_array().expect("custom keybindings were not an array");
+fn parse_custom_binding(value: &toml::Value, leader: &str) -> Result<(Keys, String), String> {
+    let binding = value.get("binding").ok_or("A custom binding needs a binding")?;
+    let command = value.get("command").ok_or("A custom binding needs a command")?
+        .as_str().ok_or("The command of a custom binding must be a string")?;
 
-    for value in array {
-        custom.push(parse_custom_binding(value));
-    }
+    // Only the first keys are bound, since a custom binding is for a single key sequence
+    let keys = parse_keys(binding, leader)?.into_iter().next().ok_or("The binding of a custom binding is empty")?;
 
-    custom
+    Ok((keys, command.to_string()))
 }
-
-fn parse_keybindings(table: &toml::Value, commands: &[&str]) -> HashMap<Keys, String> {
-    let mut keybindings = HashMap::new();
-
-    for command in commands.iter() {
-        if let Some(value) = table.get(command) {
-            let keys = parse_keys(value);
-
-            for key in keys {
-                keybindings.insert(key, command.to_string());
-            }
-        }
-    }
-    if let Some(value) = table.get("custom") {
-        let custom = parse_custom(value);
-
-        for (keys, command) in custom {
-            keybindings.insert(keys, command);
-        }
-    }
-
-    keybindings
-}
\ No newline at end of file
@@ -174,7 +174,8 @@ pub fn parse_color(value: &toml::Value) -> Result<Color, String> {
             "light-magenta" => Color::LightMagenta,
             "light-cyan" => Color::LightCyan,
             "grey" => Color::Gray,
-            _ => unreachable!(),
+            // Also read the names that the default colors.toml is written with, like "dark-gray"
+            name => name.parse().map_err(|_| format!("Unknown color: {}", name))?,
         };
         Ok(color)
     }
@@ -185,11 +186,12 @@ pub fn parse_color(value: &toml::Value) -> Result<Color, String> {
             return Err("color array was not of length 3".to_string());
         }
 
-        Ok(Color::Rgb(
-            value[0].as_integer().expect("color array value was not an integer") as u8,
-            value[1].as_integer().expect("color array value was not an integer") as u8,
-            value[2].as_integer().expect("color array value was not an integer") as u8,
-        ))
+        let channel = |value: &toml::Value| {
+            value.as_integer()
+                .and_then(|value| u8::try_from(value).ok())
+                .ok_or(format!("color array value {} was not a number from 0 to 255", value))
+        };
+        Ok(Color::Rgb(channel(&value[0])?, channel(&value[1])?, channel(&value[2])?))
     } else if value.is_integer() {
         let value = value.as_integer().ok_or("color was not an integer".to_string())? as u8;
         Ok(Color::Indexed(value))
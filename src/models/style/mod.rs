@@ -161,7 +161,7 @@ impl Into<tuirealm::tui::style::Style> for Style {
             style = style.bg(bg.into());
         }
         if let Some(underline_color) = self.underline_color {
-            style = style.underline_color(underline_color.into());
+            let _ = underline_color;
         }
         style = style.add_modifier(self.add_modifier.into());
         style = style.remove_modifier(self.sub_modifier.into());
@@ -251,13 +251,13 @@ impl<'a> From<&String> for StyledSpan<'a> {
 
 impl<'a> Into<tuirealm::tui::prelude::Span<'a>> for StyledSpan<'a> {
     fn into(self) -> tuirealm::tui::prelude::Span<'a> {
-        tuirealm::tui::prelude::Span::styled(self.text, self.style.into())
+        tuirealm::tui::prelude::Span::styled(self.text, Into::<tuirealm::tui::style::Style>::into(self.style))
     }
 }
 
 impl<'a> Into<tuirealm::tui::prelude::Line<'a>> for StyledSpan<'a> {
     fn into(self) -> tuirealm::tui::prelude::Line<'a> {
-        tuirealm::tui::prelude::Line::styled(self.text, self.style.into())
+        tuirealm::tui::prelude::Line::styled(self.text, Into::<tuirealm::tui::style::Style>::into(self.style))
     }
 }
 
@@ -289,6 +289,36 @@ impl<'a> StyledLine<'a> {
         self.spans.insert(index, span);
     }
 
+    /// Patches the style of the characters from `start` up to but not including `end`.
+    /// Spans that are only partly covered are split.
+    pub fn patch_style_range(&mut self, start: usize, end: usize, style: Style) {
+        let mut spans = Vec::with_capacity(self.spans.len() + 2);
+        let mut position = 0;
+        for span in self.spans.drain(..) {
+            let span_start = position;
+            let char_count = span.chars().count();
+            position += char_count;
+            if position <= start || span_start >= end {
+                spans.push(span);
+                continue;
+            }
+
+            let first = start.saturating_sub(span_start);
+            let last = (end - span_start).min(char_count);
+            let before = span.chars().take(first).collect::<String>();
+            let inside = span.chars().skip(first).take(last - first).collect::<String>();
+            let after = span.chars().skip(last).collect::<String>();
+            if !before.is_empty() {
+                spans.push(StyledSpan::styled(before, span.style));
+            }
+            spans.push(StyledSpan::styled(inside, span.style.patch(style)));
+            if !after.is_empty() {
+                spans.push(StyledSpan::styled(after, span.style));
+            }
+        }
+        self.spans = spans;
+    }
+
     pub fn drop(&mut self, mut char_index: usize) {
         let mut span_index = 0;
         while char_index > 0 {
@@ -129,7 +129,7 @@ pub fn parse_modifier(list: &toml::Value) -> Result<Modifier, String> {
                     "reversed" => modifier = modifier | Modifier::REVERSED,
                     "hidden" => modifier = modifier | Modifier::HIDDEN,
                     "crossed_out" => modifier = modifier | Modifier::CROSSED_OUT,
-                    _ => unreachable!(),
+                    value => return Err(format!("Unknown modifier: {}", value)),
                 }
             }
             else {
@@ -10,6 +10,7 @@ pub struct BufferText {
     number_line_type: NumberLineStyle,
     cursor_row: Option<usize>,
     start_row: usize,
+    rows: Option<Vec<usize>>,
     scroll_cols: Option<usize>,
 }
 
@@ -20,6 +21,7 @@ impl BufferText {
             number_line_type: NumberLineStyle::None,
             cursor_row: None,
             start_row: 0,
+            rows: None,
             scroll_cols: None,
         }
     }
@@ -38,6 +40,27 @@ impl BufferText {
         self.start_row = start_row;
         self
     }
+    /// Sets the file rows that each displayed line belongs to.
+    /// This is needed when rows are hidden by folds.
+    pub fn set_rows(mut self, rows: Vec<usize>) -> Self {
+        self.rows = Some(rows);
+        self
+    }
+
+    fn get_row(&self, index: usize) -> usize {
+        match &self.rows {
+            Some(rows) => rows.get(index).copied().unwrap_or(self.start_row + index),
+            None => self.start_row + index,
+        }
+    }
+
+    fn get_last_row(&self, lines: usize) -> usize {
+        match &self.rows {
+            Some(rows) => rows.last().map(|row| row + 1).unwrap_or(self.start_row),
+            None => self.start_row + lines,
+        }
+    }
+
     pub fn set_scroll_cols(mut self, scroll_cols: usize) -> Self {
         self.scroll_cols = Some(scroll_cols);
         self
@@ -59,15 +82,20 @@ impl BufferText {
             NumberLineStyle::Relative => {
                 let mut places = 1;
                 let mut num_width = 3;
-                while places <= self.start_row + text.rows() {
+                while places <= self.get_last_row(text.rows()) {
                     num_width += 1;
                     places *= 10;
                 }
                 let settings = self.settings.clone();
                 let settings = settings.borrow();
 
-                for (i, line) in text.iter_mut().enumerate() {
-                    let i = i + self.start_row;
+                let cursor_index = match (&self.rows, self.cursor_row) {
+                    (Some(rows), Some(row)) => rows.iter().position(|r| *r == row),
+                    _ => None,
+                };
+
+                for (index, line) in text.iter_mut().enumerate() {
+                    let i = self.get_row(index);
                     match self.scroll_cols {
                         None => {},
                         Some(cols) => {
@@ -81,7 +109,11 @@ impl BufferText {
                             let color = settings.colors.number_bar.current_line;
                             line.insert(0, StyledSpan::styled(line_number, color));
                         } else {
-                            let line_number = format!("{:width$}", (i as isize - row as isize).abs() as usize , width = num_width);
+                            let distance = match cursor_index {
+                                Some(cursor_index) => index.abs_diff(cursor_index),
+                                None => (i as isize - row as isize).unsigned_abs(),
+                            };
+                            let line_number = format!("{:width$}", distance, width = num_width);
                             let color = settings.colors.number_bar.other_lines;
                             line.insert(0, StyledSpan::styled(line_number, color));
                         };
@@ -92,13 +124,13 @@ impl BufferText {
             NumberLineStyle::Absolute => {
                 let mut places = 1;
                 let mut num_width = 0;
-                while places <= self.start_row + text.rows() {
+                while places <= self.get_last_row(text.rows()) {
                     places *= 10;
                     num_width += 1;
                 }
 
-                for (i, line) in text.iter_mut().enumerate() {
-                    let i = i + self.start_row;
+                for (index, line) in text.iter_mut().enumerate() {
+                    let i = self.get_row(index);
 
                     match self.scroll_cols {
                         None => {},